@@ -3,6 +3,7 @@
 //! Bundles group components for common entity types.
 
 use crate::components::*;
+use crate::resources::{DiskUsage, SymlinkInfo};
 use bevy::prelude::*;
 use std::path::PathBuf;
 use std::time::SystemTime;
@@ -24,8 +25,9 @@ impl StarBundle {
     pub fn new(
         name: String,
         path: PathBuf,
-        size_bytes: u64,
+        usage: DiskUsage,
         modified: SystemTime,
+        scale: f32,
         mesh: Handle<Mesh>,
         material: Handle<StandardMaterial>,
     ) -> Self {
@@ -33,7 +35,7 @@ impl StarBundle {
             celestial: CelestialBody {
                 name,
                 path,
-                size_bytes,
+                usage,
                 modified,
             },
             star: Star,
@@ -42,7 +44,7 @@ impl StarBundle {
             drillable: Drillable,
             mesh: Mesh3d(mesh),
             material: MeshMaterial3d(material),
-            transform: Transform::from_xyz(0.0, 0.0, 0.0),
+            transform: Transform::from_scale(Vec3::splat(scale)),
         }
     }
 }
@@ -65,7 +67,7 @@ impl DirectoryPlanetBundle {
     pub fn new(
         name: String,
         path: PathBuf,
-        size_bytes: u64,
+        usage: DiskUsage,
         modified: SystemTime,
         brightness: Brightness,
         position: Vec3,
@@ -77,7 +79,7 @@ impl DirectoryPlanetBundle {
             celestial: CelestialBody {
                 name,
                 path,
-                size_bytes,
+                usage,
                 modified,
             },
             planet: Planet { is_directory: true },
@@ -98,6 +100,7 @@ pub struct FilePlanetBundle {
     pub celestial: CelestialBody,
     pub planet: Planet,
     pub file_type: FileType,
+    pub type_source: TypeSource,
     pub brightness: Brightness,
     pub clickable: Clickable,
     pub mesh: Mesh3d,
@@ -109,9 +112,10 @@ impl FilePlanetBundle {
     pub fn new(
         name: String,
         path: PathBuf,
-        size_bytes: u64,
+        usage: DiskUsage,
         modified: SystemTime,
         file_type: FileType,
+        type_source: TypeSource,
         brightness: Brightness,
         position: Vec3,
         mesh: Handle<Mesh>,
@@ -121,11 +125,12 @@ impl FilePlanetBundle {
             celestial: CelestialBody {
                 name,
                 path,
-                size_bytes,
+                usage,
                 modified,
             },
             planet: Planet { is_directory: false },
             file_type,
+            type_source,
             brightness,
             clickable: Clickable,
             mesh: Mesh3d(mesh),
@@ -134,3 +139,55 @@ impl FilePlanetBundle {
         }
     }
 }
+
+/// Bundle for a symlink planet
+///
+/// Drillable is added separately for directory links when symlinks are followed.
+#[derive(Bundle)]
+pub struct SymlinkPlanetBundle {
+    pub celestial: CelestialBody,
+    pub planet: Planet,
+    pub file_type: FileType,
+    pub symlink: SymlinkTarget,
+    pub brightness: Brightness,
+    pub clickable: Clickable,
+    pub mesh: Mesh3d,
+    pub material: MeshMaterial3d<StandardMaterial>,
+    pub transform: Transform,
+}
+
+impl SymlinkPlanetBundle {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        name: String,
+        path: PathBuf,
+        usage: DiskUsage,
+        modified: SystemTime,
+        link: SymlinkInfo,
+        traversable: bool,
+        brightness: Brightness,
+        position: Vec3,
+        scale: f32,
+        mesh: Handle<Mesh>,
+        material: Handle<StandardMaterial>,
+    ) -> Self {
+        Self {
+            celestial: CelestialBody {
+                name,
+                path,
+                usage,
+                modified,
+            },
+            planet: Planet {
+                is_directory: traversable,
+            },
+            file_type: FileType::Symlink,
+            symlink: SymlinkTarget { link },
+            brightness,
+            clickable: Clickable,
+            mesh: Mesh3d(mesh),
+            material: MeshMaterial3d(material),
+            transform: Transform::from_translation(position).with_scale(Vec3::splat(scale)),
+        }
+    }
+}
@@ -25,3 +25,12 @@ impl Default for PulseAnimation {
 /// Marker component for entities waiting for size calculation
 #[derive(Component, Debug)]
 pub struct PendingSizeCalculation;
+
+/// Fade-in of a celestial spawned while the camera is still moving
+#[derive(Component, Debug)]
+pub struct FadeIn {
+    pub elapsed: f32,
+    pub duration: f32,
+    /// Alpha mode of the material before the fade
+    pub restore: AlphaMode,
+}
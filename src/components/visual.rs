@@ -4,41 +4,31 @@
 
 #![allow(dead_code)]
 
+use crate::resources::DiskUsage;
 use bevy::prelude::*;
+use std::collections::HashMap;
+use std::path::PathBuf;
 
 /// Brightness component based on modification time
 ///
-/// Value range: 0.25 (oldest) to 1.0 (newest)
+/// Value range: 0.25 (oldest) to 1.0 (newest) by default
 /// See visual.md for time-to-brightness mapping.
 #[derive(Component, Debug, Clone)]
 pub struct Brightness {
-    /// Brightness value (0.25 - 1.0)
+    /// Brightness value (0.25 - 1.0 by default)
     pub value: f32,
 }
 
 impl Brightness {
     /// Create brightness from age in seconds
-    pub fn from_age_seconds(age_secs: u64) -> Self {
-        const HOUR: u64 = 3600;
-        const DAY: u64 = 24 * HOUR;
-        const WEEK: u64 = 7 * DAY;
-        const MONTH: u64 = 30 * DAY;
-        const QUARTER: u64 = 90 * DAY;
-        const YEAR: u64 = 365 * DAY;
-
-        let value = if age_secs < DAY {
-            1.0       // 24 hours
-        } else if age_secs < WEEK {
-            0.85      // 1 week
-        } else if age_secs < MONTH {
-            0.70      // 1 month
-        } else if age_secs < QUARTER {
-            0.55      // 3 months
-        } else if age_secs < YEAR {
-            0.40      // 1 year
-        } else {
-            0.25      // older
-        };
+    ///
+    /// `steps` are (age limit, brightness) pairs in ascending age order;
+    /// anything older than the last limit gets `oldest`.
+    pub fn from_age_seconds(age_secs: u64, steps: &[(u64, f32)], oldest: f32) -> Self {
+        let value = steps
+            .iter()
+            .find(|(limit, _)| age_secs < *limit)
+            .map_or(oldest, |(_, value)| *value);
 
         Self { value }
     }
@@ -60,6 +50,37 @@ pub struct GrandchildRing {
     pub count: usize,
 }
 
+/// Warning marker for planets with unreadable entries
+///
+/// Their size is a lower bound; the marker mesh is a child of the planet.
+#[derive(Component, Debug, Clone)]
+pub struct ScanWarning {
+    /// Unreadable entries at or below the planet's path
+    pub count: usize,
+    /// Marker entity
+    pub marker: Entity,
+}
+
+/// Kind of highlight drawn around a celestial
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HighlightKind {
+    /// Ring facing the camera around the `Selected` celestial
+    Selection,
+    /// Faint glow around the `Hovered` celestial
+    Hover,
+}
+
+/// Highlight mesh around a celestial
+///
+/// A separate entity that follows its target rather than a child, so it is
+/// simply dropped once the target is despawned.
+#[derive(Component, Debug, Clone)]
+pub struct Highlight {
+    /// Highlighted celestial
+    pub target: Entity,
+    pub kind: HighlightKind,
+}
+
 /// Background star marker for starfield
 #[derive(Component)]
 pub struct BackgroundStar;
@@ -68,8 +89,28 @@ pub struct BackgroundStar;
 ///
 /// When there are more than 20 items, excess items
 /// are represented as an asteroid belt using particle effects.
-#[derive(Component, Debug, Clone)]
+#[derive(Component, Debug, Clone, Default)]
 pub struct AsteroidBelt {
     /// Number of items in the belt (items beyond the 20 limit)
     pub count: usize,
+    /// Combined size of the files in the belt
+    pub files: DiskUsage,
+    /// Size of each directory in the belt (None while being calculated)
+    pub directories: HashMap<PathBuf, Option<DiskUsage>>,
+}
+
+impl AsteroidBelt {
+    /// Combined known size of everything in the belt
+    pub fn usage(&self) -> DiskUsage {
+        let mut usage = self.files;
+        for size in self.directories.values().flatten() {
+            usage += *size;
+        }
+        usage
+    }
+
+    /// True while some directory in the belt has no size yet
+    pub fn is_sizing(&self) -> bool {
+        self.directories.values().any(Option::is_none)
+    }
 }
@@ -36,6 +36,14 @@ pub struct NavigateToEvent {
     pub path: PathBuf,
 }
 
+/// Go back one step in the navigation history
+#[derive(Event, Debug, Default)]
+pub struct NavigateBackEvent;
+
+/// Go forward one step in the navigation history
+#[derive(Event, Debug, Default)]
+pub struct NavigateForwardEvent;
+
 /// Request view reset to default position
 #[derive(Event, Debug, Default)]
 pub struct ViewResetEvent;
@@ -3,7 +3,7 @@
 //! Represents file systems as explorable universe spaces.
 
 use bevy::prelude::*;
-use bevy_egui::EguiPlugin;
+use bevy_egui::{EguiPlugin, EguiSet};
 use bevy_panorbit_camera::PanOrbitCameraPlugin;
 
 mod bundles;
@@ -36,16 +36,28 @@ fn main() {
         .init_resource::<CurrentDirectory>()
         .init_resource::<Breadcrumb>()
         .init_resource::<NavigationHistory>()
+        .init_resource::<RecentFolders>()
         .init_resource::<DirectoryCache>()
+        .init_resource::<DirectoryLoad>()
+        .init_resource::<TreeIndex>()
+        .init_resource::<TreeIndexTask>()
+        .init_resource::<ScanErrors>()
+        .init_resource::<CacheStatus>()
+        .init_resource::<FsWatcher>()
         .init_resource::<UiState>()
         .init_resource::<UiLayout>()
         .init_resource::<SidebarSettings>()
+        .init_resource::<ScanRules>()
         .init_resource::<VisualConfig>()
         .init_resource::<CameraConfig>()
+        .init_resource::<SettingsStore>()
+        .init_resource::<EncodingFile>()
         .init_resource::<PendingFolderSelection>()
         .init_resource::<FileDialogTask>()
+        .init_resource::<CacheManagement>()
         .init_resource::<interaction::ClickState>()
         .init_resource::<SizeCalculationChannel>()
+        .init_resource::<SizeJobQueue>()
         // States
         .init_state::<AppState>()
         .add_sub_state::<ViewingMode>()
@@ -55,12 +67,40 @@ fn main() {
         .add_event::<DrillUpEvent>()
         .add_event::<SelectionChangedEvent>()
         .add_event::<NavigateToEvent>()
+        .add_event::<NavigateBackEvent>()
+        .add_event::<NavigateForwardEvent>()
         .add_event::<ViewResetEvent>()
         .add_event::<RespawnCelestialsEvent>()
         // Startup systems
-        .add_systems(Startup, (setup_theme, setup_fonts, initialize_persistent_cache))
+        .add_systems(
+            Startup,
+            (
+                setup_theme,
+                setup_fonts,
+                load_user_settings.after(setup_theme),
+                load_visual_encoding,
+                // Cache validation comes from the loaded settings
+                initialize_persistent_cache.after(load_user_settings),
+            ),
+        )
         // Global systems (run in all states)
-        .add_systems(Update, (update_camera_viewport, sync_main_view_camera, handle_keyboard))
+        .add_systems(
+            Update,
+            (
+                update_camera_viewport,
+                sync_main_view_camera,
+                handle_keyboard,
+                apply_scan_rules,
+                apply_cache_validation,
+                sync_persistent_cache,
+                run_cache_maintenance,
+                apply_size_mode,
+                apply_sniff_setting,
+                reload_visual_encoding,
+                dispatch_size_jobs,
+                save_user_settings,
+            ),
+        )
         // State: Empty
         .add_systems(OnEnter(AppState::Empty), (setup_camera, spawn_starfield))
         .add_systems(
@@ -68,27 +108,92 @@ fn main() {
             (render_startup_ui, poll_file_dialog, check_folder_selection)
                 .run_if(in_state(AppState::Empty)),
         )
+        // State: Loading
+        .add_systems(
+            Update,
+            (
+                render_startup_ui,
+                poll_file_dialog,
+                check_folder_selection,
+                finish_loading,
+                render_loading_indicator,
+            )
+                .run_if(in_state(AppState::Loading)),
+        )
+        // Keyboard navigation keys bypass egui's widget focus (Viewing)
+        .add_systems(
+            PreUpdate,
+            reserve_navigation_keys
+                .after(EguiSet::ProcessInput)
+                .before(EguiSet::BeginPass)
+                .run_if(in_state(AppState::Viewing)),
+        )
+        // Directory reads (Loading + Viewing)
+        .add_systems(
+            Update,
+            poll_directory_load
+                .run_if(in_state(AppState::Loading).or(in_state(AppState::Viewing))),
+        )
         // State: Viewing
-        .add_systems(OnEnter(AppState::Viewing), spawn_celestials)
         .add_systems(OnExit(AppState::Viewing), cleanup_viewing)
         .add_systems(
             Update,
             (
                 update_hover,
+                // Selection forgets the first click of a double-click
                 handle_selection,
-                handle_drilldown,
+                handle_double_click.before(handle_selection),
+                handle_keyboard_navigation,
+                handle_drilldown
+                    .after(handle_double_click)
+                    .after(handle_keyboard_navigation),
+                handle_drill_up,
                 handle_navigate_to,
+                handle_history_navigation,
                 handle_respawn_celestials,
                 update_celestial_sizes,
+                poll_tree_index,
+                derive_star_size
+                    .after(update_celestial_sizes)
+                    .after(poll_tree_index),
+                mark_scan_warnings,
                 animate_pulse,
+                apply_subtree_brightness,
+                start_content_sniffing,
+                apply_content_sniffing,
+                poll_file_dialog,
+                check_folder_selection,
+            )
+                .run_if(in_state(AppState::Viewing)),
+        )
+        // Overlays (Viewing)
+        .add_systems(
+            Update,
+            (
                 render_breadcrumb,
                 render_sidebar,
                 render_tooltip,
-                poll_file_dialog,
-                check_folder_selection,
+                render_loading_indicator,
             )
                 .run_if(in_state(AppState::Viewing)),
         )
+        // Hover and selection highlights (Viewing)
+        .add_systems(
+            Update,
+            (sync_highlight_markers, update_highlights)
+                .chain()
+                .after(update_hover)
+                .after(handle_selection)
+                .after(handle_keyboard_navigation)
+                .run_if(in_state(AppState::Viewing)),
+        )
+        // Filesystem watching (Viewing)
+        .add_systems(
+            Update,
+            (sync_fs_watches, handle_fs_changes, apply_fs_refresh)
+                .chain()
+                .run_if(in_state(AppState::Viewing)),
+        )
         // SubState: Animating
         .add_systems(
             Update,
@@ -98,5 +203,16 @@ fn main() {
             Update,
             handle_view_reset.run_if(in_state(ViewingMode::Idle)),
         )
+        // Spawning (Viewing; waits out animations that don't reveal the new system)
+        .add_systems(
+            Update,
+            (
+                // A listing requested by a new folder selection belongs to Loading
+                spawn_celestials.before(check_folder_selection),
+                start_fade_in.after(spawn_celestials),
+                animate_fade_in,
+            )
+                .run_if(in_state(AppState::Viewing)),
+        )
         .run();
 }
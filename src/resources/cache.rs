@@ -4,10 +4,172 @@
 
 #![allow(dead_code)]
 
+use crate::resources::SizeMode;
 use bevy::prelude::*;
 use std::collections::HashMap;
-use std::path::PathBuf;
-use std::time::{Duration, Instant, SystemTime};
+use std::fs::Metadata;
+use std::fs;
+use std::ops::AddAssign;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Size of a file or subtree, both logical and on disk
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiskUsage {
+    /// Sum of file lengths
+    pub apparent: u64,
+    /// Sum of allocated blocks in bytes (sparse files are smaller, tiny files larger)
+    pub allocated: u64,
+}
+
+impl DiskUsage {
+    /// Usage of a single file (directories themselves count as 0)
+    pub fn from_metadata(metadata: &Metadata) -> Self {
+        if !metadata.is_file() {
+            return Self::default();
+        }
+        Self {
+            apparent: metadata.len(),
+            allocated: allocated_bytes(metadata),
+        }
+    }
+
+    /// Size used for scale and display in `mode`
+    pub fn get(&self, mode: SizeMode) -> u64 {
+        match mode {
+            SizeMode::Apparent | SizeMode::Both => self.apparent,
+            SizeMode::Allocated => self.allocated,
+        }
+    }
+}
+
+impl AddAssign for DiskUsage {
+    fn add_assign(&mut self, other: Self) {
+        self.apparent += other.apparent;
+        self.allocated += other.allocated;
+    }
+}
+
+#[cfg(unix)]
+fn allocated_bytes(metadata: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    // st_blocks is always in 512-byte units, regardless of the filesystem block size
+    metadata.blocks() * 512
+}
+
+#[cfg(not(unix))]
+fn allocated_bytes(metadata: &Metadata) -> u64 {
+    metadata.len()
+}
+
+/// Recursive counts of a subtree, collected alongside its size
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubtreeStats {
+    /// Files anywhere below (extra hardlinks included)
+    pub files: u64,
+    /// Directories anywhere below (excluding the root)
+    pub dirs: u64,
+    /// Largest file by apparent size
+    pub largest_file: Option<(PathBuf, u64)>,
+    /// Newest modification time anywhere in the subtree (root included)
+    pub newest_modified: SystemTime,
+}
+
+impl Default for SubtreeStats {
+    fn default() -> Self {
+        Self {
+            files: 0,
+            dirs: 0,
+            largest_file: None,
+            newest_modified: UNIX_EPOCH,
+        }
+    }
+}
+
+impl SubtreeStats {
+    /// Count an entry below the root (directories and files; others only touch the mtime)
+    pub fn add_entry(&mut self, path: &Path, metadata: &Metadata) {
+        if metadata.is_dir() {
+            self.dirs += 1;
+        } else if metadata.is_file() {
+            self.files += 1;
+            let len = metadata.len();
+            if self.largest_file.as_ref().is_none_or(|(_, largest)| len > *largest) {
+                self.largest_file = Some((path.to_path_buf(), len));
+            }
+        }
+        self.touch(metadata);
+    }
+
+    /// Take the entry's mtime into account without counting it (the root itself)
+    pub fn touch(&mut self, metadata: &Metadata) {
+        if let Ok(modified) = metadata.modified() {
+            self.newest_modified = self.newest_modified.max(modified);
+        }
+    }
+
+    /// Fold in a child subtree's stats
+    pub fn merge(&mut self, other: &Self) {
+        self.files += other.files;
+        self.dirs += other.dirs;
+        self.newest_modified = self.newest_modified.max(other.newest_modified);
+        if let Some((path, len)) = &other.largest_file
+            && self.largest_file.as_ref().is_none_or(|(_, largest)| len > largest)
+        {
+            self.largest_file = Some((path.clone(), *len));
+        }
+    }
+}
+
+/// Where a symlink points
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymlinkInfo {
+    /// Link target as stored in the link (may be relative)
+    pub target: PathBuf,
+    /// Target doesn't exist (or can't be accessed)
+    pub broken: bool,
+    /// Target is a directory
+    pub target_is_dir: bool,
+    /// Target is the link's own directory or one of its ancestors
+    pub loops: bool,
+}
+
+impl SymlinkInfo {
+    /// Inspect the symlink at `path` (None if it isn't one)
+    pub fn read(path: &Path) -> Option<Self> {
+        let target = fs::read_link(path).ok()?;
+        let Ok(metadata) = fs::metadata(path) else {
+            return Some(Self {
+                target,
+                broken: true,
+                target_is_dir: false,
+                loops: false,
+            });
+        };
+
+        let target_is_dir = metadata.is_dir();
+        let loops = target_is_dir
+            && match (
+                fs::canonicalize(path),
+                path.parent().map(fs::canonicalize),
+            ) {
+                (Ok(resolved), Some(Ok(parent))) => parent.starts_with(resolved),
+                _ => false,
+            };
+
+        Some(Self {
+            target,
+            broken: false,
+            target_is_dir,
+            loops,
+        })
+    }
+
+    /// True if the link can be drilled into when symlinks are followed
+    pub fn is_traversable(&self) -> bool {
+        self.target_is_dir && !self.broken && !self.loops
+    }
+}
 
 /// Single file entry from filesystem
 #[derive(Debug, Clone)]
@@ -16,12 +178,26 @@ pub struct FileEntry {
     pub name: String,
     /// Full path
     pub path: PathBuf,
-    /// Size in bytes
-    pub size_bytes: u64,
+    /// Size (0 for directories until calculated)
+    pub usage: DiskUsage,
     /// Last modification time
     pub modified: SystemTime,
-    /// True if directory
+    /// True if directory (false for symlinks, whatever they point to)
     pub is_directory: bool,
+    /// Number of direct children (directories only, None if not counted)
+    pub child_count: Option<usize>,
+    /// Link details if this entry is a symlink
+    pub symlink: Option<SymlinkInfo>,
+}
+
+impl FileEntry {
+    /// True for directories, and for directory links when symlinks are followed
+    pub fn is_drillable(&self, follow_symlinks: bool) -> bool {
+        match &self.symlink {
+            Some(link) => follow_symlinks && link.is_traversable(),
+            None => self.is_directory,
+        }
+    }
 }
 
 /// Cached directory contents
@@ -29,6 +205,8 @@ pub struct FileEntry {
 pub struct CacheEntry {
     /// Directory entries
     pub entries: Vec<FileEntry>,
+    /// Modification time of the directory itself
+    pub modified: SystemTime,
     /// When this entry was cached
     pub timestamp: Instant,
 }
@@ -65,8 +243,8 @@ impl Default for DirectoryCache {
 }
 
 impl DirectoryCache {
-    /// Get cached directory contents if valid
-    pub fn get(&mut self, path: &PathBuf) -> Option<Vec<FileEntry>> {
+    /// Get cached directory contents (and the directory's mtime) if valid
+    pub fn get(&mut self, path: &PathBuf) -> Option<(Vec<FileEntry>, SystemTime)> {
         let ttl = self.ttl;
 
         // Check if exists and not expired
@@ -75,7 +253,7 @@ impl DirectoryCache {
                 // Update access order
                 self.access_order.retain(|p| p != path);
                 self.access_order.push(path.clone());
-                return Some(entry.entries.clone());
+                return Some((entry.entries.clone(), entry.modified));
             }
         }
 
@@ -89,7 +267,7 @@ impl DirectoryCache {
     }
 
     /// Insert directory contents into cache
-    pub fn insert(&mut self, path: PathBuf, entries: Vec<FileEntry>) {
+    pub fn insert(&mut self, path: PathBuf, entries: Vec<FileEntry>, modified: SystemTime) {
         // Evict oldest if at capacity
         while self.cache.len() >= self.max_size {
             if let Some(oldest) = self.access_order.first().cloned() {
@@ -105,6 +283,7 @@ impl DirectoryCache {
             path.clone(),
             CacheEntry {
                 entries,
+                modified,
                 timestamp: Instant::now(),
             },
         );
@@ -4,8 +4,22 @@
 
 #![allow(dead_code)]
 
+use crate::components::FileType;
+use crate::resources::FileCategories;
 use bevy::prelude::*;
 
+/// Which size drives planet scale and the displayed numbers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SizeMode {
+    /// Logical file length (`metadata.len()`)
+    Apparent,
+    /// Blocks allocated on disk (`st_blocks * 512`)
+    Allocated,
+    /// Scale by apparent size, show both
+    #[default]
+    Both,
+}
+
 /// Visual encoding configuration
 #[derive(Resource, Debug)]
 pub struct VisualConfig {
@@ -15,10 +29,28 @@ pub struct VisualConfig {
     /// Size scale range for files
     pub file_size_min: f32,
     pub file_size_max: f32,
-    /// Fixed size for the star (current folder)
+    /// Base radius of the star (current folder)
     pub star_size: f32,
+    /// Star scale at the largest total size (1.0 at the smallest)
+    pub star_scale_max: f32,
     /// Maximum items before asteroid belt
     pub max_display_items: usize,
+    /// Distance of the planets from the star
+    pub orbit_radius: f32,
+    /// Size bands as (bytes, normalized size), ascending in both
+    pub size_bands: Vec<(u64, f32)>,
+    /// Brightness steps as (age limit in seconds, brightness), ascending by age
+    pub brightness_steps: Vec<(u64, f32)>,
+    /// Brightness of anything older than the last step
+    pub brightness_oldest: f32,
+    /// Directory planet color
+    pub directory_color: Color,
+    /// Symlink planet color
+    pub symlink_color: Color,
+    /// File categories (colors, extensions, globs)
+    pub categories: FileCategories,
+    /// Apparent vs. allocated size
+    pub size_mode: SizeMode,
 }
 
 impl Default for VisualConfig {
@@ -29,11 +61,54 @@ impl Default for VisualConfig {
             file_size_min: 0.3,
             file_size_max: 1.8,
             star_size: 2.5,
+            star_scale_max: 1.6,
             max_display_items: 20,
+            orbit_radius: 8.0,
+            size_bands: vec![
+                (1, 0.00),                 // < 1 KB
+                (1_000, 0.10),             // 1 KB
+                (1_000_000, 0.35),         // 1 MB
+                (1_000_000_000, 0.70),     // 1 GB
+                (1_000_000_000_000, 1.00), // 1 TB
+            ],
+            brightness_steps: vec![
+                (DAY, 1.0),
+                (7 * DAY, 0.85),
+                (30 * DAY, 0.70),
+                (90 * DAY, 0.55),
+                (365 * DAY, 0.40),
+            ],
+            brightness_oldest: 0.25,
+            directory_color: Color::WHITE,
+            symlink_color: Color::srgb_u8(0xd9, 0x46, 0xef), // Fuchsia #d946ef
+            categories: FileCategories::default(),
+            size_mode: SizeMode::default(),
+        }
+    }
+}
+
+impl VisualConfig {
+    /// Planet color for a file type
+    pub fn color(&self, file_type: FileType) -> Color {
+        match file_type {
+            FileType::File(category) => self.categories.get(category).color,
+            FileType::Symlink => self.symlink_color,
+            FileType::Directory => self.directory_color,
+        }
+    }
+
+    /// Display name for a file type
+    pub fn type_name(&self, file_type: FileType) -> &str {
+        match file_type {
+            FileType::File(category) => &self.categories.get(category).name,
+            FileType::Symlink => "Symlink",
+            FileType::Directory => "Folder",
         }
     }
 }
 
+const DAY: u64 = 24 * 3600;
+
 /// Theme color palette
 #[derive(Debug, Clone)]
 pub struct ThemeColors {
@@ -113,6 +188,8 @@ pub struct CameraConfig {
     pub zoom_min: f32,
     /// Maximum zoom distance
     pub zoom_max: f32,
+    /// Distance of the overview of a system (start, reset, drill-up)
+    pub view_radius: f32,
     /// Pitch limit in degrees (prevents gimbal lock)
     pub pitch_limit: f32,
     /// Drilldown animation duration in seconds
@@ -128,6 +205,7 @@ impl Default for CameraConfig {
         Self {
             zoom_min: 5.0,
             zoom_max: 100.0,
+            view_radius: 20.0,
             pitch_limit: 80.0,
             drilldown_duration: 0.8,
             return_duration: 0.6,
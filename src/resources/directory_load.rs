@@ -0,0 +1,112 @@
+//! Directory load resources
+//!
+//! Track the background read of the current directory's listing.
+//! Spawning waits for the listing; a new request cancels the old one.
+
+use crate::resources::{CachedSizes, FileEntry, ScanError};
+use bevy::prelude::*;
+use bevy::tasks::Task;
+use futures_lite::future;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// Listing of a single directory, ready to spawn
+#[derive(Debug, Clone)]
+pub struct DirectoryListing {
+    /// Directory the listing belongs to
+    pub path: PathBuf,
+    /// Sorted entries (directories first)
+    pub entries: Vec<FileEntry>,
+    /// Modification time of the directory itself (shown on the star)
+    pub modified: SystemTime,
+    /// Entries that couldn't be read (only for fresh reads)
+    pub errors: Vec<ScanError>,
+    /// Persisted sizes of the directory and its subdirectories, validated off the main thread
+    pub cached: CachedSizes,
+}
+
+/// Progress shared with the reading task
+#[derive(Debug, Default)]
+pub struct LoadProgress {
+    entries_read: AtomicUsize,
+    cancelled: AtomicBool,
+}
+
+impl LoadProgress {
+    /// Count one more entry read
+    pub fn tick(&self) {
+        self.entries_read.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Entries read so far
+    pub fn entries_read(&self) -> usize {
+        self.entries_read.load(Ordering::Relaxed)
+    }
+
+    /// True once the read was superseded by another request
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Background listing read for the current directory
+#[derive(Resource, Default)]
+pub struct DirectoryLoad {
+    /// Directory being loaded (None when idle)
+    pub path: Option<PathBuf>,
+    /// Running read (None if idle or served from index/cache)
+    pub task: Option<Task<Option<DirectoryListing>>>,
+    /// Progress of the running read
+    pub progress: Arc<LoadProgress>,
+    /// Listing waiting to be spawned
+    pub ready: Option<DirectoryListing>,
+}
+
+impl DirectoryLoad {
+    /// Start a new request for `path`, cancelling any read in progress
+    pub fn begin(&mut self, path: PathBuf) -> Arc<LoadProgress> {
+        self.cancel();
+        self.path = Some(path);
+        self.progress = Arc::new(LoadProgress::default());
+        Arc::clone(&self.progress)
+    }
+
+    /// Complete the request without a background read
+    pub fn finish(&mut self, listing: DirectoryListing) {
+        self.task = None;
+        self.ready = Some(listing);
+    }
+
+    /// Cancel any read in progress and drop pending results
+    pub fn cancel(&mut self) {
+        self.progress.cancelled.store(true, Ordering::Relaxed);
+        self.task = None;
+        self.ready = None;
+        self.path = None;
+    }
+
+    /// True while a background read is running
+    pub fn is_loading(&self) -> bool {
+        self.task.is_some()
+    }
+
+    /// Poll the running read (non-blocking), returning a freshly read listing
+    pub fn poll(&mut self) -> Option<DirectoryListing> {
+        let task = self.task.as_mut()?;
+        let result = future::block_on(future::poll_once(task))?;
+        self.task = None;
+        result
+    }
+
+    /// Take the ready listing if it belongs to `path`
+    pub fn take_ready(&mut self, path: &Path) -> Option<DirectoryListing> {
+        if self.ready.as_ref().is_some_and(|l| l.path == path) {
+            self.path = None;
+            self.ready.take()
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,422 @@
+//! Visual encoding file
+//!
+//! Size ranges, size bands, brightness steps, orbit radius, file
+//! categories and colors can be tuned in `closm-probe.toml` (working directory
+//! first, then the platform config directory). The file is watched and
+//! re-applied on top of the built-in defaults whenever it changes. If its
+//! directory doesn't exist yet, the nearest existing ancestor is watched
+//! until it does.
+
+use crate::resources::user_settings::Section;
+use crate::resources::{FileCategories, FileCategory, VisualConfig, OTHER_CATEGORY};
+use bevy::color::Srgba;
+use bevy::prelude::*;
+use crossbeam_channel::{unbounded, Receiver};
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use toml_edit::{DocumentMut, Item};
+
+/// Name of the visual encoding file
+pub const ENCODING_FILE_NAME: &str = "closm-probe.toml";
+
+/// Quiet period before a changed file is re-read (editors save in several steps)
+const SETTLE_DELAY: Duration = Duration::from_millis(200);
+
+const DAY_SECS: f64 = 24.0 * 3600.0;
+
+/// Location of `closm-probe.toml` and the watch on its directory
+#[derive(Resource)]
+pub struct EncodingFile {
+    /// The file (None without a working or config directory)
+    path: Option<PathBuf>,
+    /// Watcher for the file's directory (None if it couldn't be created)
+    watcher: Option<RecommendedWatcher>,
+    /// Directory being watched: the file's, or its nearest existing ancestor
+    watched: Option<PathBuf>,
+    receiver: Receiver<notify::Result<Event>>,
+    /// Arrival of the latest change not applied yet
+    changed_at: Option<Instant>,
+}
+
+impl Default for EncodingFile {
+    fn default() -> Self {
+        let local = std::env::current_dir()
+            .ok()
+            .map(|dir| dir.join(ENCODING_FILE_NAME))
+            .filter(|path| path.is_file());
+        let path = local.or_else(|| {
+            directories::ProjectDirs::from("", "", "closm-probe")
+                .map(|dirs| dirs.config_dir().join(ENCODING_FILE_NAME))
+        });
+        Self::watching(path)
+    }
+}
+
+impl EncodingFile {
+    /// Encoding file at `path`, watched for changes
+    fn watching(path: Option<PathBuf>) -> Self {
+        let (sender, receiver) = unbounded();
+        let watcher = path.as_ref().and_then(|_| {
+            notify::recommended_watcher(move |event| {
+                let _ = sender.send(event);
+            })
+            .map_err(|e| warn!("Not watching the visual encoding file: {}", e))
+            .ok()
+        });
+
+        let mut file = Self {
+            path,
+            watcher,
+            watched: None,
+            receiver,
+            changed_at: None,
+        };
+        file.update_watch();
+        file
+    }
+
+    /// Location of the encoding file
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Reset the encoding to the defaults and apply the file on top.
+    /// Ok(false) if there is no file; on error the encoding is left as is.
+    pub fn load(&self, visual: &mut VisualConfig) -> Result<bool, String> {
+        let text = match &self.path {
+            Some(path) => match std::fs::read_to_string(path) {
+                Ok(text) => Some(text),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+                Err(e) => return Err(format!("can't read {}: {}", path.display(), e)),
+            },
+            None => None,
+        };
+
+        // The size mode is a user setting, not part of the encoding
+        let mut loaded = VisualConfig {
+            size_mode: visual.size_mode,
+            ..default()
+        };
+        if let (Some(text), Some(path)) = (&text, &self.path) {
+            let doc: DocumentMut = text
+                .parse()
+                .map_err(|e| format!("{} is not valid TOML: {}", path.display(), e))?;
+            apply(&doc, &mut loaded);
+        }
+        *visual = loaded;
+        Ok(text.is_some())
+    }
+
+    /// True once the file changed and has been quiet for a moment
+    pub fn poll_changed(&mut self) -> bool {
+        let Some(path) = &self.path else {
+            return false;
+        };
+        let mut any_event = false;
+        while let Ok(event) = self.receiver.try_recv() {
+            any_event = true;
+            if let Ok(event) = event
+                && event.paths.iter().any(|changed| changed == path)
+            {
+                self.changed_at = Some(Instant::now());
+            }
+        }
+        // Directories on the way to the file may have been created or removed
+        if any_event {
+            self.update_watch();
+        }
+        if self.watched.is_none() {
+            return false;
+        }
+        match self.changed_at {
+            Some(at) if at.elapsed() >= SETTLE_DELAY => {
+                self.changed_at = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Watch the file's directory, or its nearest existing ancestor while
+    /// the directory is missing, moving the watch when that changes
+    fn update_watch(&mut self) {
+        let (Some(watcher), Some(path)) = (&mut self.watcher, &self.path) else {
+            return;
+        };
+        let Some(dir) = path.parent() else {
+            return;
+        };
+        let before = self.watched.clone();
+        // Again after each move: deeper directories may appear before the new
+        // watch is in place, and their creation would go unnoticed
+        loop {
+            let nearest = dir.ancestors().find(|ancestor| ancestor.is_dir());
+            if nearest == self.watched.as_deref() {
+                break;
+            }
+            if let Some(old) = self.watched.take() {
+                // Fails harmlessly if the directory is already gone
+                let _ = watcher.unwatch(&old);
+            }
+            let Some(nearest) = nearest else {
+                warn!(
+                    "Not watching {} for encoding changes: no existing ancestor",
+                    dir.display()
+                );
+                return;
+            };
+            // The directory, so the file may be created, replaced or removed
+            if let Err(e) = watcher.watch(nearest, RecursiveMode::NonRecursive) {
+                warn!(
+                    "Not watching {} for encoding changes: {}",
+                    nearest.display(),
+                    e
+                );
+                return;
+            }
+            self.watched = Some(nearest.to_path_buf());
+        }
+        if self.watched == before {
+            return;
+        }
+
+        match self.watched.as_deref() {
+            Some(watched) if watched != dir => info!(
+                "{} doesn't exist yet, watching {} until it does",
+                dir.display(),
+                watched.display()
+            ),
+            // The file may have been written along with its directory
+            Some(_) if before.is_some() && path.exists() => {
+                self.changed_at = Some(Instant::now());
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Read the encoding tables into `visual`
+fn apply(doc: &DocumentMut, visual: &mut VisualConfig) {
+    let s = Section::new(doc, "visual");
+    s.f32("dir_size_min", &mut visual.dir_size_min);
+    s.f32("dir_size_max", &mut visual.dir_size_max);
+    s.f32("file_size_min", &mut visual.file_size_min);
+    s.f32("file_size_max", &mut visual.file_size_max);
+    s.f32("star_size", &mut visual.star_size);
+    s.f32("star_scale_max", &mut visual.star_scale_max);
+    s.usize("max_display_items", 1..=200, &mut visual.max_display_items);
+    s.f32("orbit_radius", &mut visual.orbit_radius);
+
+    // [bytes, normalized size], bytes strictly ascending
+    if let Some(bands) = pairs(&s, "size_bands", |bands| {
+        bands.len() >= 2
+            && bands.iter().all(|(bytes, _)| *bytes >= 1.0)
+            && bands.windows(2).all(|w| w[0].0 < w[1].0)
+    }) {
+        visual.size_bands = bands
+            .into_iter()
+            .map(|(bytes, norm)| (bytes as u64, norm as f32))
+            .collect();
+    }
+
+    // [age limit in days, brightness], days strictly ascending
+    if let Some(steps) = pairs(&s, "brightness_steps", |steps| {
+        steps.iter().all(|(days, _)| *days > 0.0) && steps.windows(2).all(|w| w[0].0 < w[1].0)
+    }) {
+        visual.brightness_steps = steps
+            .into_iter()
+            .map(|(days, value)| ((days * DAY_SECS) as u64, value as f32))
+            .collect();
+    }
+    s.f32("brightness_oldest", &mut visual.brightness_oldest);
+
+    // A category named like an existing one changes it, any other is added
+    let mut categories = visual.categories.categories().to_vec();
+    if let Some(item) = doc.get("categories") {
+        match item.as_array_of_tables() {
+            Some(tables) => {
+                for table in tables.iter() {
+                    read_category(&Section::table("categories", table), &mut categories);
+                }
+            }
+            None => warn!("Ignoring invalid setting categories = {}", item.type_name()),
+        }
+    }
+
+    let s = Section::new(doc, "palette");
+    color(&s, "directory", &mut visual.directory_color);
+    color(&s, "symlink", &mut visual.symlink_color);
+    for category in &mut categories {
+        color(&s, &category.name.to_lowercase(), &mut category.color);
+    }
+    visual.categories = FileCategories::new(categories);
+}
+
+/// One `[[categories]]` entry
+fn read_category(s: &Section, categories: &mut Vec<FileCategory>) {
+    let Some(name) = s.get("name").and_then(Item::as_str) else {
+        warn!("Ignoring a category without a name");
+        return;
+    };
+    let index = match categories
+        .iter()
+        .position(|c| c.name.eq_ignore_ascii_case(name))
+    {
+        Some(index) => index,
+        None => {
+            // New categories start out in the color of "Other"
+            let color = categories
+                .iter()
+                .find(|c| c.name == OTHER_CATEGORY)
+                .map_or(Color::WHITE, |c| c.color);
+            categories.push(FileCategory {
+                name: name.to_string(),
+                color,
+                extensions: Vec::new(),
+                globs: Vec::new(),
+            });
+            categories.len() - 1
+        }
+    };
+    let category = &mut categories[index];
+    color(s, "color", &mut category.color);
+    s.strings("extensions", &mut category.extensions);
+    s.strings("globs", &mut category.globs);
+}
+
+/// `"#rrggbb"` color
+fn color(s: &Section, key: &str, target: &mut Color) {
+    let Some(item) = s.get(key) else {
+        return;
+    };
+    match item.as_str().and_then(|hex| Srgba::hex(hex).ok()) {
+        Some(color) => *target = color.into(),
+        None => s.invalid(key, item),
+    }
+}
+
+/// Array of two-number arrays, or None (with a warning) if it isn't one
+/// or fails `valid`
+fn pairs(s: &Section, key: &str, valid: impl Fn(&[(f64, f64)]) -> bool) -> Option<Vec<(f64, f64)>> {
+    let item = s.get(key)?;
+    let number = |value: &toml_edit::Value| {
+        value
+            .as_float()
+            .or_else(|| value.as_integer().map(|v| v as f64))
+            .filter(|v| v.is_finite())
+    };
+    let values: Option<Vec<(f64, f64)>> = item.as_array().and_then(|array| {
+        array
+            .iter()
+            .map(|pair| {
+                let pair = pair.as_array().filter(|pair| pair.len() == 2)?;
+                Some((number(pair.get(0)?)?, number(pair.get(1)?)?))
+            })
+            .collect()
+    });
+    match values {
+        Some(values) if valid(&values) => Some(values),
+        _ => {
+            s.invalid(key, item);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::{Brightness, FileType};
+
+    #[test]
+    fn test_watches_nearest_ancestor_until_directory_exists() {
+        let dir = crate::utils::test_dir::TestDir::new("encoding_watch");
+        let config_dir = dir.join("config/closm-probe");
+        let path = config_dir.join(ENCODING_FILE_NAME);
+        let mut file = EncodingFile::watching(Some(path.clone()));
+        assert_eq!(file.watched.as_deref(), Some(dir.path()));
+
+        std::fs::create_dir_all(&config_dir).unwrap();
+        std::fs::write(&path, "[orbit]\n").unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !file.poll_changed() {
+            assert!(Instant::now() < deadline, "change not seen");
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert_eq!(file.watched.as_deref(), Some(config_dir.as_path()));
+    }
+
+    fn load(text: &str) -> VisualConfig {
+        let mut visual = VisualConfig::default();
+        let doc = text.parse().unwrap();
+        apply(&doc, &mut visual);
+        visual
+    }
+
+    #[test]
+    fn test_encoding_file_overrides() {
+        let visual = load(
+            "[visual]\n\
+             star_size = 3\n\
+             orbit_radius = 12.5\n\
+             size_bands = [[1, 0.0], [1000000, 0.5], [1000000000, 1.0]]\n\
+             brightness_steps = [[0.5, 1.0], [30, 0.6]]\n\
+             brightness_oldest = 0.1\n\
+             [palette]\n\
+             code = \"#ff0000\"\n\
+             [[categories]]\n\
+             name = \"Logs\"\n\
+             color = \"#00ff00\"\n\
+             extensions = [\"log\"]\n\
+             [[categories]]\n\
+             name = \"image\"\n\
+             globs = [\"*.psd\"]\n",
+        );
+        assert_eq!(visual.star_size, 3.0);
+        assert_eq!(visual.orbit_radius, 12.5);
+        assert_eq!(
+            visual.size_bands,
+            vec![(1, 0.0), (1_000_000, 0.5), (1_000_000_000, 1.0)]
+        );
+        assert_eq!(
+            visual.brightness_steps,
+            vec![(43_200, 1.0), (2_592_000, 0.6)]
+        );
+        let file_type = |name: &str| FileType::File(visual.categories.classify(name).0);
+        assert_eq!(
+            visual.color(file_type("main.rs")),
+            Color::srgb(1.0, 0.0, 0.0)
+        );
+        assert_eq!(visual.type_name(file_type("app.log")), "Logs");
+        assert_eq!(
+            visual.color(file_type("app.log")),
+            Color::srgb(0.0, 1.0, 0.0)
+        );
+        // Extending a built-in category keeps its extensions
+        assert_eq!(visual.type_name(file_type("cover.psd")), "Image");
+        assert_eq!(visual.type_name(file_type("cover.png")), "Image");
+        assert_eq!(visual.type_name(file_type("a.unknown")), OTHER_CATEGORY);
+
+        let fresh = Brightness::from_age_seconds(3600, &visual.brightness_steps, 0.1);
+        let old = Brightness::from_age_seconds(u64::MAX, &visual.brightness_steps, 0.1);
+        assert_eq!(fresh.value, 1.0);
+        assert_eq!(old.value, 0.1);
+    }
+
+    #[test]
+    fn test_invalid_encoding_keeps_defaults() {
+        let visual = load(
+            "[visual]\n\
+             size_bands = [[1000, 0.5], [10, 1.0]]\n\
+             brightness_steps = \"weekly\"\n\
+             [palette]\n\
+             video = \"not a color\"\n",
+        );
+        let defaults = VisualConfig::default();
+        assert_eq!(visual.size_bands, defaults.size_bands);
+        assert_eq!(visual.brightness_steps, defaults.brightness_steps);
+        let video = FileType::File(visual.categories.classify("clip.mp4").0);
+        assert_eq!(visual.color(video), defaults.color(video));
+    }
+}
@@ -0,0 +1,260 @@
+//! File type categories
+//!
+//! Files are sorted into named categories by file name glob or extension.
+//! The built-in set follows visual.md; `closm-probe.toml` can replace or
+//! add categories. Files matching none of them go to "Other".
+
+use crate::components::TypeSource;
+use bevy::prelude::*;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use std::collections::HashMap;
+
+/// Name of the catch-all category
+pub const OTHER_CATEGORY: &str = "Other";
+
+/// Index of a category in `FileCategories`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CategoryId(pub u16);
+
+/// A named file category and what belongs to it
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileCategory {
+    /// Display name (tooltip, legend)
+    pub name: String,
+    /// Planet color
+    pub color: Color,
+    /// Extensions without the dot, matched case-insensitively
+    pub extensions: Vec<String>,
+    /// File name globs (e.g. `*.ckpt`, `Dockerfile`), matched case-insensitively
+    pub globs: Vec<String>,
+}
+
+impl FileCategory {
+    fn new(name: &str, color: Color, extensions: &[&str]) -> Self {
+        Self {
+            name: name.to_string(),
+            color,
+            extensions: extensions.iter().map(|e| e.to_string()).collect(),
+            globs: Vec::new(),
+        }
+    }
+
+    fn with_globs(mut self, globs: &[&str]) -> Self {
+        self.globs = globs.iter().map(|g| g.to_string()).collect();
+        self
+    }
+}
+
+/// Categories with their lookup tables; "Other" is always the last one
+#[derive(Debug, Clone)]
+pub struct FileCategories {
+    categories: Vec<FileCategory>,
+    /// Lowercase extension → category (later categories win)
+    by_extension: HashMap<String, CategoryId>,
+    globs: GlobSet,
+    /// Category of each glob in `globs`
+    glob_categories: Vec<CategoryId>,
+}
+
+impl Default for FileCategories {
+    fn default() -> Self {
+        Self::new(builtin_categories())
+    }
+}
+
+impl FileCategories {
+    /// Build the lookup tables. An "Other" entry is moved to the end, or
+    /// added if missing.
+    pub fn new(mut categories: Vec<FileCategory>) -> Self {
+        let other = match categories
+            .iter()
+            .position(|c| c.name.eq_ignore_ascii_case(OTHER_CATEGORY))
+        {
+            Some(index) => categories.remove(index),
+            None => other_category(),
+        };
+        categories.push(other);
+
+        let mut by_extension = HashMap::new();
+        let mut builder = GlobSetBuilder::new();
+        let mut glob_categories = Vec::new();
+        for (index, category) in categories.iter().enumerate() {
+            let id = CategoryId(index as u16);
+            for extension in &category.extensions {
+                let extension = extension.trim_start_matches('.').to_lowercase();
+                by_extension.insert(extension, id);
+            }
+            for pattern in &category.globs {
+                match GlobBuilder::new(pattern).case_insensitive(true).build() {
+                    Ok(glob) => {
+                        builder.add(glob);
+                        glob_categories.push(id);
+                    }
+                    Err(e) => warn!(
+                        "Ignoring invalid glob '{}' in category {}: {}",
+                        pattern, category.name, e
+                    ),
+                }
+            }
+        }
+
+        Self {
+            categories,
+            by_extension,
+            globs: builder.build().unwrap_or_else(|_| GlobSet::empty()),
+            glob_categories,
+        }
+    }
+
+    /// Category of a file name: globs first, then the extension, else "Other"
+    pub fn classify(&self, file_name: &str) -> (CategoryId, TypeSource) {
+        if let Some(index) = self.globs.matches(file_name).into_iter().max() {
+            return (self.glob_categories[index], TypeSource::Name);
+        }
+        file_name
+            .rsplit_once('.')
+            .filter(|(stem, _)| !stem.is_empty())
+            .and_then(|(_, extension)| self.by_extension.get(&extension.to_lowercase()))
+            .map_or((self.other(), TypeSource::Unmatched), |id| {
+                (*id, TypeSource::Extension)
+            })
+    }
+
+    /// Category by name (case-insensitive)
+    pub fn find(&self, name: &str) -> Option<CategoryId> {
+        self.categories
+            .iter()
+            .position(|c| c.name.eq_ignore_ascii_case(name))
+            .map(|index| CategoryId(index as u16))
+    }
+
+    /// The catch-all category
+    pub fn other(&self) -> CategoryId {
+        CategoryId((self.categories.len() - 1) as u16)
+    }
+
+    /// Category by id ("Other" for ids from an older set)
+    pub fn get(&self, id: CategoryId) -> &FileCategory {
+        self.categories
+            .get(id.0 as usize)
+            .unwrap_or_else(|| &self.categories[self.categories.len() - 1])
+    }
+
+    /// All categories, "Other" last
+    pub fn categories(&self) -> &[FileCategory] {
+        &self.categories
+    }
+}
+
+/// Built-in categories (from visual.md)
+pub fn builtin_categories() -> Vec<FileCategory> {
+    vec![
+        FileCategory::new(
+            "Code",
+            Color::srgb_u8(0x61, 0xda, 0xfb), // Cyan #61dafb
+            &[
+                "rs", "js", "ts", "py", "go", "java", "c", "cpp", "h", "hpp", "rb", "php", "swift",
+                "kt", "scala", "sh", "bash", "zsh", "html", "css", "scss", "sass", "less", "vue",
+                "jsx", "tsx",
+            ],
+        )
+        .with_globs(&[
+            "Dockerfile",
+            "Dockerfile.*",
+            "*.Dockerfile",
+            "Makefile",
+            "GNUmakefile",
+            "CMakeLists.txt",
+            "Justfile",
+            "Rakefile",
+            "Gemfile",
+        ]),
+        FileCategory::new(
+            "Image",
+            Color::srgb_u8(0xf5, 0x9e, 0x0b), // Orange #f59e0b
+            &[
+                "png", "jpg", "jpeg", "gif", "bmp", "svg", "webp", "ico", "tiff", "heic",
+            ],
+        ),
+        FileCategory::new(
+            "Video",
+            Color::srgb_u8(0xef, 0x44, 0x44), // Red #ef4444
+            &["mp4", "mov", "avi", "mkv", "webm", "flv", "wmv", "m4v"],
+        ),
+        FileCategory::new(
+            "Document",
+            Color::srgb_u8(0x3b, 0x82, 0xf6), // Blue #3b82f6
+            &[
+                "pdf", "doc", "docx", "txt", "rtf", "odt", "md", "markdown", "tex",
+            ],
+        ),
+        FileCategory::new(
+            "Data",
+            Color::srgb_u8(0x06, 0xb6, 0xd4), // Teal #06b6d4
+            &[
+                "json", "xml", "yaml", "yml", "csv", "toml", "ini", "cfg", "conf", "sql", "db",
+                "sqlite",
+            ],
+        ),
+        FileCategory::new(
+            "Archive",
+            Color::srgb_u8(0x6b, 0x72, 0x80), // Gray #6b7280
+            &["zip", "tar", "gz", "bz2", "xz", "7z", "rar", "dmg", "iso"],
+        ),
+        FileCategory::new(
+            "Executable",
+            Color::srgb_u8(0x84, 0xcc, 0x16), // Lime #84cc16
+            &["exe", "dll", "so", "dylib"],
+        ),
+        other_category(),
+    ]
+}
+
+fn other_category() -> FileCategory {
+    FileCategory::new(OTHER_CATEGORY, Color::srgb_u8(0x94, 0xa3, 0xb8), &[]) // Slate #94a3b8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_builtin_and_other() {
+        let categories = FileCategories::default();
+        let name = |file: &str| categories.get(categories.classify(file).0).name.clone();
+        assert_eq!(name("main.RS"), "Code");
+        assert_eq!(name("photo.jpeg"), "Image");
+        assert_eq!(name("notes.weird"), OTHER_CATEGORY);
+        assert_eq!(name("README"), OTHER_CATEGORY);
+        assert_eq!(name(".bashrc"), OTHER_CATEGORY);
+        assert_eq!(name("Makefile"), "Code");
+        assert_eq!(name("CMakeLists.txt"), "Code");
+        assert_eq!(categories.classify("notes.txt").1, TypeSource::Extension);
+        assert_eq!(categories.classify("Dockerfile").1, TypeSource::Name);
+        assert_eq!(categories.classify("README").1, TypeSource::Unmatched);
+    }
+
+    #[test]
+    fn test_custom_categories_take_precedence() {
+        let mut defs = builtin_categories();
+        defs.push(FileCategory {
+            name: "Logs".into(),
+            color: Color::WHITE,
+            extensions: vec!["log".into(), "txt".into()],
+            globs: Vec::new(),
+        });
+        defs.push(FileCategory {
+            name: "ML checkpoints".into(),
+            color: Color::BLACK,
+            extensions: Vec::new(),
+            globs: vec!["*.ckpt".into(), "model-*.bin".into()],
+        });
+        let categories = FileCategories::new(defs);
+        let name = |file: &str| categories.get(categories.classify(file).0).name.clone();
+        assert_eq!(name("server.log"), "Logs");
+        assert_eq!(name("readme.txt"), "Logs");
+        assert_eq!(name("epoch10.CKPT"), "ML checkpoints");
+        assert_eq!(name("model-7b.bin"), "ML checkpoints");
+        assert_eq!(categories.categories().last().unwrap().name, OTHER_CATEGORY);
+    }
+}
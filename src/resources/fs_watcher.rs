@@ -0,0 +1,154 @@
+//! Filesystem watcher resources
+//!
+//! notify watcher on the current directory and its child directories.
+//! Events are batched until the filesystem is quiet for a moment and
+//! then applied to the scene by the watching systems.
+
+#![allow(dead_code)]
+
+use crate::resources::DirectoryListing;
+use bevy::prelude::*;
+use bevy::tasks::Task;
+use crossbeam_channel::{unbounded, Receiver};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Quiet period before a batch of changes is applied
+const SETTLE_DELAY: Duration = Duration::from_millis(300);
+
+/// Longest a batch is held back while changes keep arriving (e.g. a growing log)
+const MAX_BATCH_DELAY: Duration = Duration::from_secs(1);
+
+/// Watches on the current directory and its children
+#[derive(Resource)]
+pub struct FsWatcher {
+    /// Platform watcher (None if it couldn't be created; watching is then off)
+    watcher: Option<RecommendedWatcher>,
+    receiver: Receiver<notify::Result<Event>>,
+    /// Directory the watches belong to
+    directory: Option<PathBuf>,
+    /// Watched paths (the directory and its child directories)
+    watched: HashSet<PathBuf>,
+    /// Changed paths not applied yet
+    changed: HashSet<PathBuf>,
+    /// Arrival of the first and the latest change in the batch
+    first_change: Option<Instant>,
+    last_change: Option<Instant>,
+    /// Re-read of the current directory after entries were added or removed
+    pub refresh: Option<Task<Option<DirectoryListing>>>,
+}
+
+impl Default for FsWatcher {
+    fn default() -> Self {
+        let (sender, receiver) = unbounded();
+        let watcher = match notify::recommended_watcher(move |event| {
+            let _ = sender.send(event);
+        }) {
+            Ok(watcher) => Some(watcher),
+            Err(e) => {
+                warn!("Filesystem watching unavailable: {}", e);
+                None
+            }
+        };
+        Self {
+            watcher,
+            receiver,
+            directory: None,
+            watched: HashSet::new(),
+            changed: HashSet::new(),
+            first_change: None,
+            last_change: None,
+            refresh: None,
+        }
+    }
+}
+
+impl FsWatcher {
+    /// Directory currently watched
+    pub fn directory(&self) -> Option<&Path> {
+        self.directory.as_deref()
+    }
+
+    /// Drop all watches and pending changes, then watch `directory` (None: stop watching)
+    pub fn watch_directory(&mut self, directory: Option<&Path>) {
+        for path in std::mem::take(&mut self.watched) {
+            if let Some(watcher) = self.watcher.as_mut() {
+                let _ = watcher.unwatch(&path);
+            }
+        }
+        self.changed.clear();
+        self.first_change = None;
+        self.last_change = None;
+        self.refresh = None;
+        self.directory = directory.map(Path::to_path_buf);
+        if let Some(directory) = directory {
+            self.watch(directory);
+        }
+    }
+
+    /// Watch a child directory of the current directory (non-recursive)
+    pub fn watch(&mut self, path: &Path) {
+        let Some(watcher) = self.watcher.as_mut() else {
+            return;
+        };
+        if self.watched.contains(path) {
+            return;
+        }
+        match watcher.watch(path, RecursiveMode::NonRecursive) {
+            Ok(()) => {
+                self.watched.insert(path.to_path_buf());
+            }
+            Err(e) => warn!("Failed to watch {}: {}", path.display(), e),
+        }
+    }
+
+    /// Stop watching a child directory that left the scene
+    pub fn unwatch(&mut self, path: &Path) {
+        if self.watched.remove(path)
+            && let Some(watcher) = self.watcher.as_mut()
+        {
+            let _ = watcher.unwatch(path);
+        }
+    }
+
+    /// Collect delivered events into the pending batch
+    pub fn drain(&mut self) {
+        while let Ok(event) = self.receiver.try_recv() {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    warn!("Filesystem watch error: {}", e);
+                    continue;
+                }
+            };
+            // Reads don't change sizes or listings
+            if matches!(event.kind, EventKind::Access(_)) {
+                continue;
+            }
+            let Some(directory) = self.directory.as_deref() else {
+                continue;
+            };
+            let now = Instant::now();
+            for path in event.paths {
+                if path.starts_with(directory) {
+                    self.changed.insert(path);
+                    self.first_change.get_or_insert(now);
+                    self.last_change = Some(now);
+                }
+            }
+        }
+    }
+
+    /// Take the pending batch once changes have settled (or waited long enough)
+    pub fn take_settled(&mut self) -> Option<HashSet<PathBuf>> {
+        let (first, last) = (self.first_change?, self.last_change?);
+        if last.elapsed() < SETTLE_DELAY && first.elapsed() < MAX_BATCH_DELAY {
+            return None;
+        }
+        self.first_change = None;
+        self.last_change = None;
+        Some(std::mem::take(&mut self.changed))
+    }
+}
@@ -2,12 +2,30 @@
 
 pub mod cache;
 pub mod config;
+pub mod directory_load;
+pub mod encoding_file;
+pub mod file_categories;
+pub mod fs_watcher;
 pub mod navigation;
 pub mod persistent_cache;
+pub mod scan_errors;
+pub mod scan_rules;
+#[cfg(unix)]
+pub mod shared_cache;
+pub mod tree_index;
 pub mod ui_state;
+pub mod user_settings;
 
 pub use cache::*;
 pub use config::*;
+pub use directory_load::*;
+pub use encoding_file::*;
+pub use file_categories::*;
+pub use fs_watcher::*;
 pub use navigation::*;
 pub use persistent_cache::*;
+pub use scan_errors::*;
+pub use scan_rules::*;
+pub use tree_index::*;
 pub use ui_state::*;
+pub use user_settings::*;
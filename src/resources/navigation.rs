@@ -1,6 +1,6 @@
 //! Navigation resources
 //!
-//! Track current directory and navigation history.
+//! Track current directory, recent folders and back/forward history.
 
 #![allow(dead_code)]
 
@@ -12,6 +12,8 @@ use std::path::PathBuf;
 pub struct CurrentDirectory {
     /// Active directory path (None if no folder selected)
     pub path: Option<PathBuf>,
+    /// Folder the user opened; the session's back stack is stored under it
+    pub root: Option<PathBuf>,
 }
 
 /// Breadcrumb navigation path
@@ -61,40 +63,62 @@ impl Breadcrumb {
     }
 }
 
-/// Navigation history for recent folders and back/forward navigation
+/// Recently opened folders (sidebar), newest first
 #[derive(Resource, Debug)]
-pub struct NavigationHistory {
-    /// Recent folders (for sidebar display), newest first
+pub struct RecentFolders {
     pub entries: Vec<PathBuf>,
     /// Maximum entries to keep
     pub max_entries: usize,
-    /// Paths for "back" navigation
-    pub back: Vec<PathBuf>,
-    /// Paths for "forward" navigation
-    pub forward: Vec<PathBuf>,
 }
 
-impl Default for NavigationHistory {
+impl Default for RecentFolders {
     fn default() -> Self {
         Self {
             entries: Vec::new(),
             max_entries: 10,
-            back: Vec::new(),
-            forward: Vec::new(),
         }
     }
 }
 
-impl NavigationHistory {
-    /// Push path to recent entries and back history
-    pub fn push(&mut self, path: PathBuf) {
-        // Add to recent entries (remove duplicates, newest first)
+impl RecentFolders {
+    /// Move `path` to the front (no duplicates)
+    pub fn add(&mut self, path: PathBuf) {
         self.entries.retain(|p| p != &path);
-        self.entries.insert(0, path.clone());
+        self.entries.insert(0, path);
         self.entries.truncate(self.max_entries);
+    }
 
-        // Add to back history
-        self.back.push(path);
+    /// Take in the history stored by all instances (newest first). Every
+    /// `add` is written to the cache, so entries it doesn't have yet are
+    /// newer than anything stored and stay in front.
+    pub fn merge(&mut self, stored: Vec<PathBuf>) {
+        let mut merged: Vec<PathBuf> = self
+            .entries
+            .iter()
+            .filter(|entry| !stored.contains(entry))
+            .cloned()
+            .collect();
+        merged.extend(stored);
+        merged.truncate(self.max_entries);
+        self.entries = merged;
+    }
+}
+
+/// Back/forward history of the current session
+#[derive(Resource, Debug, Default)]
+pub struct NavigationHistory {
+    /// Paths for "back" navigation, most recent last
+    pub back: Vec<PathBuf>,
+    /// Paths for "forward" navigation, most recent last
+    pub forward: Vec<PathBuf>,
+}
+
+impl NavigationHistory {
+    /// Remember the directory being left; a new branch drops the forward history
+    pub fn push(&mut self, path: PathBuf) {
+        if self.back.last() != Some(&path) {
+            self.back.push(path);
+        }
         self.forward.clear();
     }
 
@@ -118,3 +142,59 @@ impl NavigationHistory {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_back_and_forward() {
+        let path = |p: &str| PathBuf::from(p);
+        let mut history = NavigationHistory::default();
+        history.push(path("/a"));
+        history.push(path("/a/b"));
+
+        // At /a/b/c
+        assert_eq!(history.go_back(path("/a/b/c")), Some(path("/a/b")));
+        assert_eq!(history.go_back(path("/a/b")), Some(path("/a")));
+        assert_eq!(history.go_back(path("/a")), None);
+        assert_eq!(history.go_forward(path("/a")), Some(path("/a/b")));
+        assert_eq!(history.back, vec![path("/a")]);
+
+        // Navigating elsewhere drops the forward history
+        history.push(path("/a/b"));
+        assert!(history.forward.is_empty());
+        assert_eq!(history.go_forward(path("/x")), None);
+    }
+
+    #[test]
+    fn test_recent_folders_dedup() {
+        let mut recent = RecentFolders {
+            max_entries: 2,
+            ..default()
+        };
+        recent.add(PathBuf::from("/a"));
+        recent.add(PathBuf::from("/b"));
+        recent.add(PathBuf::from("/a"));
+        recent.add(PathBuf::from("/c"));
+        assert_eq!(
+            recent.entries,
+            vec![PathBuf::from("/c"), PathBuf::from("/a")]
+        );
+    }
+
+    #[test]
+    fn test_recent_folders_merge_keeps_unsaved_entries() {
+        let path = |p: &str| PathBuf::from(p);
+        let mut recent = RecentFolders {
+            max_entries: 3,
+            ..default()
+        };
+        recent.add(path("/a"));
+        recent.add(path("/b"));
+
+        // Another instance opened /x since; /b's write hasn't landed yet
+        recent.merge(vec![path("/x"), path("/a"), path("/old")]);
+        assert_eq!(recent.entries, vec![path("/b"), path("/x"), path("/a")]);
+    }
+}
@@ -2,84 +2,514 @@
 //!
 //! Two-tier cache: in-memory DirectoryCache (fast, volatile) + redb (persistent, ACID).
 //! Background writer thread handles all writes non-blocking via crossbeam channel.
+//! A second instance shares the file through the first one (see `shared_cache`).
 
+#[cfg(unix)]
+use crate::resources::shared_cache::{self, SharedCacheClient};
+use crate::resources::{DiskUsage, FileEntry, ScanRules, SidebarSettings, SubtreeStats, fnv1a};
 use bevy::prelude::*;
+use bevy::tasks::{IoTaskPool, Task};
 use crossbeam_channel::{Sender, TrySendError};
-use redb::{Database, ReadableDatabase, ReadableTable, TableDefinition};
+use redb::{
+    Database, DatabaseError, Key, ReadableDatabase, ReadableTable, ReadableTableMetadata, Table,
+    TableDefinition, TableHandle, Value, WriteTransaction,
+};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::ffi::{OsStr, OsString};
+use std::fs::Metadata;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-/// Table: path (string) -> (size_bytes: u64, timestamp_epoch_secs: u64)
-const SIZE_TABLE: TableDefinition<&str, (u64, u64)> = TableDefinition::new("sizes");
+/// Directory stamp stored with each size and stats row:
+/// (mtime_epoch_nanos, ctime_epoch_nanos, listing_fingerprint), see `DirStamp`
+pub(crate) type StampValue = (u64, u64, u64);
 
-/// Table: index (u64) -> path (string), 0 = newest
-const HISTORY_TABLE: TableDefinition<u64, &str> = TableDefinition::new("history");
+/// Table: size_key(rules_fingerprint, path) -> (apparent: u64, allocated: u64, timestamp_epoch_secs: u64, stamp)
+const SIZE_TABLE: TableDefinition<&[u8], SizeRow> = TableDefinition::new("dir_sizes_os");
+
+/// Table: size_key(rules_fingerprint, path) -> (files, dirs, largest_bytes, largest_path,
+/// newest_mtime_epoch_secs, timestamp_epoch_secs, stamp); largest_path is empty if there are no files
+const STATS_TABLE: TableDefinition<&[u8], StatsValue> = TableDefinition::new("dir_stats_os");
+
+type StatsValue = (u64, u64, u64, &'static [u8], u64, u64, StampValue);
+
+/// A `SIZE_TABLE` row as read, before validation
+pub(crate) type SizeRow = (u64, u64, u64, StampValue);
+
+/// A `STATS_TABLE` row as read, before validation (owned largest_path bytes)
+pub(crate) type StatsRow = (u64, u64, u64, Vec<u8>, u64, u64, StampValue);
+
+/// Table: size_key(rules_fingerprint, dir) -> (files_apparent, files_allocated, child_dirs,
+/// timestamp_epoch_secs, stamp); child_dirs are the names of the child directories, NUL-separated
+///
+/// What a directory's total is made of, so it can be rolled up from its children.
+const MANIFEST_TABLE: TableDefinition<&[u8], ManifestValue> =
+    TableDefinition::new("dir_manifests_os");
+
+type ManifestValue = (u64, u64, &'static [u8], u64, StampValue);
+
+/// Table: index (u64) -> path bytes, 0 = newest
+const HISTORY_TABLE: TableDefinition<u64, &[u8]> = TableDefinition::new("history_os");
+
+/// Entries kept in `HISTORY_TABLE` (the largest history limit setting)
+const HISTORY_CAPACITY: usize = 30;
+
+/// Table: opened folder path bytes -> (timestamp_epoch_secs, back stack);
+/// the back stack holds the paths oldest first, NUL-separated
+const SESSION_TABLE: TableDefinition<&[u8], (u64, &[u8])> = TableDefinition::new("sessions_os");
+
+/// Sessions kept in `SESSION_TABLE` (the least recently written go first)
+const SESSION_CAPACITY: usize = 30;
+
+/// Back stack entries kept per session
+const BACK_STACK_CAPACITY: usize = 100;
+
+/// Table: name -> value; holds "schema_version"
+const META_TABLE: TableDefinition<&str, u64> = TableDefinition::new("meta");
+
+/// Layout of the tables above. Bump it together with a new `MIGRATIONS` step
+/// whenever a table is added, removed or changes its key or value type.
+const SCHEMA_VERSION: u64 = 1;
+
+/// `MIGRATIONS[n]` upgrades schema version `n` to `n + 1`, inside the opening
+/// transaction. A changed value type (e.g. a new column) gets a new table name;
+/// the step copies rows over in the new shape and deletes the old table.
+const MIGRATIONS: [Migration; SCHEMA_VERSION as usize] = [migrate_unversioned];
+
+type Migration = fn(&WriteTransaction) -> Result<(), redb::Error>;
+
+/// Tables of unversioned caches. Their rows carry no directory stamp, so
+/// they can't be revalidated and are dropped rather than copied.
+const LEGACY_TABLES: [&str; 1] = ["sizes"];
+
+/// Version 0 (no meta table) -> 1: drop the tables replaced by stamped ones
+/// and move the history from lossy strings to raw bytes. Paths that went
+/// through a lossy conversion (U+FFFD) can't be mapped back and are dropped.
+fn migrate_unversioned(write_txn: &WriteTransaction) -> Result<(), redb::Error> {
+    const HISTORY: TableDefinition<u64, &str> = TableDefinition::new("history");
+
+    let legacy: Vec<_> = write_txn
+        .list_tables()?
+        .filter(|table| LEGACY_TABLES.contains(&table.name()))
+        .collect();
+    for table in legacy {
+        write_txn.delete_table(table)?;
+    }
+
+    {
+        let old = write_txn.open_table(HISTORY)?;
+        let mut new = write_txn.open_table(HISTORY_TABLE)?;
+        let mut index = 0;
+        for row in old.iter()? {
+            let (_, path) = row?;
+            if !path.value().contains(char::REPLACEMENT_CHARACTER) {
+                new.insert(index, path.value().as_bytes())?;
+                index += 1;
+            }
+        }
+    }
+    write_txn.delete_table(HISTORY)?;
+    Ok(())
+}
+
+/// Why the cache file couldn't be opened
+enum OpenError {
+    /// Locked by another process
+    Locked,
+    /// Corrupt, from a newer version, or otherwise not readable as a cache
+    Unusable(String),
+}
+
+/// Open (or create) the database at `path` and bring it to `SCHEMA_VERSION`
+fn open_database(path: &Path) -> Result<Database, OpenError> {
+    let db = Database::create(path).map_err(|e| match e {
+        DatabaseError::DatabaseAlreadyOpen => OpenError::Locked,
+        e => OpenError::Unusable(e.to_string()),
+    })?;
+    migrate(&db).map_err(OpenError::Unusable)?;
+    Ok(db)
+}
+
+/// Check the stored schema version and upgrade to `SCHEMA_VERSION`
+fn migrate(db: &Database) -> Result<(), String> {
+    let write_txn = db.begin_write().map_err(|e| e.to_string())?;
+    let version = stored_version(&write_txn).map_err(|e| e.to_string())?;
+    if version > SCHEMA_VERSION {
+        return Err(format!(
+            "written by a newer version (schema {}, supported {})",
+            version, SCHEMA_VERSION
+        ));
+    }
+    upgrade(&write_txn, version).map_err(|e| e.to_string())?;
+    write_txn.commit().map_err(|e| e.to_string())
+}
+
+/// Schema version of an open database (0 if unversioned or new)
+fn stored_version(write_txn: &WriteTransaction) -> Result<u64, redb::Error> {
+    let meta = write_txn.open_table(META_TABLE)?;
+    let version = meta.get("schema_version")?.map_or(0, |v| v.value());
+    Ok(version)
+}
+
+/// Run the migrations after `version`, then make sure every table exists
+fn upgrade(write_txn: &WriteTransaction, version: u64) -> Result<(), redb::Error> {
+    for (from, step) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        step(write_txn)?;
+        info!(
+            "Migrated persistent cache from schema {} to {}",
+            from,
+            from + 1
+        );
+    }
+
+    // Opening creates missing tables and checks the types of existing ones
+    write_txn.open_table(SIZE_TABLE)?;
+    write_txn.open_table(STATS_TABLE)?;
+    write_txn.open_table(MANIFEST_TABLE)?;
+    write_txn.open_table(HISTORY_TABLE)?;
+    write_txn.open_table(SESSION_TABLE)?;
+    write_txn
+        .open_table(META_TABLE)?
+        .insert("schema_version", SCHEMA_VERSION)?;
+    Ok(())
+}
+
+/// An unusable cache file that was moved aside on open
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheRecovery {
+    /// What was wrong with it
+    pub reason: String,
+    /// Where the old file was moved
+    pub backup: PathBuf,
+}
+
+/// Outcome of opening the persistent cache, reported in the sidebar
+#[derive(Resource, Debug, Clone, Default, PartialEq, Eq)]
+pub enum CacheStatus {
+    /// Opened normally (or not opened yet)
+    #[default]
+    Ready,
+    /// Unusable file replaced by a fresh cache
+    Recovered(CacheRecovery),
+    /// Running without persistence
+    Unavailable { reason: String },
+    /// Notice closed by the user
+    Dismissed,
+}
+
+/// Maintenance action on the cache file (Settings → Storage cache)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CacheMaintenance {
+    /// Only refresh the summary
+    Summarize,
+    /// Drop all sizes, stats and manifests (every scan rule set)
+    ClearSizes,
+    /// Drop the stored navigation history (recent folders and back stacks)
+    ClearHistory,
+    /// Give the space of removed rows back to the filesystem
+    Compact,
+    /// Write a copy of the cache to a new file
+    Export(PathBuf),
+    /// Merge a cache file (e.g. an export) into this one
+    Import(PathBuf),
+}
+
+/// What the cache file holds, shown in Settings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheSummary {
+    /// Location of the redb file
+    pub path: PathBuf,
+    /// File size on disk
+    pub file_bytes: u64,
+    /// Another instance owns the file: counts are unknown and
+    /// maintenance is left to it
+    pub shared: bool,
+    /// Cached directory sizes
+    pub sizes: u64,
+    /// Calculation time of the oldest cached size
+    pub oldest: Option<SystemTime>,
+    /// Calculation time of the newest cached size
+    pub newest: Option<SystemTime>,
+    /// Stored navigation history entries
+    pub history: u64,
+}
+
+impl CacheSummary {
+    /// Summarize the file at `path`; the counts need its database
+    fn read(path: &Path, db: Option<&Database>) -> Result<Self, redb::Error> {
+        let mut summary = Self {
+            path: path.to_path_buf(),
+            file_bytes: std::fs::metadata(path).map_or(0, |m| m.len()),
+            shared: db.is_none(),
+            sizes: 0,
+            oldest: None,
+            newest: None,
+            history: 0,
+        };
+        let Some(db) = db else {
+            return Ok(summary);
+        };
+
+        let read_txn = db.begin_read()?;
+        let (mut oldest, mut newest) = (u64::MAX, 0);
+        for row in read_txn.open_table(SIZE_TABLE)?.iter()? {
+            let (_, value) = row?;
+            let (_, _, timestamp, _) = value.value();
+            summary.sizes += 1;
+            oldest = oldest.min(timestamp);
+            newest = newest.max(timestamp);
+        }
+        if summary.sizes > 0 {
+            summary.oldest = Some(UNIX_EPOCH + Duration::from_secs(oldest));
+            summary.newest = Some(UNIX_EPOCH + Duration::from_secs(newest));
+        }
+        summary.history = read_txn.open_table(HISTORY_TABLE)?.len()?;
+        Ok(summary)
+    }
+}
 
 /// Commands sent to the background writer thread
-enum CacheWriteCommand {
+/// (or forwarded to the instance that owns the cache, see `shared_cache`)
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum CacheWriteCommand {
     WriteSize {
-        path: String,
-        size: u64,
+        fingerprint: u64,
+        path: PathBuf,
+        usage: DiskUsage,
+        timestamp: u64,
+        stamp: StampValue,
+        validation: CacheValidation,
+    },
+    WriteManifest {
+        fingerprint: u64,
+        path: PathBuf,
+        files: DiskUsage,
+        child_dirs: Vec<OsString>,
         timestamp: u64,
+        validation: CacheValidation,
+    },
+    WriteStats {
+        fingerprint: u64,
+        path: PathBuf,
+        stats: SubtreeStats,
+        timestamp: u64,
+        stamp: StampValue,
     },
     WriteHistory {
-        entries: Vec<String>,
+        entries: Vec<PathBuf>,
+    },
+    WriteSession {
+        root: PathBuf,
+        back: Vec<PathBuf>,
+        timestamp: u64,
     },
+    Invalidate {
+        fingerprint: u64,
+        paths: Vec<PathBuf>,
+    },
+}
+
+/// When a cached size or stats entry is still trusted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheValidation {
+    /// Maximum entry age (None: never expire while the directory is unchanged,
+    /// missing nested changes made while the app wasn't running)
+    pub ttl_secs: Option<u64>,
+    /// Also compare the fingerprint of the direct children (names, sizes, mtimes)
+    pub check_listing: bool,
+}
+
+impl CacheValidation {
+    /// Build from the user's cache settings
+    pub fn from_settings(settings: &SidebarSettings) -> Self {
+        Self {
+            ttl_secs: (!settings.cache_never_expire).then_some(settings.cache_ttl_hours * 3600),
+            check_listing: settings.cache_check_listing,
+        }
+    }
+
+    /// Whether an entry written at `timestamp` for `path` stamped `stored` still holds
+    fn accepts(&self, path: &Path, timestamp: u64, stored: StampValue) -> bool {
+        if let Some(ttl_secs) = self.ttl_secs
+            && epoch_secs(SystemTime::now()).saturating_sub(timestamp) > ttl_secs
+        {
+            return false;
+        }
+        DirStamp::read(path, self.check_listing)
+            .is_some_and(|current| current.matches(&DirStamp::from(stored), self.check_listing))
+    }
+}
+
+impl Default for CacheValidation {
+    fn default() -> Self {
+        Self::from_settings(&SidebarSettings::default())
+    }
+}
+
+/// What a cached entry is revalidated against on read.
+///
+/// A directory's mtime and ctime change when entries are added, removed or
+/// renamed in it; the listing fingerprint also catches direct files edited
+/// in place. Changes deeper down are only seen by the TTL and the watcher.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct DirStamp {
+    /// mtime in nanoseconds since the epoch
+    modified: u64,
+    /// ctime in nanoseconds since the epoch (0 where unavailable)
+    changed: u64,
+    /// Order-independent hash of the direct children's names, lengths and mtimes
+    /// (0 if not computed)
+    listing: u64,
+}
+
+impl DirStamp {
+    /// Stamp the directory at `path` as it is now (None if it can't be read)
+    fn read(path: &Path, with_listing: bool) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        let listing = if with_listing {
+            std::fs::read_dir(path)
+                .ok()?
+                .filter_map(|entry| entry.ok())
+                .map(|entry| {
+                    let (len, modified) = entry
+                        .metadata()
+                        .map(|m| (m.len(), modified_nanos(&m)))
+                        .unwrap_or_default();
+                    let mut bytes = os_bytes(&entry.file_name()).into_owned();
+                    bytes.extend_from_slice(&len.to_le_bytes());
+                    bytes.extend_from_slice(&modified.to_le_bytes());
+                    fnv1a(&bytes)
+                })
+                .fold(0u64, u64::wrapping_add)
+        } else {
+            0
+        };
+        Some(Self {
+            modified: modified_nanos(&metadata),
+            changed: changed_nanos(&metadata),
+            listing,
+        })
+    }
+
+    fn matches(&self, stored: &Self, check_listing: bool) -> bool {
+        self.modified == stored.modified
+            && self.changed == stored.changed
+            && (!check_listing || self.listing == stored.listing)
+    }
+
+    fn value(&self) -> StampValue {
+        (self.modified, self.changed, self.listing)
+    }
+}
+
+impl From<StampValue> for DirStamp {
+    fn from((modified, changed, listing): StampValue) -> Self {
+        Self {
+            modified,
+            changed,
+            listing,
+        }
+    }
+}
+
+/// Stamp of a directory taken before its size was calculated, stored with
+/// the result so a change during the walk makes the entry stale
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStamp(StampValue);
+
+impl CacheStamp {
+    /// Stamp the directory at `path` as it is now (None if it can't be read)
+    pub fn read(path: &Path) -> Option<Self> {
+        DirStamp::read(path, true).map(|stamp| Self(stamp.value()))
+    }
+
+    /// Stamp the directory as it is now, if its mtime is still the `modified`
+    /// a finished walk saw (for walks that couldn't stamp it beforehand)
+    pub fn read_unmodified(path: &Path, modified: SystemTime) -> Option<Self> {
+        let stamp = DirStamp::read(path, true)?;
+        let walked = modified
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
+        (stamp.modified == walked).then_some(Self(stamp.value()))
+    }
 }
 
 /// Persistent cache resource backed by redb
 #[derive(Resource)]
 pub struct PersistentCache {
-    db: Arc<Database>,
+    store: CacheStore,
     write_sender: Sender<CacheWriteCommand>,
-    size_ttl_secs: u64,
+    /// TTL and revalidation settings
+    validation: CacheValidation,
+    /// `ScanRules` fingerprint sizes are stored under
+    rules_fingerprint: u64,
+    /// Unusable file found on open
+    recovery: Option<CacheRecovery>,
+}
+
+/// Where reads are answered and writes end up
+#[derive(Clone)]
+enum CacheStore {
+    /// This instance holds the file lock (and serves other instances).
+    /// Compaction takes the lock exclusively; reads meanwhile are misses.
+    Owner(Arc<RwLock<Database>>),
+    /// Another instance does; everything goes through its socket
+    #[cfg(unix)]
+    Shared(Arc<SharedCacheClient>),
 }
 
 impl PersistentCache {
     /// Initialize persistent cache at platform data directory.
-    /// Returns None if DB creation fails (app continues without persistence).
-    pub fn new(ttl_secs: u64) -> Option<Self> {
-        let db_path = Self::db_path()?;
+    /// A corrupt or incompatible file is moved aside and recreated (see `recovery`);
+    /// a file locked by another instance is shared with it (see `shared_cache`).
+    /// Returns the reason if the cache can't be used (app continues without persistence).
+    pub fn new(validation: CacheValidation) -> Result<Self, String> {
+        let db_path = Self::db_path().ok_or("no data directory")?;
+        Self::open(&db_path, validation)
+    }
 
+    /// Open (or create, recover or share) the cache file at `db_path`
+    fn open(db_path: &Path, validation: CacheValidation) -> Result<Self, String> {
         // Ensure parent directory exists
         if let Some(parent) = db_path.parent() {
             if let Err(e) = std::fs::create_dir_all(parent) {
                 warn!("Failed to create cache directory: {}", e);
-                return None;
+                return Err(format!("can't create {}: {}", parent.display(), e));
             }
         }
 
-        let db = match Database::create(&db_path) {
-            Ok(db) => Arc::new(db),
-            Err(e) => {
-                warn!("Failed to open persistent cache: {}", e);
-                return None;
-            }
-        };
-
-        // Ensure tables exist
-        {
-            let write_txn = match db.begin_write() {
-                Ok(txn) => txn,
-                Err(e) => {
-                    warn!("Failed to begin write transaction: {}", e);
-                    return None;
+        let (db, recovery) = match open_database(db_path) {
+            Ok(db) => (db, None),
+            Err(OpenError::Locked) => return Self::join_owner(db_path, validation),
+            Err(OpenError::Unusable(reason)) => {
+                let backup =
+                    db_path.with_extension(format!("redb.{}.bak", epoch_secs(SystemTime::now())));
+                if let Err(e) = std::fs::rename(db_path, &backup) {
+                    warn!(
+                        "Persistent cache unusable ({}), backup failed: {}",
+                        reason, e
+                    );
+                    return Err(reason);
+                }
+                warn!(
+                    "Persistent cache unusable ({}), moved to {} and recreated",
+                    reason,
+                    backup.display()
+                );
+                match open_database(db_path) {
+                    Ok(db) => (db, Some(CacheRecovery { reason, backup })),
+                    Err(OpenError::Locked) => return Self::join_owner(db_path, validation),
+                    Err(OpenError::Unusable(reason)) => {
+                        warn!("Failed to recreate persistent cache: {}", reason);
+                        return Err(reason);
+                    }
                 }
-            };
-            if write_txn.open_table(SIZE_TABLE).is_err() {
-                warn!("Failed to create size table");
-                return None;
-            }
-            if write_txn.open_table(HISTORY_TABLE).is_err() {
-                warn!("Failed to create history table");
-                return None;
-            }
-            if let Err(e) = write_txn.commit() {
-                warn!("Failed to commit table creation: {}", e);
-                return None;
             }
-        }
+        };
+        let db = Arc::new(RwLock::new(db));
 
         // Spawn background writer thread
         let (sender, receiver) = crossbeam_channel::bounded::<CacheWriteCommand>(64);
@@ -89,92 +519,132 @@ impl PersistentCache {
             .name("persistent-cache-writer".into())
             .spawn(move || {
                 while let Ok(cmd) = receiver.recv() {
-                    match cmd {
-                        CacheWriteCommand::WriteSize {
-                            path,
-                            size,
-                            timestamp,
-                        } => {
-                            if let Ok(write_txn) = writer_db.begin_write() {
-                                if let Ok(mut table) = write_txn.open_table(SIZE_TABLE) {
-                                    let _ = table.insert(path.as_str(), (size, timestamp));
-                                }
-                                let _ = write_txn.commit();
-                            }
-                        }
-                        CacheWriteCommand::WriteHistory { entries } => {
-                            if let Ok(write_txn) = writer_db.begin_write() {
-                                if let Ok(mut table) = write_txn.open_table(HISTORY_TABLE) {
-                                    // Clear existing entries
-                                    let keys: Vec<u64> = table
-                                        .iter()
-                                        .ok()
-                                        .map(|iter| {
-                                            iter.filter_map(|r| r.ok())
-                                                .map(|(k, _)| k.value())
-                                                .collect()
-                                        })
-                                        .unwrap_or_default();
-                                    for key in keys {
-                                        let _ = table.remove(key);
-                                    }
-                                    // Write new entries (0 = newest)
-                                    for (i, entry) in entries.iter().enumerate() {
-                                        let _ = table.insert(i as u64, entry.as_str());
-                                    }
-                                }
-                                let _ = write_txn.commit();
-                            }
-                        }
+                    if let Ok(db) = writer_db.read() {
+                        apply_write(&db, cmd);
                     }
                 }
             })
-            .ok()?;
+            .map_err(|e| format!("can't start writer thread: {}", e))?;
+
+        // Later instances find the file locked and connect here instead
+        #[cfg(unix)]
+        if let Err(e) = shared_cache::serve(
+            &Self::socket_path(db_path),
+            Arc::clone(&db),
+            sender.clone(),
+        ) {
+            warn!("Can't share persistent cache with other instances: {}", e);
+        }
 
         info!(
             "Persistent cache initialized at {}",
             db_path.display()
         );
 
-        Some(Self {
-            db,
+        Ok(Self {
+            store: CacheStore::Owner(db),
             write_sender: sender,
-            size_ttl_secs: ttl_secs,
+            validation,
+            rules_fingerprint: 0,
+            recovery,
         })
     }
 
-    /// Get cached directory size if not expired
-    pub fn get_size(&self, path: &Path) -> Option<u64> {
-        let path_str = path.to_string_lossy();
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .ok()?
-            .as_secs();
+    /// Use the cache of the instance holding the file lock, through its socket
+    #[cfg(unix)]
+    fn join_owner(db_path: &Path, validation: CacheValidation) -> Result<Self, String> {
+        let socket = Self::socket_path(db_path);
+        // An owner that can't be reached yet is retried on later requests
+        let (client, sender) = SharedCacheClient::connect(&socket).map_err(|e| {
+            warn!(
+                "Can't share the persistent cache of another instance: {}",
+                e
+            );
+            format!("in use by another instance ({})", e)
+        })?;
+        info!(
+            "Persistent cache is in use by another instance, sharing it through {}",
+            socket.display()
+        );
 
-        let read_txn = self.db.begin_read().ok()?;
-        let table = read_txn.open_table(SIZE_TABLE).ok()?;
-        let entry = table.get(path_str.as_ref()).ok()??;
-        let (size, timestamp) = entry.value();
+        Ok(Self {
+            store: CacheStore::Shared(Arc::new(client)),
+            write_sender: sender,
+            validation,
+            rules_fingerprint: 0,
+            recovery: None,
+        })
+    }
 
-        // TTL check
-        if now.saturating_sub(timestamp) > self.size_ttl_secs {
-            return None;
+    #[cfg(not(unix))]
+    fn join_owner(_db_path: &Path, _validation: CacheValidation) -> Result<Self, String> {
+        warn!("Persistent cache is in use by another instance");
+        Err("in use by another instance".into())
+    }
+
+    /// Set if the file was unusable when opened and a fresh cache was created
+    pub fn recovery(&self) -> Option<&CacheRecovery> {
+        self.recovery.as_ref()
+    }
+
+    /// Whether the owning instance can't be reached; reopening then takes the
+    /// file over if it has quit
+    pub fn is_disconnected(&self) -> bool {
+        match &self.store {
+            CacheStore::Owner(_) => false,
+            #[cfg(unix)]
+            CacheStore::Shared(client) => !client.is_connected(),
         }
+    }
+
+    /// Apply changed TTL / revalidation settings
+    pub fn set_validation(&mut self, validation: CacheValidation) {
+        self.validation = validation;
+    }
+
+    /// Active TTL / revalidation settings
+    pub fn validation(&self) -> CacheValidation {
+        self.validation
+    }
 
-        Some(size)
+    /// Select the size namespace for the active scan rules
+    pub fn set_rules_fingerprint(&mut self, fingerprint: u64) {
+        self.rules_fingerprint = fingerprint;
     }
 
-    /// Queue a size write (non-blocking, drops if channel full)
-    pub fn write_size(&self, path: &Path, size: u64) {
+    /// Active size namespace
+    pub fn rules_fingerprint(&self) -> u64 {
+        self.rules_fingerprint
+    }
+
+    /// Handle for looking up sizes and stats from background tasks
+    ///
+    /// Validating an entry stats (and may list) its directory, so lookups
+    /// run next to the listing read instead of on the main thread.
+    pub fn reader(&self) -> CacheReader {
+        CacheReader {
+            store: self.store.clone(),
+            validation: self.validation,
+            rules_fingerprint: self.rules_fingerprint,
+        }
+    }
+
+    /// Queue a size write (non-blocking, drops if channel full), stamped as
+    /// the directory was before the walk. Ancestors whose children are now
+    /// all known get their total rolled up.
+    pub fn write_size(&self, path: &Path, usage: DiskUsage, stamp: CacheStamp) {
         let now = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .map(|d| d.as_secs())
             .unwrap_or(0);
 
         let cmd = CacheWriteCommand::WriteSize {
-            path: path.to_string_lossy().into_owned(),
-            size,
+            fingerprint: self.rules_fingerprint,
+            path: path.to_path_buf(),
+            usage,
             timestamp: now,
+            stamp: stamp.0,
+            validation: self.validation,
         };
 
         if let Err(TrySendError::Full(_)) = self.write_sender.try_send(cmd) {
@@ -182,40 +652,84 @@ impl PersistentCache {
         }
     }
 
-    /// Load navigation history from persistent storage (synchronous, startup only)
-    pub fn load_history(&self) -> Vec<PathBuf> {
-        let read_txn = match self.db.begin_read() {
-            Ok(txn) => txn,
-            Err(_) => return Vec::new(),
+    /// Queue a directory's manifest from its full listing: the usage of its
+    /// direct files and the child directories its total rolls up from
+    ///
+    /// Skipped when the rules count an inode once per walk (hardlink dedup,
+    /// followed symlinks): summing the children would count files they share
+    /// once per child, so those totals only come from walks.
+    pub fn write_manifest(&self, path: &Path, entries: &[FileEntry], rules: &ScanRules) {
+        if rules.dedup_hardlinks || rules.follow_symlinks {
+            return;
+        }
+        let mut files = DiskUsage::default();
+        let mut child_dirs = Vec::new();
+        for entry in entries {
+            if entry.is_drillable(rules.follow_symlinks) {
+                let name = entry.path.file_name().map(OsStr::to_os_string);
+                child_dirs.push(name.unwrap_or_else(|| entry.name.clone().into()));
+            } else {
+                files += entry.usage;
+            }
+        }
+
+        let cmd = CacheWriteCommand::WriteManifest {
+            fingerprint: self.rules_fingerprint,
+            path: path.to_path_buf(),
+            files,
+            child_dirs,
+            timestamp: epoch_secs(SystemTime::now()),
+            validation: self.validation,
         };
-        let table = match read_txn.open_table(HISTORY_TABLE) {
-            Ok(t) => t,
-            Err(_) => return Vec::new(),
+
+        if let Err(TrySendError::Full(_)) = self.write_sender.try_send(cmd) {
+            warn!("Persistent cache write channel full, dropping manifest write");
+        }
+    }
+
+    /// Queue a stats write (non-blocking, drops if channel full), stamped as
+    /// the directory was before the walk
+    pub fn write_stats(&self, path: &Path, stats: &SubtreeStats, stamp: CacheStamp) {
+        let cmd = CacheWriteCommand::WriteStats {
+            fingerprint: self.rules_fingerprint,
+            path: path.to_path_buf(),
+            stats: stats.clone(),
+            timestamp: epoch_secs(SystemTime::now()),
+            stamp: stamp.0,
         };
 
-        let mut entries: Vec<(u64, PathBuf)> = Vec::new();
-        if let Ok(iter) = table.iter() {
-            for item in iter {
-                if let Ok((key, value)) = item {
-                    entries.push((key.value(), PathBuf::from(value.value())));
-                }
-            }
+        if let Err(TrySendError::Full(_)) = self.write_sender.try_send(cmd) {
+            warn!("Persistent cache write channel full, dropping stats write");
         }
+    }
+
+    /// Queue invalidation of changed paths: only the sizes and stats of their
+    /// ancestor chain (whose totals include the change) are dropped, and the
+    /// manifest of the directory each change happened in. Everything else
+    /// stays, so re-sizing the changed child rolls the chain up again.
+    pub fn invalidate_sizes<'a>(&self, paths: impl IntoIterator<Item = &'a Path>) {
+        let cmd = CacheWriteCommand::Invalidate {
+            fingerprint: self.rules_fingerprint,
+            paths: paths.into_iter().map(Path::to_path_buf).collect(),
+        };
 
-        // Sort by index (0 = newest)
-        entries.sort_by_key(|(idx, _)| *idx);
-        entries.into_iter().map(|(_, path)| path).collect()
+        if let Err(TrySendError::Full(_)) = self.write_sender.try_send(cmd) {
+            warn!("Persistent cache write channel full, dropping size invalidation");
+        }
     }
 
-    /// Queue history write (non-blocking, drops if channel full)
-    pub fn write_history(&self, entries: &[PathBuf]) {
-        let string_entries: Vec<String> = entries
-            .iter()
-            .map(|p| p.to_string_lossy().into_owned())
-            .collect();
+    /// Load navigation history from persistent storage (synchronous; at
+    /// startup, entries written by other instances are picked up through `reader`)
+    pub fn load_history(&self) -> Vec<PathBuf> {
+        self.reader().load_history()
+    }
 
+    /// Queue history write (non-blocking, drops if channel full).
+    /// Stored entries missing from `entries` (e.g. added by another
+    /// instance) are kept behind them.
+    pub fn write_history(&self, entries: &[PathBuf]) {
         let cmd = CacheWriteCommand::WriteHistory {
-            entries: string_entries,
+            entries: entries.to_vec(),
         };
 
         if let Err(TrySendError::Full(_)) = self.write_sender.try_send(cmd) {
@@ -223,9 +737,1165 @@ impl PersistentCache {
         }
     }
 
+    /// Back stack stored for the session opened at `root`, oldest first
+    pub fn load_session(&self, root: &Path) -> Vec<PathBuf> {
+        match &self.store {
+            CacheStore::Owner(db) => db
+                .read()
+                .map(|db| read_session(&db, root))
+                .unwrap_or_default(),
+            #[cfg(unix)]
+            CacheStore::Shared(client) => client.session(root).unwrap_or_default(),
+        }
+    }
+
+    /// Queue a write of the session's back stack (non-blocking, drops if
+    /// channel full); an empty stack removes the session
+    pub fn write_session(&self, root: &Path, back: &[PathBuf]) {
+        let skip = back.len().saturating_sub(BACK_STACK_CAPACITY);
+        let cmd = CacheWriteCommand::WriteSession {
+            root: root.to_path_buf(),
+            back: back[skip..].to_vec(),
+            timestamp: epoch_secs(SystemTime::now()),
+        };
+
+        if let Err(TrySendError::Full(_)) = self.write_sender.try_send(cmd) {
+            warn!("Persistent cache write channel full, dropping session write");
+        }
+    }
+
+    /// Run a maintenance action on the IoTaskPool; the task returns the
+    /// summary afterwards. Only the instance owning the file maintains it.
+    pub fn maintain(&self, action: CacheMaintenance) -> Task<Result<CacheSummary, String>> {
+        let path = Self::db_path().unwrap_or_default();
+        let db = match &self.store {
+            CacheStore::Owner(db) => Some(Arc::clone(db)),
+            #[cfg(unix)]
+            CacheStore::Shared(_) => None,
+        };
+
+        IoTaskPool::get().spawn(async move {
+            let Some(db) = db else {
+                if action != CacheMaintenance::Summarize {
+                    return Err("the cache is managed by another open window".into());
+                }
+                return CacheSummary::read(&path, None).map_err(|e| e.to_string());
+            };
+            run_maintenance(&db, &path, &action)?;
+            let db = db.read().map_err(|e| e.to_string())?;
+            CacheSummary::read(&path, Some(&db)).map_err(|e| e.to_string())
+        })
+    }
+
     /// Platform-correct database path
     fn db_path() -> Option<PathBuf> {
         let proj_dirs = directories::ProjectDirs::from("", "", "closm-probe")?;
         Some(proj_dirs.data_dir().join("cache.redb"))
     }
+
+    /// Socket the owning instance listens on, next to the database
+    #[cfg(unix)]
+    fn socket_path(db_path: &Path) -> PathBuf {
+        db_path.with_extension("sock")
+    }
+}
+
+/// Read side of the persistent cache, for background tasks (see `PersistentCache::reader`)
+#[derive(Clone)]
+pub struct CacheReader {
+    store: CacheStore,
+    validation: CacheValidation,
+    rules_fingerprint: u64,
+}
+
+impl CacheReader {
+    /// Valid sizes and stats of `paths` (blocking: reads the database, in one
+    /// request when shared, and revalidates each entry against its directory)
+    pub fn lookup<'a>(&self, paths: impl IntoIterator<Item = &'a Path>) -> CachedSizes {
+        let paths: Vec<&Path> = paths.into_iter().collect();
+        let keys: Vec<Vec<u8>> = paths
+            .iter()
+            .map(|path| size_key(self.rules_fingerprint, path))
+            .collect();
+        let rows = match &self.store {
+            CacheStore::Owner(db) => match db.try_read() {
+                Ok(db) => keys.iter().map(|key| read_rows(&db, key)).collect(),
+                Err(_) => Vec::new(),
+            },
+            #[cfg(unix)]
+            CacheStore::Shared(client) => client.rows(&keys).unwrap_or_default(),
+        };
+
+        let mut cached = CachedSizes::default();
+        for (path, (size, stats)) in paths.into_iter().zip(rows) {
+            if let Some(usage) = size.and_then(|row| self.valid_size(path, row)) {
+                cached.sizes.insert(path.to_path_buf(), usage);
+            }
+            if let Some(stats) = stats.and_then(|row| self.valid_stats(path, row)) {
+                cached.stats.insert(path.to_path_buf(), stats);
+            }
+        }
+        cached
+    }
+
+    /// Navigation history, newest first (blocking, like `lookup`)
+    pub fn load_history(&self) -> Vec<PathBuf> {
+        match &self.store {
+            CacheStore::Owner(db) => db.read().map(|db| read_history(&db)).unwrap_or_default(),
+            #[cfg(unix)]
+            CacheStore::Shared(client) => client.history().unwrap_or_default(),
+        }
+    }
+
+    /// Cached directory size if within TTL and the directory is unchanged
+    fn valid_size(&self, path: &Path, row: SizeRow) -> Option<DiskUsage> {
+        let (apparent, allocated, timestamp, stamp) = row;
+        if !self.validation.accepts(path, timestamp, stamp) {
+            return None;
+        }
+
+        Some(DiskUsage {
+            apparent,
+            allocated,
+        })
+    }
+
+    /// Cached subtree stats if within TTL and the directory is unchanged
+    fn valid_stats(&self, path: &Path, row: StatsRow) -> Option<SubtreeStats> {
+        let (files, dirs, largest_bytes, largest_path, newest_secs, timestamp, stamp) = row;
+        if !self.validation.accepts(path, timestamp, stamp) {
+            return None;
+        }
+
+        Some(SubtreeStats {
+            files,
+            dirs,
+            largest_file: (!largest_path.is_empty())
+                .then(|| (PathBuf::from(os_from_bytes(&largest_path)), largest_bytes)),
+            newest_modified: UNIX_EPOCH + Duration::from_secs(newest_secs),
+        })
+    }
+}
+
+/// Persisted sizes and stats found for a listing's directories
+#[derive(Debug, Clone, Default)]
+pub struct CachedSizes {
+    sizes: HashMap<PathBuf, DiskUsage>,
+    stats: HashMap<PathBuf, SubtreeStats>,
+}
+
+impl CachedSizes {
+    pub fn size(&self, path: &Path) -> Option<DiskUsage> {
+        self.sizes.get(path).copied()
+    }
+
+    pub fn stats(&self, path: &Path) -> Option<SubtreeStats> {
+        self.stats.get(path).cloned()
+    }
+}
+
+/// Apply one write command in its own transaction (writer thread)
+fn apply_write(db: &Database, cmd: CacheWriteCommand) {
+    match cmd {
+        CacheWriteCommand::WriteSize {
+            fingerprint,
+            path,
+            usage,
+            timestamp,
+            stamp,
+            validation,
+        } => {
+            if let Ok(write_txn) = db.begin_write() {
+                if let (Ok(mut sizes), Ok(manifests)) = (
+                    write_txn.open_table(SIZE_TABLE),
+                    write_txn.open_table(MANIFEST_TABLE),
+                ) {
+                    let key = size_key(fingerprint, &path);
+                    let _ = sizes.insert(
+                        key.as_slice(),
+                        (usage.apparent, usage.allocated, timestamp, stamp),
+                    );
+                    if let Some(parent) = path.parent() {
+                        roll_up(&mut sizes, &manifests, fingerprint, parent, validation);
+                    }
+                }
+                let _ = write_txn.commit();
+            }
+        }
+        CacheWriteCommand::WriteManifest {
+            fingerprint,
+            path,
+            files,
+            child_dirs,
+            timestamp,
+            validation,
+        } => {
+            let Some(stamp) = DirStamp::read(&path, true) else {
+                return;
+            };
+            if let Ok(write_txn) = db.begin_write() {
+                if let (Ok(mut sizes), Ok(mut manifests)) = (
+                    write_txn.open_table(SIZE_TABLE),
+                    write_txn.open_table(MANIFEST_TABLE),
+                ) {
+                    let key = size_key(fingerprint, &path);
+                    let names: Vec<_> = child_dirs.iter().map(|name| os_bytes(name)).collect();
+                    let _ = manifests.insert(
+                        key.as_slice(),
+                        (
+                            files.apparent,
+                            files.allocated,
+                            names.join(&0u8).as_slice(),
+                            timestamp,
+                            stamp.value(),
+                        ),
+                    );
+                    // Children may all be sized already
+                    roll_up(&mut sizes, &manifests, fingerprint, &path, validation);
+                }
+                let _ = write_txn.commit();
+            }
+        }
+        CacheWriteCommand::WriteStats {
+            fingerprint,
+            path,
+            stats,
+            timestamp,
+            stamp,
+        } => {
+            if let Ok(write_txn) = db.begin_write() {
+                if let Ok(mut table) = write_txn.open_table(STATS_TABLE) {
+                    let (largest_path, largest_bytes) = stats
+                        .largest_file
+                        .as_ref()
+                        .map(|(path, len)| (os_bytes(path.as_os_str()), *len))
+                        .unwrap_or_default();
+                    let _ = table.insert(
+                        size_key(fingerprint, &path).as_slice(),
+                        (
+                            stats.files,
+                            stats.dirs,
+                            largest_bytes,
+                            largest_path.as_ref(),
+                            epoch_secs(stats.newest_modified),
+                            timestamp,
+                            stamp,
+                        ),
+                    );
+                }
+                let _ = write_txn.commit();
+            }
+        }
+        CacheWriteCommand::Invalidate { fingerprint, paths } => {
+            // Totals of the ancestor chain include the change; the
+            // listing of the directory it happened in is stale
+            let dirty: HashSet<Vec<u8>> = paths
+                .iter()
+                .flat_map(|p| p.ancestors())
+                .map(|p| size_key(fingerprint, p))
+                .collect();
+            let relisted: HashSet<Vec<u8>> = paths
+                .iter()
+                .flat_map(|p| std::iter::once(p.as_path()).chain(p.parent()))
+                .map(|p| size_key(fingerprint, p))
+                .collect();
+            if let Ok(write_txn) = db.begin_write() {
+                if let Ok(mut table) = write_txn.open_table(SIZE_TABLE) {
+                    for key in &dirty {
+                        let _ = table.remove(key.as_slice());
+                    }
+                }
+                if let Ok(mut table) = write_txn.open_table(STATS_TABLE) {
+                    for key in &dirty {
+                        let _ = table.remove(key.as_slice());
+                    }
+                }
+                if let Ok(mut table) = write_txn.open_table(MANIFEST_TABLE) {
+                    for key in &relisted {
+                        let _ = table.remove(key.as_slice());
+                    }
+                }
+                let _ = write_txn.commit();
+            }
+        }
+        CacheWriteCommand::WriteHistory { entries } => {
+            if let Ok(write_txn) = db.begin_write() {
+                if let Ok(mut table) = write_txn.open_table(HISTORY_TABLE) {
+                    // Stored entries, 0 = newest (keys iterate in order)
+                    let stored: Vec<Vec<u8>> = table
+                        .iter()
+                        .ok()
+                        .map(|iter| {
+                            iter.filter_map(|r| r.ok())
+                                .map(|(_, v)| v.value().to_vec())
+                                .collect()
+                        })
+                        .unwrap_or_default();
+                    for key in 0..stored.len() as u64 {
+                        let _ = table.remove(key);
+                    }
+                    // Another instance may have added entries this list
+                    // doesn't have yet: keep them behind it
+                    let mut merged: Vec<Cow<[u8]>> = Vec::new();
+                    let ours = entries.iter().map(|p| os_bytes(p.as_os_str()));
+                    for entry in ours.chain(stored.iter().map(|p| Cow::Borrowed(p.as_slice()))) {
+                        if !merged.contains(&entry) {
+                            merged.push(entry);
+                        }
+                    }
+                    for (i, entry) in merged.iter().take(HISTORY_CAPACITY).enumerate() {
+                        let _ = table.insert(i as u64, entry.as_ref());
+                    }
+                }
+                let _ = write_txn.commit();
+            }
+        }
+        CacheWriteCommand::WriteSession {
+            root,
+            back,
+            timestamp,
+        } => {
+            if let Ok(write_txn) = db.begin_write() {
+                if let Ok(mut table) = write_txn.open_table(SESSION_TABLE) {
+                    let key = os_bytes(root.as_os_str());
+                    if back.is_empty() {
+                        let _ = table.remove(key.as_ref());
+                    } else {
+                        let paths: Vec<_> = back.iter().map(|p| os_bytes(p.as_os_str())).collect();
+                        let _ =
+                            table.insert(key.as_ref(), (timestamp, paths.join(&0u8).as_slice()));
+                        prune_sessions(&mut table);
+                    }
+                }
+                let _ = write_txn.commit();
+            }
+        }
+    }
+}
+
+/// Drop the least recently written sessions beyond `SESSION_CAPACITY`
+fn prune_sessions(table: &mut Table<&'static [u8], (u64, &'static [u8])>) {
+    let Ok(iter) = table.iter() else {
+        return;
+    };
+    let mut sessions: Vec<(u64, Vec<u8>)> = iter
+        .filter_map(|r| r.ok())
+        .map(|(key, value)| (value.value().0, key.value().to_vec()))
+        .collect();
+    if sessions.len() <= SESSION_CAPACITY {
+        return;
+    }
+    sessions.sort_by_key(|(timestamp, _)| std::cmp::Reverse(*timestamp));
+    for (_, key) in &sessions[SESSION_CAPACITY..] {
+        let _ = table.remove(key.as_slice());
+    }
+}
+
+/// Run `action` on the owner's database at `path` (blocking)
+fn run_maintenance(
+    db: &RwLock<Database>,
+    path: &Path,
+    action: &CacheMaintenance,
+) -> Result<(), String> {
+    match action {
+        CacheMaintenance::Summarize => Ok(()),
+        CacheMaintenance::ClearSizes => {
+            let db = db.read().map_err(|e| e.to_string())?;
+            clear_sizes(&db).map_err(|e| e.to_string())
+        }
+        CacheMaintenance::ClearHistory => {
+            let db = db.read().map_err(|e| e.to_string())?;
+            clear_history(&db).map_err(|e| e.to_string())
+        }
+        CacheMaintenance::Compact => {
+            // Exclusive: no transaction may be open while compacting
+            let mut db = db.write().map_err(|e| e.to_string())?;
+            db.compact().map(|_| ()).map_err(|e| e.to_string())
+        }
+        CacheMaintenance::Export(target) => {
+            if is_same_file(target, path) {
+                return Err("can't export the cache onto itself".into());
+            }
+            let db = db.read().map_err(|e| e.to_string())?;
+            export_cache(&db, target)
+        }
+        CacheMaintenance::Import(source) => {
+            if is_same_file(source, path) {
+                return Err("can't import the cache into itself".into());
+            }
+            let db = db.read().map_err(|e| e.to_string())?;
+            import_cache(&db, source, &path.with_extension("import.redb"))
+        }
+    }
+}
+
+fn clear_sizes(db: &Database) -> Result<(), redb::Error> {
+    let write_txn = db.begin_write()?;
+    clear_table(&write_txn, SIZE_TABLE)?;
+    clear_table(&write_txn, STATS_TABLE)?;
+    clear_table(&write_txn, MANIFEST_TABLE)?;
+    write_txn.commit()?;
+    Ok(())
+}
+
+fn clear_history(db: &Database) -> Result<(), redb::Error> {
+    let write_txn = db.begin_write()?;
+    clear_table(&write_txn, HISTORY_TABLE)?;
+    clear_table(&write_txn, SESSION_TABLE)?;
+    write_txn.commit()?;
+    Ok(())
+}
+
+/// Remove every row of `table` (it stays, empty)
+fn clear_table<K: Key + 'static, V: Value + 'static>(
+    write_txn: &WriteTransaction,
+    table: TableDefinition<K, V>,
+) -> Result<(), redb::Error> {
+    write_txn.delete_table(table)?;
+    write_txn.open_table(table)?;
+    Ok(())
+}
+
+/// Write the rows of `db` to a new cache file at `target`, replacing any
+/// file there (the save dialog asked before overwriting)
+fn export_cache(db: &Database, target: &Path) -> Result<(), String> {
+    // Written next to the target and renamed over it: a failed export
+    // leaves an existing file as it was
+    let name = target.file_name().ok_or("no file name to export to")?;
+    let mut temp_name = OsString::from(".");
+    temp_name.push(name);
+    temp_name.push(format!(".{}.tmp", std::process::id()));
+    let temp = target.with_file_name(temp_name);
+
+    let result = write_export(db, &temp)
+        .and_then(|()| std::fs::rename(&temp, target).map_err(|e| e.to_string()));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp);
+    }
+    result
+}
+
+/// Copy of `db` in a new file at `path` (never an existing one)
+fn write_export(db: &Database, path: &Path) -> Result<(), String> {
+    let file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .map_err(|e| e.to_string())?;
+    let exported = Database::builder()
+        .create_file(file)
+        .map_err(|e| e.to_string())?;
+    migrate(&exported)?;
+    copy_rows(db, &exported).map_err(|e| e.to_string())
+}
+
+/// Whether `a` and `b` name the same file, through symlinks and relative
+/// paths; a path that doesn't exist yet is resolved through its directory
+fn is_same_file(a: &Path, b: &Path) -> bool {
+    match (resolve_path(a), resolve_path(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+fn resolve_path(path: &Path) -> Option<PathBuf> {
+    if let Ok(resolved) = path.canonicalize() {
+        return Some(resolved);
+    }
+    let name = path.file_name()?;
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    Some(dir.canonicalize().ok()?.join(name))
+}
+
+/// Merge the cache file at `source` into `db`. Opening it may migrate it,
+/// so that happens on a copy at `scratch` rather than the user's file.
+fn import_cache(db: &Database, source: &Path, scratch: &Path) -> Result<(), String> {
+    std::fs::copy(source, scratch).map_err(|e| e.to_string())?;
+    let result = Database::create(scratch)
+        .map_err(|e| e.to_string())
+        .and_then(|imported| {
+            migrate(&imported)?;
+            copy_rows(&imported, db).map_err(|e| e.to_string())
+        });
+    let _ = std::fs::remove_file(scratch);
+    result
+}
+
+/// Copy the size, stats, manifest, history and session rows of `from` into
+/// `to`. Rows of `from` replace those under the same key; its history
+/// entries go behind the ones `to` already has.
+fn copy_rows(from: &Database, to: &Database) -> Result<(), redb::Error> {
+    let read_txn = from.begin_read()?;
+    let write_txn = to.begin_write()?;
+    copy_table(
+        &read_txn.open_table(SIZE_TABLE)?,
+        &mut write_txn.open_table(SIZE_TABLE)?,
+    )?;
+    copy_table(
+        &read_txn.open_table(STATS_TABLE)?,
+        &mut write_txn.open_table(STATS_TABLE)?,
+    )?;
+    copy_table(
+        &read_txn.open_table(MANIFEST_TABLE)?,
+        &mut write_txn.open_table(MANIFEST_TABLE)?,
+    )?;
+    {
+        let mut sessions = write_txn.open_table(SESSION_TABLE)?;
+        copy_table(&read_txn.open_table(SESSION_TABLE)?, &mut sessions)?;
+        prune_sessions(&mut sessions);
+    }
+    {
+        let mut history = write_txn.open_table(HISTORY_TABLE)?;
+        let copied = read_txn.open_table(HISTORY_TABLE)?;
+        let mut entries = Vec::new();
+        for row in history.iter()?.chain(copied.iter()?) {
+            let (_, path) = row?;
+            if !entries.iter().any(|entry: &Vec<u8>| entry == path.value()) {
+                entries.push(path.value().to_vec());
+            }
+        }
+        for (i, entry) in entries.iter().take(HISTORY_CAPACITY).enumerate() {
+            history.insert(i as u64, entry.as_slice())?;
+        }
+    }
+    write_txn.commit()?;
+    Ok(())
+}
+
+fn copy_table<K: Key + 'static, V: Value + 'static>(
+    from: &impl ReadableTable<K, V>,
+    to: &mut Table<K, V>,
+) -> Result<(), redb::Error> {
+    for row in from.iter()? {
+        let (key, value) = row?;
+        to.insert(key.value(), value.value())?;
+    }
+    Ok(())
+}
+
+/// The size and stats rows stored under `key`, not yet validated
+pub(crate) fn read_rows(db: &Database, key: &[u8]) -> (Option<SizeRow>, Option<StatsRow>) {
+    (read_size(db, key), read_stats(db, key))
+}
+
+/// The size row stored under `key`, not yet validated
+pub(crate) fn read_size(db: &Database, key: &[u8]) -> Option<SizeRow> {
+    let read_txn = db.begin_read().ok()?;
+    let table = read_txn.open_table(SIZE_TABLE).ok()?;
+    let entry = table.get(key).ok()??;
+    Some(entry.value())
+}
+
+/// The stats row stored under `key`, not yet validated
+pub(crate) fn read_stats(db: &Database, key: &[u8]) -> Option<StatsRow> {
+    let read_txn = db.begin_read().ok()?;
+    let table = read_txn.open_table(STATS_TABLE).ok()?;
+    let entry = table.get(key).ok()??;
+    let (files, dirs, largest_bytes, largest_path, newest_secs, timestamp, stamp) = entry.value();
+    Some((
+        files,
+        dirs,
+        largest_bytes,
+        largest_path.to_vec(),
+        newest_secs,
+        timestamp,
+        stamp,
+    ))
+}
+
+/// Stored navigation history, newest first
+pub(crate) fn read_history(db: &Database) -> Vec<PathBuf> {
+    let read_txn = match db.begin_read() {
+        Ok(txn) => txn,
+        Err(_) => return Vec::new(),
+    };
+    let table = match read_txn.open_table(HISTORY_TABLE) {
+        Ok(t) => t,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut entries: Vec<(u64, PathBuf)> = Vec::new();
+    if let Ok(iter) = table.iter() {
+        for item in iter {
+            if let Ok((key, value)) = item {
+                entries.push((key.value(), PathBuf::from(os_from_bytes(value.value()))));
+            }
+        }
+    }
+
+    // Sort by index (0 = newest)
+    entries.sort_by_key(|(idx, _)| *idx);
+    entries.into_iter().map(|(_, path)| path).collect()
+}
+
+/// Stored back stack of the session opened at `root`, oldest first
+pub(crate) fn read_session(db: &Database, root: &Path) -> Vec<PathBuf> {
+    let Ok(read_txn) = db.begin_read() else {
+        return Vec::new();
+    };
+    let Ok(table) = read_txn.open_table(SESSION_TABLE) else {
+        return Vec::new();
+    };
+    let Ok(Some(entry)) = table.get(os_bytes(root.as_os_str()).as_ref()) else {
+        return Vec::new();
+    };
+    let (_, back) = entry.value();
+    back.split(|&b| b == 0)
+        .filter(|path| !path.is_empty())
+        .map(|path| PathBuf::from(os_from_bytes(path)))
+        .collect()
+}
+
+/// Key in the path-keyed tables for `path` under a rules fingerprint:
+/// the fingerprint (big-endian, so rows group by rules) and the path bytes
+fn size_key(fingerprint: u64, path: &Path) -> Vec<u8> {
+    let mut key = fingerprint.to_be_bytes().to_vec();
+    key.extend_from_slice(&os_bytes(path.as_os_str()));
+    key
+}
+
+/// Stored form of a path or name: the raw `OsStr` bytes on Unix, so distinct
+/// non-UTF-8 names stay distinct and round-trip; UTF-8 elsewhere
+#[cfg(unix)]
+fn os_bytes(name: &OsStr) -> Cow<'_, [u8]> {
+    use std::os::unix::ffi::OsStrExt;
+    Cow::Borrowed(name.as_bytes())
+}
+
+#[cfg(not(unix))]
+fn os_bytes(name: &OsStr) -> Cow<'_, [u8]> {
+    match name.to_string_lossy() {
+        Cow::Borrowed(name) => Cow::Borrowed(name.as_bytes()),
+        Cow::Owned(name) => Cow::Owned(name.into_bytes()),
+    }
+}
+
+/// Inverse of `os_bytes`
+#[cfg(unix)]
+fn os_from_bytes(bytes: &[u8]) -> OsString {
+    use std::os::unix::ffi::OsStrExt;
+    OsStr::from_bytes(bytes).to_os_string()
+}
+
+#[cfg(not(unix))]
+fn os_from_bytes(bytes: &[u8]) -> OsString {
+    String::from_utf8_lossy(bytes).into_owned().into()
+}
+
+/// Store the totals of `start` and its ancestors, as far up as each
+/// directory has a valid manifest whose child directories all have a valid size.
+///
+/// A rolled-up total is as old as its oldest input, so it expires with it,
+/// and carries the manifest's stamp (the listing it was built from).
+/// Manifests only exist for rules without inode dedup (see `write_manifest`),
+/// so the sum matches what a walk would count.
+fn roll_up(
+    sizes: &mut Table<&'static [u8], SizeRow>,
+    manifests: &Table<&'static [u8], ManifestValue>,
+    fingerprint: u64,
+    start: &Path,
+    validation: CacheValidation,
+) {
+    for dir in start.ancestors() {
+        let key = size_key(fingerprint, dir);
+        let Some((mut total, child_dirs, mut oldest, stamp)) =
+            manifests.get(key.as_slice()).ok().flatten().map(|entry| {
+                let (apparent, allocated, child_dirs, timestamp, stamp) = entry.value();
+                (
+                    DiskUsage {
+                        apparent,
+                        allocated,
+                    },
+                    child_dirs.to_vec(),
+                    timestamp,
+                    stamp,
+                )
+            })
+        else {
+            return;
+        };
+        if !validation.accepts(dir, oldest, stamp) {
+            return;
+        }
+        for name in child_dirs
+            .split(|&b| b == 0)
+            .filter(|name| !name.is_empty())
+        {
+            let child = dir.join(os_from_bytes(name));
+            let child_key = size_key(fingerprint, &child);
+            let Some((apparent, allocated, timestamp, child_stamp)) = sizes
+                .get(child_key.as_slice())
+                .ok()
+                .flatten()
+                .map(|entry| entry.value())
+            else {
+                return;
+            };
+            if !validation.accepts(&child, timestamp, child_stamp) {
+                return;
+            }
+            total += DiskUsage {
+                apparent,
+                allocated,
+            };
+            oldest = oldest.min(timestamp);
+        }
+        let _ = sizes.insert(
+            key.as_slice(),
+            (total.apparent, total.allocated, oldest, stamp),
+        );
+    }
+}
+
+/// mtime in nanoseconds since the Unix epoch (0 for earlier or unknown times)
+fn modified_nanos(metadata: &Metadata) -> u64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_nanos() as u64)
+}
+
+/// ctime in nanoseconds since the Unix epoch
+#[cfg(unix)]
+fn changed_nanos(metadata: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    (metadata.ctime().max(0) as u64)
+        .saturating_mul(1_000_000_000)
+        .saturating_add(metadata.ctime_nsec().max(0) as u64)
+}
+
+#[cfg(not(unix))]
+fn changed_nanos(_metadata: &Metadata) -> u64 {
+    0
+}
+
+/// Seconds since the Unix epoch (0 for earlier times)
+fn epoch_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_dir::TestDir;
+    use redb::backends::InMemoryBackend;
+    use std::fs;
+
+    const VALIDATION: CacheValidation = CacheValidation {
+        ttl_secs: Some(3600),
+        check_listing: true,
+    };
+
+    fn key(path: &Path) -> Vec<u8> {
+        size_key(1, path)
+    }
+
+    fn stamp(path: &Path) -> StampValue {
+        DirStamp::read(path, true).unwrap().value()
+    }
+
+    fn apparent(sizes: &Table<&'static [u8], SizeRow>, path: &Path) -> Option<u64> {
+        sizes
+            .get(key(path).as_slice())
+            .unwrap()
+            .map(|entry| entry.value().0)
+    }
+
+    fn in_memory() -> Database {
+        Database::builder()
+            .create_with_backend(InMemoryBackend::new())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_migrate_unversioned_keeps_history() {
+        let db = in_memory();
+        let legacy: TableDefinition<&str, (u64, u64)> = TableDefinition::new("sizes");
+        let write_txn = db.begin_write().unwrap();
+        write_txn
+            .open_table(legacy)
+            .unwrap()
+            .insert("/a", (1, 1))
+            .unwrap();
+        let history: TableDefinition<u64, &str> = TableDefinition::new("history");
+        write_txn
+            .open_table(history)
+            .unwrap()
+            .insert(0, "/a")
+            .unwrap();
+        write_txn.commit().unwrap();
+
+        migrate(&db).unwrap();
+
+        let read_txn = db.begin_read().unwrap();
+        let tables: Vec<String> = read_txn
+            .list_tables()
+            .unwrap()
+            .map(|table| table.name().to_string())
+            .collect();
+        assert!(!tables.contains(&"sizes".to_string()));
+        assert!(!tables.contains(&"history".to_string()));
+        assert!(tables.contains(&"dir_sizes_os".to_string()));
+        let history = read_txn.open_table(HISTORY_TABLE).unwrap();
+        assert_eq!(history.get(0).unwrap().unwrap().value(), b"/a");
+        let meta = read_txn.open_table(META_TABLE).unwrap();
+        let version = meta.get("schema_version").unwrap().unwrap().value();
+        assert_eq!(version, SCHEMA_VERSION);
+        drop((meta, history, read_txn));
+
+        // Reopening at the current version is a no-op
+        migrate(&db).unwrap();
+    }
+
+    #[test]
+    fn test_newer_schema_is_rejected() {
+        let db = in_memory();
+        let write_txn = db.begin_write().unwrap();
+        write_txn
+            .open_table(META_TABLE)
+            .unwrap()
+            .insert("schema_version", SCHEMA_VERSION + 1)
+            .unwrap();
+        write_txn.commit().unwrap();
+
+        assert!(migrate(&db).is_err());
+    }
+
+    #[test]
+    fn test_lossy_history_dropped_on_migration() {
+        let db = in_memory();
+        let history: TableDefinition<u64, &str> = TableDefinition::new("history");
+        let write_txn = db.begin_write().unwrap();
+        {
+            let mut table = write_txn.open_table(history).unwrap();
+            // Lossy: can't tell which folder this was
+            table.insert(0, "/\u{FFFD}").unwrap();
+            table.insert(1, "/a").unwrap();
+        }
+        write_txn.commit().unwrap();
+
+        migrate(&db).unwrap();
+
+        assert_eq!(read_history(&db), vec![PathBuf::from("/a")]);
+    }
+
+    #[test]
+    fn test_export_clear_import_round_trip() {
+        let dir = TestDir::new("export");
+        let db = in_memory();
+        migrate(&db).unwrap();
+        let write_txn = db.begin_write().unwrap();
+        {
+            let mut sizes = write_txn.open_table(SIZE_TABLE).unwrap();
+            sizes
+                .insert(key(Path::new("/a")).as_slice(), (1, 2, 100, (4, 5, 6)))
+                .unwrap();
+            sizes
+                .insert(key(Path::new("/b")).as_slice(), (1, 2, 300, (4, 5, 6)))
+                .unwrap();
+            write_txn
+                .open_table(HISTORY_TABLE)
+                .unwrap()
+                .insert(0, b"/a".as_slice())
+                .unwrap();
+        }
+        write_txn.commit().unwrap();
+
+        let exported = dir.join("export.redb");
+        export_cache(&db, &exported).unwrap();
+        clear_sizes(&db).unwrap();
+        clear_history(&db).unwrap();
+        let summary = CacheSummary::read(&exported, Some(&db)).unwrap();
+        assert_eq!((summary.sizes, summary.history), (0, 0));
+        assert_eq!(summary.oldest, None);
+
+        // Entries added since the export come first
+        apply_write(
+            &db,
+            CacheWriteCommand::WriteHistory {
+                entries: vec![PathBuf::from("/b")],
+            },
+        );
+        import_cache(&db, &exported, &dir.join("scratch.redb")).unwrap();
+        assert!(!dir.join("scratch.redb").exists());
+        let summary = CacheSummary::read(&exported, Some(&db)).unwrap();
+        assert_eq!(summary.sizes, 2);
+        assert_eq!(summary.oldest, Some(UNIX_EPOCH + Duration::from_secs(100)));
+        assert_eq!(summary.newest, Some(UNIX_EPOCH + Duration::from_secs(300)));
+        assert_eq!(
+            read_history(&db),
+            vec![PathBuf::from("/b"), PathBuf::from("/a")]
+        );
+
+        // Compaction needs exclusive access, hence the lock
+        let db = RwLock::new(db);
+        run_maintenance(&db, &dir.join("cache.redb"), &CacheMaintenance::Compact).unwrap();
+    }
+
+    #[test]
+    fn test_export_replaces_target_but_not_the_cache() {
+        let dir = TestDir::new("export-target");
+        let db = RwLock::new(in_memory());
+        migrate(&db.read().unwrap()).unwrap();
+        let cache_path = dir.join("cache.redb");
+        fs::write(&cache_path, b"").unwrap();
+        fs::create_dir_all(dir.join("sub")).unwrap();
+
+        // The cache file itself, however it's spelled
+        let export =
+            |target: PathBuf| run_maintenance(&db, &cache_path, &CacheMaintenance::Export(target));
+        assert!(export(dir.join("sub").join("..").join("cache.redb")).is_err());
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(&cache_path, dir.join("link.redb")).unwrap();
+            assert!(export(dir.join("link.redb")).is_err());
+        }
+
+        // An existing file is replaced in one step, leaving nothing behind
+        let target = dir.join("export.redb");
+        fs::write(&target, b"not a database").unwrap();
+        export(target.clone()).unwrap();
+        assert!(Database::open(&target).is_ok());
+        let leftovers = fs::read_dir(dir.path())
+            .unwrap()
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_name().to_string_lossy().ends_with(".tmp"))
+            .count();
+        assert_eq!(leftovers, 0);
+    }
+
+    #[test]
+    fn test_unusable_file_is_backed_up_and_recreated() {
+        let dir = TestDir::new("recover");
+        let db_path = dir.join("cache.redb");
+        fs::write(&db_path, b"not a database").unwrap();
+
+        let cache = PersistentCache::open(&db_path, VALIDATION).unwrap();
+        let recovery = cache.recovery().expect("recovery reported");
+        let backup_name = recovery.backup.file_name().unwrap().to_string_lossy();
+        let secs = backup_name
+            .strip_prefix("cache.redb.")
+            .and_then(|rest| rest.strip_suffix(".bak"))
+            .unwrap();
+        assert!(secs.parse::<u64>().is_ok());
+        assert_eq!(recovery.backup.parent(), Some(dir.path()));
+        assert_eq!(fs::read(&recovery.backup).unwrap(), b"not a database");
+
+        // A fresh database took its place
+        assert!(matches!(cache.store, CacheStore::Owner(_)));
+        assert!(cache.load_history().is_empty());
+        assert!(fs::metadata(&db_path).unwrap().len() > 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_keys_are_distinct() {
+        use std::os::unix::ffi::OsStrExt;
+        let a = Path::new(OsStr::from_bytes(b"/dir/\xff"));
+        let b = Path::new(OsStr::from_bytes(b"/dir/\xfe"));
+        assert_eq!(a.to_string_lossy(), b.to_string_lossy());
+        assert_ne!(key(a), key(b));
+        assert_eq!(os_from_bytes(&os_bytes(a.as_os_str())), a.as_os_str());
+    }
+
+    #[test]
+    fn test_roll_up_waits_for_all_children() {
+        let tmp = TestDir::new("rollup");
+        let root = tmp.path().to_path_buf();
+        let dir = root.join("a");
+        fs::create_dir_all(dir.join("b")).unwrap();
+        fs::create_dir_all(dir.join("c")).unwrap();
+
+        let db = in_memory();
+        let now = epoch_secs(SystemTime::now());
+        let write_txn = db.begin_write().unwrap();
+        {
+            let mut sizes = write_txn.open_table(SIZE_TABLE).unwrap();
+            let mut manifests = write_txn.open_table(MANIFEST_TABLE).unwrap();
+            manifests
+                .insert(
+                    key(&root).as_slice(),
+                    (5, 5, b"a".as_slice(), now, stamp(&root)),
+                )
+                .unwrap();
+            manifests
+                .insert(
+                    key(&dir).as_slice(),
+                    (10, 10, b"b\0c".as_slice(), now, stamp(&dir)),
+                )
+                .unwrap();
+
+            let b = dir.join("b");
+            sizes
+                .insert(key(&b).as_slice(), (100, 100, now, stamp(&b)))
+                .unwrap();
+            roll_up(&mut sizes, &manifests, 1, &dir, VALIDATION);
+            assert_eq!(apparent(&sizes, &dir), None);
+
+            let c = dir.join("c");
+            sizes
+                .insert(key(&c).as_slice(), (1000, 1000, now, stamp(&c)))
+                .unwrap();
+            roll_up(&mut sizes, &manifests, 1, &dir, VALIDATION);
+            assert_eq!(apparent(&sizes, &dir), Some(1110));
+            assert_eq!(apparent(&sizes, &root), Some(1115));
+        }
+        write_txn.commit().unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_no_roll_up_over_shared_hardlinks() {
+        let tmp = TestDir::new("rollup-hardlinks");
+        let root = tmp.path();
+        fs::create_dir_all(root.join("a")).unwrap();
+        fs::create_dir_all(root.join("b")).unwrap();
+        fs::write(root.join("a/data.bin"), [0u8; 4096]).unwrap();
+        fs::hard_link(root.join("a/data.bin"), root.join("b/data.bin")).unwrap();
+        let entries: Vec<FileEntry> = ["a", "b"]
+            .iter()
+            .map(|name| FileEntry {
+                name: name.to_string(),
+                path: root.join(name),
+                usage: DiskUsage::default(),
+                modified: SystemTime::now(),
+                is_directory: true,
+                child_count: Some(1),
+                symlink: None,
+            })
+            .collect();
+        let child = DiskUsage {
+            apparent: 4096,
+            allocated: 4096,
+        };
+
+        for dedup_hardlinks in [true, false] {
+            let rules = ScanRules::from_settings(&SidebarSettings {
+                dedup_hardlinks,
+                ..default()
+            });
+            let db = Arc::new(RwLock::new(in_memory()));
+            migrate(&db.read().unwrap()).unwrap();
+            let (sender, writes) = crossbeam_channel::unbounded();
+            let mut cache = PersistentCache {
+                store: CacheStore::Owner(Arc::clone(&db)),
+                write_sender: sender,
+                validation: VALIDATION,
+                rules_fingerprint: 0,
+                recovery: None,
+            };
+            cache.set_rules_fingerprint(rules.fingerprint());
+
+            cache.write_manifest(root, &entries, &rules);
+            for name in ["a", "b"] {
+                let stamp = CacheStamp::read(&root.join(name)).unwrap();
+                cache.write_size(&root.join(name), child, stamp);
+            }
+            for cmd in writes.try_iter() {
+                apply_write(&db.read().unwrap(), cmd);
+            }
+
+            // A deduplicating walk counts the shared file once, the children's sum twice
+            let rolled_up = cache.reader().lookup([root]).size(root);
+            let rolled_up = rolled_up.map(|usage| usage.apparent);
+            assert_eq!(rolled_up, (!dedup_hardlinks).then_some(8192));
+        }
+    }
+
+    #[test]
+    fn test_stamp_taken_before_the_walk() {
+        let tmp = TestDir::new("stamp-walk");
+        let dir = tmp.path();
+        let db = in_memory();
+        migrate(&db).unwrap();
+        let write = |stamp: CacheStamp| CacheWriteCommand::WriteSize {
+            fingerprint: 1,
+            path: dir.to_path_buf(),
+            usage: DiskUsage::default(),
+            timestamp: epoch_secs(SystemTime::now()),
+            stamp: stamp.0,
+            validation: VALIDATION,
+        };
+        let stored = |db: &Database| {
+            let (.., timestamp, stamp) = read_size(db, &key(dir)).unwrap();
+            VALIDATION.accepts(dir, timestamp, stamp)
+        };
+
+        apply_write(&db, write(CacheStamp::read(dir).unwrap()));
+        assert!(stored(&db));
+
+        // A file added while the walk ran isn't in its total
+        let before = CacheStamp::read(dir).unwrap();
+        fs::write(dir.join("late"), b"1").unwrap();
+        apply_write(&db, write(before));
+        assert!(!stored(&db));
+    }
+
+    #[test]
+    fn test_entries_revalidated_against_directory() {
+        let tmp = TestDir::new("stamp");
+        let dir = tmp.path();
+        fs::write(dir.join("file"), b"1").unwrap();
+        let now = epoch_secs(SystemTime::now());
+        let stored = stamp(dir);
+
+        assert!(VALIDATION.accepts(dir, now, stored));
+        // Expired by TTL, unless entries never expire while unchanged
+        assert!(!VALIDATION.accepts(dir, now - 7200, stored));
+        let never = CacheValidation {
+            ttl_secs: None,
+            ..VALIDATION
+        };
+        assert!(never.accepts(dir, now - 7200, stored));
+
+        // A direct file edited in place only shows in the listing fingerprint
+        fs::write(dir.join("file"), b"12").unwrap();
+        assert!(!VALIDATION.accepts(dir, now, stored));
+        let (modified, changed, _) = stored;
+        assert_eq!(stamp(dir).0, modified);
+        assert_eq!(stamp(dir).1, changed);
+    }
+
+    #[test]
+    fn test_sessions_keep_back_stacks() {
+        let db = in_memory();
+        migrate(&db).unwrap();
+        let session = |root: &str, back: &[&str], timestamp: u64| CacheWriteCommand::WriteSession {
+            root: PathBuf::from(root),
+            back: back.iter().map(PathBuf::from).collect(),
+            timestamp,
+        };
+
+        apply_write(&db, session("/a", &["/a", "/a/b"], 1));
+        apply_write(&db, session("/x", &["/x"], 2));
+        assert_eq!(
+            read_session(&db, Path::new("/a")),
+            vec![PathBuf::from("/a"), PathBuf::from("/a/b")]
+        );
+        assert!(read_session(&db, Path::new("/b")).is_empty());
+
+        // An empty stack forgets the session
+        apply_write(&db, session("/x", &[], 3));
+        assert!(read_session(&db, Path::new("/x")).is_empty());
+
+        // The least recently written go first
+        for i in 0..SESSION_CAPACITY as u64 {
+            apply_write(&db, session(&format!("/s{}", i), &["/"], 10 + i));
+        }
+        assert!(read_session(&db, Path::new("/a")).is_empty());
+        assert_eq!(
+            read_session(&db, Path::new("/s0")),
+            vec![PathBuf::from("/")]
+        );
+
+        clear_history(&db).unwrap();
+        assert!(read_session(&db, Path::new("/s0")).is_empty());
+    }
 }
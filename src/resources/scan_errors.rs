@@ -0,0 +1,148 @@
+//! Scan error resources
+//!
+//! Per-path I/O errors met while listing, indexing or sizing.
+//! Sizes of directories with unreadable entries are lower bounds.
+
+#![allow(dead_code)]
+
+use bevy::prelude::*;
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// ELOOP (too many levels of symbolic links)
+#[cfg(target_os = "linux")]
+const ELOOP: i32 = 40;
+#[cfg(target_os = "macos")]
+const ELOOP: i32 = 62;
+
+/// Why an entry couldn't be read
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScanErrorKind {
+    /// EACCES / EPERM
+    PermissionDenied,
+    /// Removed between listing and reading it
+    NotFound,
+    /// Symlink loop (ELOOP or detected by the walker)
+    Loop,
+    /// Anything else
+    Other,
+}
+
+impl ScanErrorKind {
+    /// Classify an I/O error
+    pub fn from_io(error: &io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::PermissionDenied => Self::PermissionDenied,
+            io::ErrorKind::NotFound => Self::NotFound,
+            _ if is_loop(error) => Self::Loop,
+            _ => Self::Other,
+        }
+    }
+
+    /// Short label for the UI
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::PermissionDenied => "Permission denied",
+            Self::NotFound => "Vanished while scanning",
+            Self::Loop => "Symlink loop",
+            Self::Other => "Read error",
+        }
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn is_loop(error: &io::Error) -> bool {
+    error.raw_os_error() == Some(ELOOP)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn is_loop(_error: &io::Error) -> bool {
+    false
+}
+
+/// Single unreadable entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScanError {
+    /// Entry that couldn't be read
+    pub path: PathBuf,
+    pub kind: ScanErrorKind,
+    /// Original error message
+    pub message: String,
+}
+
+impl ScanError {
+    /// Error from a std::fs call on `path`
+    pub fn from_io(path: &Path, error: &io::Error) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            kind: ScanErrorKind::from_io(error),
+            message: error.to_string(),
+        }
+    }
+
+    /// Error yielded by a jwalk walk (`fallback` if the error carries no path)
+    pub fn from_walk(error: &jwalk::Error, fallback: &Path) -> Self {
+        let path = error.path().unwrap_or(fallback).to_path_buf();
+        let kind = if error.loop_ancestor().is_some() {
+            ScanErrorKind::Loop
+        } else {
+            error
+                .io_error()
+                .map(ScanErrorKind::from_io)
+                .unwrap_or(ScanErrorKind::Other)
+        };
+        Self {
+            path,
+            kind,
+            message: error.to_string(),
+        }
+    }
+}
+
+/// Unreadable entries below the opened root, by path
+#[derive(Resource, Debug, Default)]
+pub struct ScanErrors {
+    errors: BTreeMap<PathBuf, ScanError>,
+}
+
+impl ScanErrors {
+    /// Record errors (a newer error for the same path replaces the old one)
+    pub fn record(&mut self, errors: impl IntoIterator<Item = ScanError>) {
+        for error in errors {
+            self.errors.insert(error.path.clone(), error);
+        }
+    }
+
+    /// Forget errors at or below `path` (before recording the results of a fresh walk)
+    pub fn clear_below(&mut self, path: &Path) {
+        self.errors.retain(|p, _| !p.starts_with(path));
+    }
+
+    /// Forget all errors
+    pub fn clear(&mut self) {
+        self.errors.clear();
+    }
+
+    /// Number of unreadable entries at or below `path`
+    pub fn count_below(&self, path: &Path) -> usize {
+        // Paths order by component, so a subtree is one contiguous range
+        self.errors
+            .range(path.to_path_buf()..)
+            .take_while(|(p, _)| p.starts_with(path))
+            .count()
+    }
+
+    /// All errors, ordered by path
+    pub fn iter(&self) -> impl Iterator<Item = &ScanError> {
+        self.errors.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.errors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
@@ -0,0 +1,567 @@
+//! Scan rules
+//!
+//! Decide which entries are listed and counted: hidden files,
+//! glob include/exclude patterns, optional `.gitignore` support,
+//! hardlink dedup, filesystem boundaries and symlink following.
+//! Shared by directory listings, the tree index and the size walkers.
+
+#![allow(dead_code)]
+
+use crate::resources::SidebarSettings;
+use bevy::prelude::*;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::gitignore::Gitignore;
+use std::collections::HashSet;
+use std::fs::{self, Metadata};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Gitignore matchers from the enclosing repository down to the current directory
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreStack(Vec<Arc<Gitignore>>);
+
+/// Active filtering rules (derived from `SidebarSettings`)
+#[derive(Resource, Debug, Clone)]
+pub struct ScanRules {
+    /// Include dotfiles
+    pub show_hidden: bool,
+    /// Glob patterns for entries to skip (matched against name and full path)
+    pub exclude_patterns: Vec<String>,
+    /// Glob patterns files must match to be shown (empty = all files)
+    pub include_patterns: Vec<String>,
+    /// Skip entries ignored by `.gitignore` files
+    pub respect_gitignore: bool,
+    /// Count each (device, inode) once per total
+    pub dedup_hardlinks: bool,
+    /// Skip directories on a different device than the walk root
+    pub one_filesystem: bool,
+    /// Descend into symlinked directories and count link targets
+    pub follow_symlinks: bool,
+    exclude: GlobSet,
+    include: GlobSet,
+    fingerprint: u64,
+}
+
+impl Default for ScanRules {
+    fn default() -> Self {
+        Self::from_settings(&SidebarSettings::default())
+    }
+}
+
+impl ScanRules {
+    /// Build rules from user settings (invalid patterns are skipped)
+    pub fn from_settings(settings: &SidebarSettings) -> Self {
+        let mut rules = Self {
+            show_hidden: settings.show_hidden_files,
+            exclude_patterns: settings.exclude_patterns.clone(),
+            include_patterns: settings.include_patterns.clone(),
+            respect_gitignore: settings.respect_gitignore,
+            dedup_hardlinks: settings.dedup_hardlinks,
+            one_filesystem: settings.one_filesystem,
+            follow_symlinks: settings.follow_symlinks,
+            exclude: build_glob_set(&settings.exclude_patterns),
+            include: build_glob_set(&settings.include_patterns),
+            fingerprint: 0,
+        };
+        rules.fingerprint = rules.compute_fingerprint();
+        rules
+    }
+
+    /// True if these rules were built from the same settings
+    pub fn matches_settings(&self, settings: &SidebarSettings) -> bool {
+        self.show_hidden == settings.show_hidden_files
+            && self.exclude_patterns == settings.exclude_patterns
+            && self.include_patterns == settings.include_patterns
+            && self.respect_gitignore == settings.respect_gitignore
+            && self.dedup_hardlinks == settings.dedup_hardlinks
+            && self.one_filesystem == settings.one_filesystem
+            && self.follow_symlinks == settings.follow_symlinks
+    }
+
+    /// Stable hash of the rules; cached sizes are only valid for the same fingerprint
+    pub fn fingerprint(&self) -> u64 {
+        self.fingerprint
+    }
+
+    fn compute_fingerprint(&self) -> u64 {
+        let mut canonical = format!(
+            "hidden={};gitignore={};hardlinks={};onefs={};symlinks={};",
+            self.show_hidden,
+            self.respect_gitignore,
+            self.dedup_hardlinks,
+            self.one_filesystem,
+            self.follow_symlinks
+        );
+        for pattern in &self.exclude_patterns {
+            canonical.push('-');
+            canonical.push_str(pattern);
+            canonical.push('\0');
+        }
+        for pattern in &self.include_patterns {
+            canonical.push('+');
+            canonical.push_str(pattern);
+            canonical.push('\0');
+        }
+        fnv1a(canonical.as_bytes())
+    }
+
+    /// True if the entry should be listed and counted
+    pub fn allows(&self, path: &Path, is_dir: bool, ignores: &IgnoreStack) -> bool {
+        let Some(name) = path.file_name().map(|n| n.to_string_lossy()) else {
+            return true;
+        };
+
+        if !self.show_hidden && name.starts_with('.') {
+            return false;
+        }
+
+        if self.exclude.is_match(&*name) || self.exclude.is_match(path) {
+            return false;
+        }
+
+        // Include patterns select files; directories are always traversed
+        if !is_dir && !self.include.is_empty() && !self.include.is_match(&*name) {
+            return false;
+        }
+
+        if self.respect_gitignore {
+            if is_dir && name == ".git" {
+                return false;
+            }
+            // Deepest .gitignore wins
+            for gitignore in ignores.0.iter().rev() {
+                let matched = gitignore.matched(path, is_dir);
+                if matched.is_ignore() {
+                    return false;
+                }
+                if matched.is_whitelist() {
+                    return true;
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Gitignore matchers for the ancestors of `dir` (exclusive), up to the repository root
+    pub fn ancestor_ignores(&self, dir: &Path) -> IgnoreStack {
+        let mut stack = IgnoreStack::default();
+        if !self.respect_gitignore {
+            return stack;
+        }
+
+        let mut ancestors = Vec::new();
+        let mut current = dir.parent();
+        while let Some(ancestor) = current {
+            ancestors.push(ancestor);
+            if ancestor.join(".git").exists() {
+                break;
+            }
+            current = ancestor.parent();
+        }
+
+        // Root-first, so deeper files are pushed last
+        for ancestor in ancestors.into_iter().rev() {
+            self.descend(&mut stack, ancestor);
+        }
+        stack
+    }
+
+    /// Gitignore matchers in effect for the entries of `dir`
+    pub fn ignores_for(&self, dir: &Path) -> IgnoreStack {
+        let mut stack = self.ancestor_ignores(dir);
+        self.descend(&mut stack, dir);
+        stack
+    }
+
+    /// Push `dir/.gitignore` (if any) onto the stack
+    pub fn descend(&self, stack: &mut IgnoreStack, dir: &Path) {
+        if !self.respect_gitignore {
+            return;
+        }
+        let gitignore_path = dir.join(".gitignore");
+        if !gitignore_path.is_file() {
+            return;
+        }
+        let (gitignore, error) = Gitignore::new(&gitignore_path);
+        if let Some(e) = error {
+            warn!("Partially invalid {}: {}", gitignore_path.display(), e);
+        }
+        stack.0.push(Arc::new(gitignore));
+    }
+
+    /// Hardlink/filesystem bookkeeping for one walk rooted at `root`
+    pub fn bounds(&self, root: &Path) -> WalkBounds {
+        let root_metadata = fs::metadata(root).ok();
+        let mut bounds = WalkBounds {
+            root_device: root_metadata.as_ref().and_then(device_id),
+            one_filesystem: self.one_filesystem,
+            dedup_hardlinks: self.dedup_hardlinks,
+            follow_symlinks: self.follow_symlinks,
+            seen: HashSet::new(),
+            entered: HashSet::new(),
+        };
+        if let Some(metadata) = root_metadata {
+            bounds.enter_directory(&metadata);
+        }
+        bounds
+    }
+
+    /// jwalk walker that applies these rules while descending
+    ///
+    /// Filesystem boundaries are enforced here; hardlink dedup is left to the
+    /// consumer (see `WalkBounds::first_link`). Symlinks are yielded as links;
+    /// with `follow_symlinks`, linked directories are also descended unless
+    /// they point back at a directory on the current path.
+    pub fn walker(&self, root: &Path) -> jwalk::WalkDirGeneric<(WalkState, ())> {
+        let rules = self.clone();
+        let bounds = self.bounds(root);
+        let root_state = WalkState {
+            ignores: self.ancestor_ignores(root),
+            ancestors: Vec::new(),
+        };
+        jwalk::WalkDirGeneric::<(WalkState, ())>::new(root)
+            .skip_hidden(false)
+            .follow_links(false)
+            .sort(false)
+            .root_read_dir_state(root_state)
+            .process_read_dir(move |depth, dir, state, children| {
+                // depth is None for the synthetic listing that yields the root itself
+                if depth.is_none() {
+                    return;
+                }
+                rules.descend(&mut state.ignores, dir);
+                if rules.follow_symlinks
+                    && let Some(key) = fs::metadata(dir).ok().as_ref().and_then(inode_key)
+                {
+                    state.ancestors.push(key);
+                }
+                children.retain_mut(|result| match result {
+                    Ok(entry) => {
+                        let is_dir = entry.file_type().is_dir();
+                        if is_dir && bounds.one_filesystem {
+                            let same = entry.metadata().is_ok_and(|m| bounds.same_filesystem(&m));
+                            if !same {
+                                return false;
+                            }
+                        }
+                        if !rules.allows(&entry.path(), is_dir, &state.ignores) {
+                            return false;
+                        }
+                        if rules.follow_symlinks && entry.file_type().is_symlink() {
+                            let path = entry.path();
+                            let descend = fs::metadata(&path).is_ok_and(|target| {
+                                target.is_dir()
+                                    && bounds.same_filesystem(&target)
+                                    && !inode_key(&target)
+                                        .is_some_and(|key| state.ancestors.contains(&key))
+                            });
+                            if descend {
+                                entry.read_children_path = Some(Arc::from(path));
+                            }
+                        }
+                        true
+                    }
+                    Err(_) => true,
+                });
+            })
+    }
+
+    /// Extra BSD `du` flags for hardlink and filesystem handling
+    pub fn du_flags(&self) -> Vec<&'static str> {
+        let mut flags = Vec::new();
+        // BSD du counts hardlinks once by default; -l counts every link
+        if !self.dedup_hardlinks {
+            flags.push("-l");
+        }
+        if self.one_filesystem {
+            flags.push("-x");
+        }
+        // fts detects link cycles on its own
+        if self.follow_symlinks {
+            flags.push("-L");
+        }
+        flags
+    }
+
+    /// Masks for BSD `du -I`, or None if the rules can't be expressed that way
+    pub fn du_masks(&self) -> Option<Vec<String>> {
+        if self.respect_gitignore || !self.include_patterns.is_empty() {
+            return None;
+        }
+        // du -I matches names only
+        if self.exclude_patterns.iter().any(|p| p.contains('/')) {
+            return None;
+        }
+
+        let mut masks = self.exclude_patterns.clone();
+        if !self.show_hidden {
+            masks.push(".*".to_string());
+        }
+        Some(masks)
+    }
+}
+
+/// Per-directory state of a `ScanRules::walker` walk
+#[derive(Debug, Clone, Default)]
+pub struct WalkState {
+    /// Gitignore matchers in effect
+    pub ignores: IgnoreStack,
+    /// (device, inode) of the directories on the current path (only when following symlinks)
+    ancestors: Vec<(u64, u64)>,
+}
+
+/// Hardlink, filesystem-boundary and symlink state for a single walk
+#[derive(Debug, Clone)]
+pub struct WalkBounds {
+    /// Device of the walk root (None if unknown or unsupported)
+    root_device: Option<u64>,
+    one_filesystem: bool,
+    dedup_hardlinks: bool,
+    follow_symlinks: bool,
+    /// (device, inode) of files already counted
+    seen: HashSet<(u64, u64)>,
+    /// (device, inode) of directories already walked (only when following symlinks)
+    entered: HashSet<(u64, u64)>,
+}
+
+impl WalkBounds {
+    /// False if `metadata` lives on another filesystem and the rules stay on one
+    pub fn same_filesystem(&self, metadata: &Metadata) -> bool {
+        if !self.one_filesystem {
+            return true;
+        }
+        match (self.root_device, device_id(metadata)) {
+            (Some(root), Some(device)) => root == device,
+            _ => true,
+        }
+    }
+
+    /// True the first time a file is seen (always true unless dedup is on)
+    ///
+    /// Only multiply-linked files are tracked, unless symlinks are followed:
+    /// then any file may be reached again through a link.
+    pub fn first_link(&mut self, metadata: &Metadata) -> bool {
+        if !self.dedup_hardlinks {
+            return true;
+        }
+        if !self.follow_symlinks && link_count(metadata) < 2 {
+            return true;
+        }
+        match inode_key(metadata) {
+            Some(key) => self.seen.insert(key),
+            None => true,
+        }
+    }
+
+    /// Inode key `first_link` would track for `metadata`, without recording it
+    ///
+    /// None when the file always counts. Lets callers pick which link owns an
+    /// inode independently of walk order.
+    pub fn link_key(&self, metadata: &Metadata) -> Option<(u64, u64)> {
+        if !self.dedup_hardlinks {
+            return None;
+        }
+        if !self.follow_symlinks && link_count(metadata) < 2 {
+            return None;
+        }
+        inode_key(metadata)
+    }
+
+    /// True if symlinks to directories should be descended
+    pub fn follows_symlinks(&self) -> bool {
+        self.follow_symlinks
+    }
+
+    /// True the first time a directory is entered (guards recursive walks against link cycles)
+    pub fn enter_directory(&mut self, metadata: &Metadata) -> bool {
+        if !self.follow_symlinks {
+            return true;
+        }
+        match inode_key(metadata) {
+            Some(key) => self.entered.insert(key),
+            None => true,
+        }
+    }
+}
+
+#[cfg(unix)]
+fn device_id(metadata: &Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.dev())
+}
+
+#[cfg(not(unix))]
+fn device_id(_metadata: &Metadata) -> Option<u64> {
+    None
+}
+
+/// (device, inode) identifying a file or directory
+#[cfg(unix)]
+fn inode_key(metadata: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn inode_key(_metadata: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+#[cfg(unix)]
+fn link_count(metadata: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.nlink()
+}
+
+#[cfg(not(unix))]
+fn link_count(_metadata: &Metadata) -> u64 {
+    1
+}
+
+/// Parse a comma or newline separated pattern list
+pub fn parse_pattern_list(text: &str) -> Vec<String> {
+    text.split([',', '\n'])
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn build_glob_set(patterns: &[String]) -> GlobSet {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        match Glob::new(pattern) {
+            Ok(glob) => {
+                builder.add(glob);
+            }
+            Err(e) => warn!("Ignoring invalid pattern '{}': {}", pattern, e),
+        }
+    }
+    builder.build().unwrap_or_else(|_| GlobSet::empty())
+}
+
+/// FNV-1a 64-bit (stable across builds, unlike `DefaultHasher`)
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_dir::TestDir;
+    use std::path::PathBuf;
+
+    fn rules(exclude: &[&str], include: &[&str], show_hidden: bool) -> ScanRules {
+        let settings = SidebarSettings {
+            show_hidden_files: show_hidden,
+            exclude_patterns: exclude.iter().map(|s| s.to_string()).collect(),
+            include_patterns: include.iter().map(|s| s.to_string()).collect(),
+            ..default()
+        };
+        ScanRules::from_settings(&settings)
+    }
+
+    #[test]
+    fn test_hidden_files() {
+        let path = PathBuf::from("/project/.env");
+        assert!(!rules(&[], &[], false).allows(&path, false, &IgnoreStack::default()));
+        assert!(rules(&[], &[], true).allows(&path, false, &IgnoreStack::default()));
+    }
+
+    #[test]
+    fn test_exclude_patterns() {
+        let rules = rules(&["node_modules", "*.tmp"], &[], false);
+        let none = IgnoreStack::default();
+        assert!(!rules.allows(Path::new("/p/node_modules"), true, &none));
+        assert!(!rules.allows(Path::new("/p/build.tmp"), false, &none));
+        assert!(rules.allows(Path::new("/p/src"), true, &none));
+    }
+
+    #[test]
+    fn test_include_patterns_only_filter_files() {
+        let rules = rules(&[], &["*.rs"], false);
+        let none = IgnoreStack::default();
+        assert!(rules.allows(Path::new("/p/main.rs"), false, &none));
+        assert!(!rules.allows(Path::new("/p/notes.md"), false, &none));
+        assert!(rules.allows(Path::new("/p/docs"), true, &none));
+    }
+
+    #[test]
+    fn test_fingerprint_changes_with_rules() {
+        assert_eq!(
+            rules(&["target"], &[], false).fingerprint(),
+            rules(&["target"], &[], false).fingerprint()
+        );
+        assert_ne!(
+            rules(&["target"], &[], false).fingerprint(),
+            rules(&[], &[], false).fingerprint()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hardlinks_counted_once() {
+        let tmp = TestDir::new("hardlinks");
+        let dir = tmp.path();
+        let original = dir.join("a.bin");
+        fs::write(&original, [0u8; 64]).unwrap();
+        fs::hard_link(&original, dir.join("b.bin")).unwrap();
+
+        let mut settings = SidebarSettings::default();
+        let count = |settings: &SidebarSettings| {
+            let rules = ScanRules::from_settings(settings);
+            let mut bounds = rules.bounds(dir);
+            ["a.bin", "b.bin"]
+                .iter()
+                .filter(|name| bounds.first_link(&fs::metadata(dir.join(name)).unwrap()))
+                .count()
+        };
+
+        settings.dedup_hardlinks = true;
+        assert_eq!(count(&settings), 1);
+        settings.dedup_hardlinks = false;
+        assert_eq!(count(&settings), 2);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_cycles_not_descended() {
+        let tmp = TestDir::new("symlinks");
+        let dir = tmp.path();
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("sub/file.txt"), [0u8; 8]).unwrap();
+        std::os::unix::fs::symlink("..", dir.join("sub/up")).unwrap();
+        std::os::unix::fs::symlink("sub", dir.join("alias")).unwrap();
+
+        let settings = SidebarSettings {
+            follow_symlinks: true,
+            ..default()
+        };
+        let rules = ScanRules::from_settings(&settings);
+        let paths: Vec<PathBuf> = rules
+            .walker(dir)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .collect();
+
+        // The alias is walked, the links back up are listed but never entered
+        assert!(paths.contains(&dir.join("alias/file.txt")));
+        assert!(paths.contains(&dir.join("sub/up")));
+        assert!(!paths.iter().any(|p| p.parent().is_some_and(|p| p.ends_with("up"))));
+    }
+
+    #[test]
+    fn test_parse_pattern_list() {
+        assert_eq!(
+            parse_pattern_list("node_modules, target\n*.tmp,,"),
+            vec!["node_modules", "target", "*.tmp"]
+        );
+    }
+}
@@ -0,0 +1,744 @@
+//! Sharing the persistent cache between instances
+//!
+//! redb locks the cache file for a single process. The instance that opened
+//! it listens on a Unix socket next to it (`cache.sock`); instances started
+//! later send their reads and writes there instead of running without a cache.
+//!
+//! Each frame is a little-endian u32 length followed by the payload, whose
+//! first byte is the request tag. Reads get one response frame, writes none.
+//! A client that loses the owner keeps trying to reach it on later requests.
+
+use crate::resources::persistent_cache::{
+    CacheValidation, CacheWriteCommand, SizeRow, StampValue, StatsRow, read_history, read_rows,
+    read_session,
+};
+use crate::resources::{DiskUsage, SubtreeStats};
+use bevy::prelude::*;
+use crossbeam_channel::{Sender, TrySendError};
+use redb::Database;
+use std::ffi::{OsStr, OsString};
+use std::io::{self, Read, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, UNIX_EPOCH};
+
+/// How long a read waits for the owning instance before answering it as a miss
+const READ_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Larger frames are treated as a broken connection
+const MAX_FRAME: usize = 64 * 1024 * 1024;
+
+/// What a client asks the owning instance
+#[derive(Debug, Clone, PartialEq)]
+enum Request {
+    /// Size and stats rows under each cache key (one request per listing)
+    Lookup(Vec<Vec<u8>>),
+    /// Navigation history, newest first
+    History,
+    /// Back stack of the session opened at a folder, oldest first
+    Session(PathBuf),
+    /// Queue a write on the owner's writer thread (no response)
+    Write(CacheWriteCommand),
+}
+
+/// Listen on `socket` and answer other instances from `db`; their writes
+/// join `writes`, the owner's own writer queue
+pub(crate) fn serve(
+    socket: &Path,
+    db: Arc<RwLock<Database>>,
+    writes: Sender<CacheWriteCommand>,
+) -> io::Result<()> {
+    // The caller holds the database lock, so a leftover socket is from an
+    // instance that is gone
+    let _ = std::fs::remove_file(socket);
+    let listener = UnixListener::bind(socket)?;
+    std::fs::set_permissions(socket, std::fs::Permissions::from_mode(0o600))?;
+
+    std::thread::Builder::new()
+        .name("persistent-cache-server".into())
+        .spawn(move || {
+            for stream in listener.incoming().filter_map(Result::ok) {
+                let db = Arc::clone(&db);
+                let writes = writes.clone();
+                let _ = std::thread::Builder::new()
+                    .name("persistent-cache-peer".into())
+                    .spawn(move || serve_peer(stream, &db, &writes));
+            }
+        })?;
+    Ok(())
+}
+
+/// Answer one connected instance until it disconnects
+/// (answered as misses while the owner compacts the file)
+fn serve_peer(mut stream: UnixStream, db: &RwLock<Database>, writes: &Sender<CacheWriteCommand>) {
+    while let Ok(frame) = read_frame(&mut stream) {
+        let Some(request) = Request::decode(&frame) else {
+            warn!("Malformed request on the shared persistent cache, closing connection");
+            return;
+        };
+        let db = db.try_read().ok();
+        let mut response = Encoder::default();
+        match request {
+            Request::Lookup(keys) => response.rows(match db {
+                Some(db) => keys.iter().map(|key| read_rows(&db, key)).collect(),
+                None => vec![(None, None); keys.len()],
+            }),
+            Request::History => response.paths(&db.map(|db| read_history(&db)).unwrap_or_default()),
+            Request::Session(root) => {
+                response.paths(&db.map(|db| read_session(&db, &root)).unwrap_or_default())
+            }
+            Request::Write(cmd) => {
+                if let Err(TrySendError::Full(_)) = writes.try_send(cmd) {
+                    warn!("Persistent cache write channel full, dropping shared write");
+                }
+                continue;
+            }
+        }
+        if write_frame(&mut stream, &response.0).is_err() {
+            return;
+        }
+    }
+}
+
+/// Connection to the instance that owns the cache file
+pub(crate) struct SharedCacheClient {
+    /// Where the owner listens; lost streams are reopened here
+    socket: PathBuf,
+    /// Request/response stream for reads (writes have their own, see `connect`);
+    /// None until the next request reconnects
+    reads: Mutex<Option<UnixStream>>,
+    /// Cleared while the owner can't be reached
+    connected: Arc<AtomicBool>,
+}
+
+impl SharedCacheClient {
+    /// Connect to the owner listening on `socket`. Commands sent to the
+    /// returned channel are forwarded to it by a writer thread. If the owner
+    /// can't be reached yet, later requests try again.
+    pub(crate) fn connect(socket: &Path) -> io::Result<(Self, Sender<CacheWriteCommand>)> {
+        let connected = Arc::new(AtomicBool::new(true));
+        let mut client = Self {
+            socket: socket.to_path_buf(),
+            reads: Mutex::new(None),
+            connected: Arc::clone(&connected),
+        };
+        let reads = client.reconnect();
+        *client.reads.get_mut().unwrap_or_else(|e| e.into_inner()) = reads;
+
+        let (sender, receiver) = crossbeam_channel::bounded::<CacheWriteCommand>(64);
+        let socket = socket.to_path_buf();
+        std::thread::Builder::new()
+            .name("persistent-cache-writer".into())
+            .spawn(move || {
+                // Writes queued while the owner can't be reached are dropped
+                let mut writes: Option<UnixStream> = None;
+                while let Ok(cmd) = receiver.recv() {
+                    if writes.is_none() {
+                        writes = UnixStream::connect(&socket).ok();
+                    }
+                    let Some(stream) = writes.as_mut() else {
+                        continue;
+                    };
+                    if let Err(e) = write_frame(stream, &Request::Write(cmd).encode()) {
+                        warn!("Lost the instance sharing the persistent cache: {}", e);
+                        connected.store(false, Ordering::Relaxed);
+                        writes = None;
+                    }
+                }
+            })?;
+
+        Ok((client, sender))
+    }
+
+    /// False while the owning instance can't be reached
+    pub(crate) fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
+    }
+
+    /// Size and stats rows under each of `keys`, in order
+    pub(crate) fn rows(
+        &self,
+        keys: &[Vec<u8>],
+    ) -> Option<Vec<(Option<SizeRow>, Option<StatsRow>)>> {
+        let frame = self.request(Request::Lookup(keys.to_vec()))?;
+        Decoder(&frame)
+            .rows()
+            .filter(|rows| rows.len() == keys.len())
+    }
+
+    pub(crate) fn history(&self) -> Option<Vec<PathBuf>> {
+        let frame = self.request(Request::History)?;
+        Decoder(&frame).paths()
+    }
+
+    pub(crate) fn session(&self, root: &Path) -> Option<Vec<PathBuf>> {
+        let frame = self.request(Request::Session(root.to_path_buf()))?;
+        Decoder(&frame).paths()
+    }
+
+    /// Send a read and wait for its response, reconnecting first if the
+    /// last request lost the stream
+    fn request(&self, request: Request) -> Option<Vec<u8>> {
+        let mut reads = self.reads.lock().ok()?;
+        if reads.is_none() {
+            *reads = self.reconnect();
+        }
+        let stream = reads.as_mut()?;
+        match write_frame(stream, &request.encode()).and_then(|()| read_frame(stream)) {
+            Ok(frame) => Some(frame),
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                // Its late response would answer the next request, which gets a fresh stream
+                warn!(
+                    "Instance sharing the persistent cache is slow to answer: {}",
+                    e
+                );
+                *reads = None;
+                None
+            }
+            Err(e) => {
+                warn!("Lost the instance sharing the persistent cache: {}", e);
+                self.connected.store(false, Ordering::Relaxed);
+                *reads = None;
+                None
+            }
+        }
+    }
+
+    /// Open a read stream to the owner, logging when it becomes (un)reachable
+    fn reconnect(&self) -> Option<UnixStream> {
+        let stream = UnixStream::connect(&self.socket).and_then(|stream| {
+            stream.set_read_timeout(Some(READ_TIMEOUT))?;
+            Ok(stream)
+        });
+        match stream {
+            Ok(stream) => {
+                if !self.connected.swap(true, Ordering::Relaxed) {
+                    info!("Reconnected to the instance sharing the persistent cache");
+                }
+                Some(stream)
+            }
+            Err(e) => {
+                if self.connected.swap(false, Ordering::Relaxed) {
+                    warn!(
+                        "Can't reach the instance sharing the persistent cache: {}",
+                        e
+                    );
+                }
+                None
+            }
+        }
+    }
+}
+
+fn write_frame(stream: &mut impl Write, payload: &[u8]) -> io::Result<()> {
+    stream.write_all(&(payload.len() as u32).to_le_bytes())?;
+    stream.write_all(payload)
+}
+
+fn read_frame(stream: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut len = [0; 4];
+    stream.read_exact(&mut len)?;
+    let len = u32::from_le_bytes(len) as usize;
+    if len > MAX_FRAME {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "frame too large",
+        ));
+    }
+    let mut payload = vec![0; len];
+    stream.read_exact(&mut payload)?;
+    Ok(payload)
+}
+
+impl Request {
+    fn encode(&self) -> Vec<u8> {
+        let mut out = Encoder::default();
+        match self {
+            Request::Lookup(keys) => {
+                out.u8(0);
+                out.keys(keys);
+            }
+            Request::History => out.u8(1),
+            Request::Session(root) => {
+                out.u8(2);
+                out.path(root);
+            }
+            Request::Write(cmd) => {
+                out.u8(3);
+                out.command(cmd);
+            }
+        }
+        out.0
+    }
+
+    fn decode(frame: &[u8]) -> Option<Self> {
+        let mut input = Decoder(frame);
+        let request = match input.u8()? {
+            0 => Request::Lookup(input.keys()?),
+            1 => Request::History,
+            2 => Request::Session(input.path()?),
+            3 => Request::Write(input.command()?),
+            _ => return None,
+        };
+        // Trailing bytes mean the two sides disagree on the format
+        input.0.is_empty().then_some(request)
+    }
+}
+
+/// Appends fields to a payload
+#[derive(Default)]
+struct Encoder(Vec<u8>);
+
+impl Encoder {
+    fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn bytes(&mut self, value: &[u8]) {
+        self.u64(value.len() as u64);
+        self.0.extend_from_slice(value);
+    }
+
+    fn path(&mut self, value: &Path) {
+        self.bytes(value.as_os_str().as_bytes());
+    }
+
+    fn paths(&mut self, value: &[PathBuf]) {
+        self.u64(value.len() as u64);
+        for path in value {
+            self.path(path);
+        }
+    }
+
+    fn keys(&mut self, value: &[Vec<u8>]) {
+        self.u64(value.len() as u64);
+        for key in value {
+            self.bytes(key);
+        }
+    }
+
+    fn stamp(&mut self, (modified, changed, listing): StampValue) {
+        self.u64(modified);
+        self.u64(changed);
+        self.u64(listing);
+    }
+
+    fn usage(&mut self, usage: DiskUsage) {
+        self.u64(usage.apparent);
+        self.u64(usage.allocated);
+    }
+
+    fn validation(&mut self, validation: CacheValidation) {
+        self.u8(validation.ttl_secs.is_some() as u8);
+        self.u64(validation.ttl_secs.unwrap_or(0));
+        self.u8(validation.check_listing as u8);
+    }
+
+    fn size_row(&mut self, row: Option<SizeRow>) {
+        self.u8(row.is_some() as u8);
+        if let Some((apparent, allocated, timestamp, stamp)) = row {
+            self.u64(apparent);
+            self.u64(allocated);
+            self.u64(timestamp);
+            self.stamp(stamp);
+        }
+    }
+
+    fn stats_row(&mut self, row: Option<StatsRow>) {
+        self.u8(row.is_some() as u8);
+        if let Some((files, dirs, largest_bytes, largest_path, newest, timestamp, stamp)) = row {
+            self.u64(files);
+            self.u64(dirs);
+            self.u64(largest_bytes);
+            self.bytes(&largest_path);
+            self.u64(newest);
+            self.u64(timestamp);
+            self.stamp(stamp);
+        }
+    }
+
+    fn rows(&mut self, rows: Vec<(Option<SizeRow>, Option<StatsRow>)>) {
+        self.u64(rows.len() as u64);
+        for (size, stats) in rows {
+            self.size_row(size);
+            self.stats_row(stats);
+        }
+    }
+
+    fn command(&mut self, cmd: &CacheWriteCommand) {
+        match cmd {
+            CacheWriteCommand::WriteSize {
+                fingerprint,
+                path,
+                usage,
+                timestamp,
+                stamp,
+                validation,
+            } => {
+                self.u8(0);
+                self.u64(*fingerprint);
+                self.path(path);
+                self.usage(*usage);
+                self.u64(*timestamp);
+                self.stamp(*stamp);
+                self.validation(*validation);
+            }
+            CacheWriteCommand::WriteManifest {
+                fingerprint,
+                path,
+                files,
+                child_dirs,
+                timestamp,
+                validation,
+            } => {
+                self.u8(1);
+                self.u64(*fingerprint);
+                self.path(path);
+                self.usage(*files);
+                self.u64(child_dirs.len() as u64);
+                for name in child_dirs {
+                    self.bytes(name.as_bytes());
+                }
+                self.u64(*timestamp);
+                self.validation(*validation);
+            }
+            CacheWriteCommand::WriteStats {
+                fingerprint,
+                path,
+                stats,
+                timestamp,
+                stamp,
+            } => {
+                self.u8(2);
+                self.u64(*fingerprint);
+                self.path(path);
+                self.u64(stats.files);
+                self.u64(stats.dirs);
+                self.u8(stats.largest_file.is_some() as u8);
+                if let Some((largest, len)) = &stats.largest_file {
+                    self.path(largest);
+                    self.u64(*len);
+                }
+                let newest = stats.newest_modified.duration_since(UNIX_EPOCH);
+                self.u64(newest.map_or(0, |d| d.as_nanos() as u64));
+                self.u64(*timestamp);
+                self.stamp(*stamp);
+            }
+            CacheWriteCommand::WriteHistory { entries } => {
+                self.u8(3);
+                self.paths(entries);
+            }
+            CacheWriteCommand::WriteSession {
+                root,
+                back,
+                timestamp,
+            } => {
+                self.u8(4);
+                self.path(root);
+                self.paths(back);
+                self.u64(*timestamp);
+            }
+            CacheWriteCommand::Invalidate { fingerprint, paths } => {
+                self.u8(5);
+                self.u64(*fingerprint);
+                self.paths(paths);
+            }
+        }
+    }
+}
+
+/// Reads fields off the front of a payload (None once it runs short)
+struct Decoder<'a>(&'a [u8]);
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if len > self.0.len() {
+            return None;
+        }
+        let (head, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Some(head)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    fn flag(&mut self) -> Option<bool> {
+        Some(self.u8()? != 0)
+    }
+
+    fn bytes(&mut self) -> Option<&'a [u8]> {
+        let len = usize::try_from(self.u64()?).ok()?;
+        self.take(len)
+    }
+
+    fn os_string(&mut self) -> Option<OsString> {
+        Some(OsStr::from_bytes(self.bytes()?).to_os_string())
+    }
+
+    fn path(&mut self) -> Option<PathBuf> {
+        Some(PathBuf::from(self.os_string()?))
+    }
+
+    /// A length-prefixed list; the length is checked against what's left
+    /// (every item takes at least `min_item` bytes) before allocating
+    fn list<T>(
+        &mut self,
+        min_item: usize,
+        mut item: impl FnMut(&mut Self) -> Option<T>,
+    ) -> Option<Vec<T>> {
+        let len = usize::try_from(self.u64()?).ok()?;
+        if len > self.0.len() / min_item {
+            return None;
+        }
+        (0..len).map(|_| item(self)).collect()
+    }
+
+    fn paths(&mut self) -> Option<Vec<PathBuf>> {
+        self.list(8, Self::path)
+    }
+
+    fn keys(&mut self) -> Option<Vec<Vec<u8>>> {
+        self.list(8, |input| input.bytes().map(<[u8]>::to_vec))
+    }
+
+    /// Size and stats row per key (two flag bytes when both are missing)
+    fn rows(&mut self) -> Option<Vec<(Option<SizeRow>, Option<StatsRow>)>> {
+        self.list(2, |input| Some((input.size_row()?, input.stats_row()?)))
+    }
+
+    fn stamp(&mut self) -> Option<StampValue> {
+        Some((self.u64()?, self.u64()?, self.u64()?))
+    }
+
+    fn usage(&mut self) -> Option<DiskUsage> {
+        Some(DiskUsage {
+            apparent: self.u64()?,
+            allocated: self.u64()?,
+        })
+    }
+
+    fn validation(&mut self) -> Option<CacheValidation> {
+        let has_ttl = self.flag()?;
+        let ttl_secs = self.u64()?;
+        Some(CacheValidation {
+            ttl_secs: has_ttl.then_some(ttl_secs),
+            check_listing: self.flag()?,
+        })
+    }
+
+    /// Outer None: malformed; inner None: no row
+    fn size_row(&mut self) -> Option<Option<SizeRow>> {
+        if !self.flag()? {
+            return Some(None);
+        }
+        Some(Some((self.u64()?, self.u64()?, self.u64()?, self.stamp()?)))
+    }
+
+    /// Outer None: malformed; inner None: no row
+    fn stats_row(&mut self) -> Option<Option<StatsRow>> {
+        if !self.flag()? {
+            return Some(None);
+        }
+        Some(Some((
+            self.u64()?,
+            self.u64()?,
+            self.u64()?,
+            self.bytes()?.to_vec(),
+            self.u64()?,
+            self.u64()?,
+            self.stamp()?,
+        )))
+    }
+
+    fn command(&mut self) -> Option<CacheWriteCommand> {
+        let cmd = match self.u8()? {
+            0 => CacheWriteCommand::WriteSize {
+                fingerprint: self.u64()?,
+                path: self.path()?,
+                usage: self.usage()?,
+                timestamp: self.u64()?,
+                stamp: self.stamp()?,
+                validation: self.validation()?,
+            },
+            1 => CacheWriteCommand::WriteManifest {
+                fingerprint: self.u64()?,
+                path: self.path()?,
+                files: self.usage()?,
+                child_dirs: self.list(8, Self::os_string)?,
+                timestamp: self.u64()?,
+                validation: self.validation()?,
+            },
+            2 => CacheWriteCommand::WriteStats {
+                fingerprint: self.u64()?,
+                path: self.path()?,
+                stats: SubtreeStats {
+                    files: self.u64()?,
+                    dirs: self.u64()?,
+                    largest_file: if self.flag()? {
+                        Some((self.path()?, self.u64()?))
+                    } else {
+                        None
+                    },
+                    newest_modified: UNIX_EPOCH + Duration::from_nanos(self.u64()?),
+                },
+                timestamp: self.u64()?,
+                stamp: self.stamp()?,
+            },
+            3 => CacheWriteCommand::WriteHistory {
+                entries: self.paths()?,
+            },
+            4 => CacheWriteCommand::WriteSession {
+                root: self.path()?,
+                back: self.paths()?,
+                timestamp: self.u64()?,
+            },
+            5 => CacheWriteCommand::Invalidate {
+                fingerprint: self.u64()?,
+                paths: self.paths()?,
+            },
+            _ => return None,
+        };
+        Some(cmd)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_dir::TestDir;
+    use redb::backends::InMemoryBackend;
+
+    fn validation() -> CacheValidation {
+        CacheValidation {
+            ttl_secs: Some(3600),
+            check_listing: true,
+        }
+    }
+
+    #[test]
+    fn test_requests_round_trip() {
+        let requests = [
+            Request::Lookup(vec![b"\0\0\0\0\0\0\0\x01/a".to_vec(), Vec::new()]),
+            Request::History,
+            Request::Session(PathBuf::from("/a")),
+            Request::Write(CacheWriteCommand::WriteSession {
+                root: PathBuf::from("/a"),
+                back: vec![PathBuf::from("/a"), PathBuf::from("/a/b")],
+                timestamp: 5,
+            }),
+            Request::Write(CacheWriteCommand::WriteManifest {
+                fingerprint: 1,
+                path: PathBuf::from("/a"),
+                files: DiskUsage {
+                    apparent: 10,
+                    allocated: 4096,
+                },
+                child_dirs: vec!["b".into(), OsStr::from_bytes(b"\xff").into()],
+                timestamp: 5,
+                validation: validation(),
+            }),
+            Request::Write(CacheWriteCommand::WriteStats {
+                fingerprint: 1,
+                path: PathBuf::from("/a"),
+                stats: SubtreeStats {
+                    files: 3,
+                    dirs: 1,
+                    largest_file: Some((PathBuf::from("/a/big"), 1 << 20)),
+                    newest_modified: UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+                },
+                timestamp: 5,
+                stamp: (1, 2, 3),
+            }),
+            Request::Write(CacheWriteCommand::WriteSize {
+                fingerprint: 1,
+                path: PathBuf::from("/a"),
+                usage: DiskUsage {
+                    apparent: 10,
+                    allocated: 4096,
+                },
+                timestamp: 5,
+                stamp: (1, 2, 3),
+                validation: validation(),
+            }),
+            Request::Write(CacheWriteCommand::WriteHistory {
+                entries: vec![PathBuf::from("/a/b"), PathBuf::from("/a")],
+            }),
+            Request::Write(CacheWriteCommand::Invalidate {
+                fingerprint: 1,
+                // Not valid UTF-8: paths travel as raw bytes
+                paths: vec![PathBuf::from(OsStr::from_bytes(b"/a/\xff"))],
+            }),
+        ];
+        for request in requests {
+            assert_eq!(Request::decode(&request.encode()), Some(request));
+        }
+
+        let truncated = Request::Lookup(vec![b"key".to_vec()]).encode();
+        assert_eq!(Request::decode(&truncated[..truncated.len() - 1]), None);
+    }
+
+    #[test]
+    fn test_client_goes_through_owner() {
+        let dir = TestDir::new("share");
+        let socket = dir.join("cache.sock");
+        let db = Database::builder()
+            .create_with_backend(InMemoryBackend::new())
+            .unwrap();
+        let (writes, queued) = crossbeam_channel::unbounded();
+        serve(&socket, Arc::new(RwLock::new(db)), writes).unwrap();
+
+        let (client, sender) = SharedCacheClient::connect(&socket).unwrap();
+        assert_eq!(
+            client.rows(&[b"missing".to_vec()]),
+            Some(vec![(None, None)])
+        );
+        assert_eq!(client.history(), Some(Vec::new()));
+        assert!(client.is_connected());
+
+        let cmd = CacheWriteCommand::WriteHistory {
+            entries: vec![PathBuf::from("/a")],
+        };
+        sender.send(cmd.clone()).unwrap();
+        assert_eq!(queued.recv_timeout(READ_TIMEOUT), Ok(cmd));
+    }
+
+    #[test]
+    fn test_client_retries_until_owner_listens() {
+        let dir = TestDir::new("share-retry");
+        let socket = dir.join("cache.sock");
+
+        // Nobody listening yet: reads are misses, not a dead client
+        let (client, _sender) = SharedCacheClient::connect(&socket).unwrap();
+        assert!(!client.is_connected());
+        assert_eq!(client.history(), None);
+
+        let db = Database::builder()
+            .create_with_backend(InMemoryBackend::new())
+            .unwrap();
+        let (writes, _queued) = crossbeam_channel::unbounded();
+        serve(&socket, Arc::new(RwLock::new(db)), writes).unwrap();
+
+        let keys = [b"a".to_vec(), b"b".to_vec()];
+        assert_eq!(client.rows(&keys), Some(vec![(None, None), (None, None)]));
+        assert!(client.is_connected());
+    }
+}
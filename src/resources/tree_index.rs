@@ -0,0 +1,515 @@
+//! Tree index resources
+//!
+//! In-memory index of the opened root, built once by a background scan.
+//! Listings and aggregated sizes below the root are served from here,
+//! so drilldowns no longer touch the filesystem.
+
+#![allow(dead_code)]
+
+use crate::resources::{DiskUsage, FileEntry, ScanError, ScanRules, SubtreeStats, SymlinkInfo};
+use crate::systems::size_calculation::CancelFlag;
+use bevy::prelude::*;
+use bevy::tasks::{IoTaskPool, Task};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Position of a node in `TreeIndex::nodes`
+pub type NodeId = usize;
+
+/// Single file or directory in the index
+///
+/// Nodes only store their name; paths are rebuilt from the parent chain,
+/// so the index doesn't keep a full path per entry.
+#[derive(Debug, Clone)]
+pub struct IndexNode {
+    /// File or directory name (relative to the parent)
+    pub name: OsString,
+    /// True if directory
+    pub is_directory: bool,
+    /// True if the directory's children were walked (symlinks only when followed)
+    pub scanned: bool,
+    /// True once a change below made `usage` and `stats` out of date
+    pub stale: bool,
+    /// File size, or aggregated subtree size for directories
+    pub usage: DiskUsage,
+    /// Own modification time
+    pub modified: SystemTime,
+    /// Recursive counts (a file counts itself)
+    pub stats: SubtreeStats,
+    /// Direct children, sorted by name
+    pub children: Vec<NodeId>,
+    /// Link details if this node is a symlink
+    pub symlink: Option<SymlinkInfo>,
+}
+
+impl IndexNode {
+    fn from_metadata(path: &Path, metadata: &fs::Metadata, scanned: bool) -> Self {
+        let name = path
+            .file_name()
+            .map(|n| n.to_os_string())
+            .unwrap_or_else(|| OsString::from("/"));
+        let is_directory = metadata.is_dir();
+        let modified = metadata.modified().unwrap_or(UNIX_EPOCH);
+        let mut stats = SubtreeStats::default();
+        if is_directory {
+            stats.touch(metadata);
+        } else {
+            stats.add_entry(path, metadata);
+        }
+
+        Self {
+            name,
+            is_directory,
+            scanned: is_directory && scanned,
+            stale: false,
+            symlink: None,
+            usage: DiskUsage::from_metadata(metadata),
+            modified,
+            stats,
+            children: Vec::new(),
+        }
+    }
+
+    fn to_file_entry(&self, path: PathBuf) -> FileEntry {
+        FileEntry {
+            name: self.name.to_string_lossy().to_string(),
+            path,
+            usage: self.usage,
+            modified: self.modified,
+            is_directory: self.is_directory,
+            child_count: self.scanned.then_some(self.children.len()),
+            symlink: self.symlink.clone(),
+        }
+    }
+}
+
+/// Recursive index of the opened root with per-node aggregates
+#[derive(Resource, Debug, Default)]
+pub struct TreeIndex {
+    /// Root the index was built for (None until a scan completes)
+    root: Option<PathBuf>,
+    /// All indexed nodes; the root is the first one
+    nodes: Vec<IndexNode>,
+    /// Entries the scan couldn't read (handed to `ScanErrors` once installed)
+    errors: Vec<ScanError>,
+}
+
+impl TreeIndex {
+    /// Walk `root` and build the index (blocking, run on a background task)
+    ///
+    /// Returns None if `cancel` was set before the walk finished.
+    pub fn build(root: &Path, rules: &ScanRules, cancel: &CancelFlag) -> Option<Self> {
+        let mut nodes: Vec<IndexNode> = Vec::new();
+        // Depth and parent directory of each node, until children are linked
+        let mut placement: Vec<(usize, Arc<Path>)> = Vec::new();
+        // Nodes whose children the walk yields, by path (dropped after the build)
+        let mut directories: HashMap<PathBuf, NodeId> = HashMap::new();
+        // The walk is parallel, so each deduplicated inode is owned by its
+        // smallest path rather than whichever link the walk meets first
+        let mut owners: HashMap<(u64, u64), (PathBuf, NodeId)> = HashMap::new();
+        let mut links: Vec<((u64, u64), NodeId)> = Vec::new();
+        let bounds = rules.bounds(root);
+        let mut errors = Vec::new();
+
+        for entry in rules.walker(root) {
+            if cancel.is_cancelled() {
+                info!("Tree index scan of {} cancelled", root.display());
+                return None;
+            }
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    errors.push(ScanError::from_walk(&e, root));
+                    continue;
+                }
+            };
+            let path = entry.path();
+            let id = nodes.len();
+            let is_link = entry.depth > 0 && entry.file_type().is_symlink();
+            let metadata = if entry.depth == 0 {
+                fs::metadata(&path).map_err(|e| ScanError::from_io(&path, &e))
+            } else {
+                entry.metadata().map_err(|e| ScanError::from_walk(&e, &path))
+            };
+            let metadata = match metadata {
+                Ok(metadata) => metadata,
+                Err(error) => {
+                    errors.push(error);
+                    continue;
+                }
+            };
+            // Listed, but its children are unknown
+            let unreadable = match &entry.read_children_error {
+                Some(e) => {
+                    errors.push(ScanError::from_walk(e, &path));
+                    true
+                }
+                None => false,
+            };
+
+            let node = if is_link {
+                // Listed as a link; the walker descends it (and we count its
+                // target) only when following symlinks and it isn't a cycle.
+                // Unfollowed links add nothing to the totals, as in size jobs.
+                let symlink = SymlinkInfo::read(&path);
+                let descended = entry.read_children_path.is_some();
+                let mut node = IndexNode::from_metadata(&path, &metadata, false);
+                node.usage = DiskUsage::default();
+                node.stats = SubtreeStats::default();
+                let target = if rules.follow_symlinks {
+                    fs::metadata(&path).ok()
+                } else {
+                    None
+                };
+                if let Some(target) = target {
+                    node.stats.add_entry(&path, &target);
+                    if target.is_file() {
+                        if let Some(key) = bounds.link_key(&target) {
+                            claim(&mut owners, key, &path, id);
+                            links.push((key, id));
+                        }
+                        node.usage = DiskUsage::from_metadata(&target);
+                    }
+                }
+                node.scanned = descended && !unreadable;
+                node.symlink = symlink;
+                node
+            } else {
+                let key = if metadata.is_file() {
+                    bounds.link_key(&metadata)
+                } else {
+                    None
+                };
+                if let Some(key) = key {
+                    claim(&mut owners, key, &path, id);
+                    links.push((key, id));
+                }
+                IndexNode::from_metadata(&path, &metadata, !unreadable)
+            };
+            if entry.read_children_path.is_some() {
+                directories.insert(path, id);
+            }
+            placement.push((entry.depth, entry.parent_path.clone()));
+            nodes.push(node);
+        }
+
+        // Extra links to an inode owned elsewhere (listed, but add nothing to totals)
+        let extra_links: HashSet<NodeId> = links
+            .into_iter()
+            .filter(|(key, id)| owners.get(key).is_none_or(|(_, owner)| owner != id))
+            .map(|(_, id)| id)
+            .collect();
+
+        // Link children to parents
+        let mut parents: Vec<Option<NodeId>> = vec![None; nodes.len()];
+        for (id, (depth, parent_path)) in placement.iter().enumerate() {
+            if *depth == 0 {
+                continue;
+            }
+            if let Some(&parent) = directories.get(parent_path.as_ref()) {
+                nodes[parent].children.push(id);
+                parents[id] = Some(parent);
+            }
+        }
+        drop(directories);
+        for id in 0..nodes.len() {
+            let mut children = std::mem::take(&mut nodes[id].children);
+            children.sort_by(|a, b| nodes[*a].name.cmp(&nodes[*b].name));
+            nodes[id].children = children;
+        }
+
+        // Aggregate bottom-up: deepest nodes first, so each directory is
+        // complete before it contributes to its parent
+        let mut order: Vec<(usize, NodeId)> = placement
+            .iter()
+            .enumerate()
+            .map(|(id, (depth, _))| (*depth, id))
+            .collect();
+        order.sort_by_key(|(depth, _)| Reverse(*depth));
+        for (_, id) in order {
+            let Some(parent) = parents[id] else {
+                continue;
+            };
+            let usage = if extra_links.contains(&id) {
+                DiskUsage::default()
+            } else {
+                nodes[id].usage
+            };
+            let stats = nodes[id].stats.clone();
+            let is_directory = nodes[id].is_directory;
+
+            let parent = &mut nodes[parent];
+            parent.usage += usage;
+            parent.stats.merge(&stats);
+            parent.stats.dirs += u64::from(is_directory);
+        }
+
+        info!(
+            "Tree index built for {} ({} nodes, {} unreadable)",
+            root.display(),
+            nodes.len(),
+            errors.len()
+        );
+
+        Some(Self {
+            root: Some(root.to_path_buf()),
+            nodes,
+            errors,
+        })
+    }
+
+    /// Take the errors met while building (left empty afterwards)
+    pub fn take_errors(&mut self) -> Vec<ScanError> {
+        std::mem::take(&mut self.errors)
+    }
+
+    /// Root this index covers
+    pub fn root(&self) -> Option<&Path> {
+        self.root.as_deref()
+    }
+
+    /// Number of indexed nodes
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Find the node for `path` by walking down from the root
+    fn find(&self, path: &Path) -> Option<NodeId> {
+        let relative = path.strip_prefix(self.root.as_deref()?).ok()?;
+        if self.nodes.is_empty() {
+            return None;
+        }
+        let mut id = 0;
+        for component in relative.components() {
+            let Component::Normal(name) = component else {
+                return None;
+            };
+            let children = &self.nodes[id].children;
+            let position = children
+                .binary_search_by(|child| self.nodes[*child].name.as_os_str().cmp(name))
+                .ok()?;
+            id = children[position];
+        }
+        Some(id)
+    }
+
+    /// Look up a node by path
+    pub fn get(&self, path: &Path) -> Option<&IndexNode> {
+        self.find(path).map(|id| &self.nodes[id])
+    }
+
+    /// Aggregated size of a fully indexed directory
+    pub fn directory_size(&self, path: &Path) -> Option<DiskUsage> {
+        self.get(path)
+            .filter(|n| n.scanned && !n.stale)
+            .map(|n| n.usage)
+    }
+
+    /// Recursive counts of a fully indexed directory
+    pub fn directory_stats(&self, path: &Path) -> Option<SubtreeStats> {
+        self.get(path)
+            .filter(|n| n.scanned && !n.stale)
+            .map(|n| n.stats.clone())
+    }
+
+    /// Forget what a change at `path` made out of date
+    ///
+    /// The path itself and the directory it is in are listed again; the
+    /// ancestors above keep their listings and only lose their aggregates,
+    /// so sizes along the chain come from fresh walks again.
+    pub fn invalidate(&mut self, path: &Path) {
+        let relisted = std::iter::once(path).chain(path.parent());
+        for listed in relisted {
+            if let Some(id) = self.find(listed) {
+                self.nodes[id].scanned = false;
+            }
+        }
+        for ancestor in path.ancestors() {
+            if let Some(id) = self.find(ancestor) {
+                self.nodes[id].stale = true;
+            }
+        }
+    }
+
+    /// Direct children of an indexed directory (unsorted)
+    pub fn entries(&self, path: &Path) -> Option<Vec<FileEntry>> {
+        let node = self.get(path).filter(|n| n.scanned)?;
+        Some(
+            node.children
+                .iter()
+                .map(|child| {
+                    let child = &self.nodes[*child];
+                    child.to_file_entry(path.join(&child.name))
+                })
+                .collect(),
+        )
+    }
+
+    /// Number of direct children of an indexed directory
+    pub fn child_count(&self, path: &Path) -> Option<usize> {
+        self.get(path)
+            .filter(|n| n.scanned)
+            .map(|n| n.children.len())
+    }
+}
+
+/// Record node `id` at `path` as the owner of `key` if it sorts before the current owner
+fn claim(
+    owners: &mut HashMap<(u64, u64), (PathBuf, NodeId)>,
+    key: (u64, u64),
+    path: &Path,
+    id: NodeId,
+) {
+    match owners.get_mut(&key) {
+        Some((owner, _)) if owner.as_path() <= path => {}
+        Some(owner) => *owner = (path.to_path_buf(), id),
+        None => {
+            owners.insert(key, (path.to_path_buf(), id));
+        }
+    }
+}
+
+/// Background tree index scan
+#[derive(Resource, Default)]
+pub struct TreeIndexTask {
+    /// Running scan (None when idle); yields None if it was cancelled
+    pub task: Option<Task<Option<TreeIndex>>>,
+    /// Root of the most recently started scan
+    pub root: Option<PathBuf>,
+    /// Cancellation flag of the running scan
+    cancel: CancelFlag,
+}
+
+impl TreeIndexTask {
+    /// Start scanning a new root, cancelling any scan in progress
+    pub fn start(&mut self, root: PathBuf, rules: ScanRules) {
+        self.cancel.cancel();
+        self.cancel = CancelFlag::default();
+        self.root = Some(root.clone());
+        let cancel = self.cancel.clone();
+        let task = IoTaskPool::get().spawn(async move { TreeIndex::build(&root, &rules, &cancel) });
+        self.task = Some(task);
+    }
+
+    /// True while a scan is running
+    pub fn is_running(&self) -> bool {
+        self.task.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::SidebarSettings;
+    use crate::utils::test_dir::TestDir;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hardlink_owned_by_first_sorted_path() {
+        let tmp = TestDir::new("index-hardlinks");
+        let root = tmp.path();
+        fs::create_dir_all(root.join("a")).unwrap();
+        fs::create_dir_all(root.join("b")).unwrap();
+        fs::write(root.join("b/data.bin"), [0u8; 4096]).unwrap();
+        fs::hard_link(root.join("b/data.bin"), root.join("a/data.bin")).unwrap();
+
+        let rules = ScanRules::from_settings(&SidebarSettings {
+            dedup_hardlinks: true,
+            ..default()
+        });
+
+        // Same attribution on every build, whatever order the walk meets the links
+        for _ in 0..4 {
+            let index = TreeIndex::build(root, &rules, &CancelFlag::default()).unwrap();
+            let a = index.directory_size(&root.join("a")).unwrap();
+            let b = index.directory_size(&root.join("b")).unwrap();
+            assert_eq!(a.apparent, 4096);
+            assert_eq!(b.apparent, 0);
+            assert_eq!(index.directory_size(root).unwrap().apparent, 4096);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks_count_only_when_followed() {
+        let tmp = TestDir::new("index-symlinks");
+        let root = tmp.path();
+        fs::create_dir_all(root.join("data/sub")).unwrap();
+        fs::create_dir_all(root.join("links")).unwrap();
+        fs::write(root.join("data/file.bin"), [0u8; 4096]).unwrap();
+        std::os::unix::fs::symlink(root.join("data/file.bin"), root.join("links/file")).unwrap();
+        std::os::unix::fs::symlink(root.join("data/sub"), root.join("links/dir")).unwrap();
+
+        let unfollowed =
+            TreeIndex::build(root, &ScanRules::default(), &CancelFlag::default()).unwrap();
+        let links = root.join("links");
+        assert_eq!(unfollowed.directory_size(&links).unwrap().apparent, 0);
+        let stats = unfollowed.directory_stats(&links).unwrap();
+        assert_eq!((stats.files, stats.dirs), (0, 0));
+
+        let rules = ScanRules::from_settings(&SidebarSettings {
+            follow_symlinks: true,
+            dedup_hardlinks: false,
+            ..default()
+        });
+        let followed = TreeIndex::build(root, &rules, &CancelFlag::default()).unwrap();
+        assert_eq!(followed.directory_size(&links).unwrap().apparent, 4096);
+        let stats = followed.directory_stats(&links).unwrap();
+        assert_eq!((stats.files, stats.dirs), (1, 1));
+    }
+
+    #[test]
+    fn test_invalidate_keeps_unrelated_listings() {
+        let tmp = TestDir::new("index-invalidate");
+        let root = tmp.path();
+        fs::create_dir_all(root.join("a/deep")).unwrap();
+        fs::create_dir_all(root.join("b")).unwrap();
+        fs::write(root.join("a/deep/file.txt"), [0u8; 16]).unwrap();
+        fs::write(root.join("b/file.txt"), [0u8; 16]).unwrap();
+
+        let mut index =
+            TreeIndex::build(root, &ScanRules::default(), &CancelFlag::default()).unwrap();
+        index.invalidate(&root.join("a/deep/file.txt"));
+
+        // The directory the change happened in is read again
+        assert!(index.entries(&root.join("a/deep")).is_none());
+        // Ancestors keep their listings but not their totals
+        assert!(index.entries(&root.join("a")).is_some());
+        assert!(index.entries(root).is_some());
+        assert!(index.directory_size(&root.join("a")).is_none());
+        assert!(index.directory_size(root).is_none());
+        // Siblings are untouched
+        assert!(index.directory_size(&root.join("b")).is_some());
+    }
+
+    #[test]
+    fn test_cancelled_build_returns_nothing() {
+        let tmp = TestDir::new("index-cancel");
+        let root = tmp.path();
+        fs::create_dir_all(root.join("a")).unwrap();
+
+        let cancel = CancelFlag::default();
+        cancel.cancel();
+        assert!(TreeIndex::build(root, &ScanRules::default(), &cancel).is_none());
+    }
+
+    #[test]
+    fn test_entries_rebuild_paths_from_names() {
+        let tmp = TestDir::new("index-paths");
+        let root = tmp.path();
+        fs::create_dir_all(root.join("a/b")).unwrap();
+        fs::write(root.join("a/b/file.txt"), [0u8; 8]).unwrap();
+
+        let index = TreeIndex::build(root, &ScanRules::default(), &CancelFlag::default()).unwrap();
+        let entries = index.entries(&root.join("a/b")).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].name, "file.txt");
+        assert_eq!(entries[0].path, root.join("a/b/file.txt"));
+        assert!(index.get(&root.join("a/missing")).is_none());
+    }
+}
@@ -2,6 +2,7 @@
 //!
 //! Track UI interaction state and layout.
 
+use crate::resources::{CacheMaintenance, CacheSummary};
 use bevy::prelude::*;
 use bevy::tasks::Task;
 use std::path::PathBuf;
@@ -25,6 +26,10 @@ pub struct UiState {
     pub selected_entity: Option<Entity>,
     /// Current main area view
     pub main_view: MainView,
+    /// Planet search text (breadcrumb field)
+    pub search: String,
+    /// Move keyboard focus to the search field on the next frame
+    pub focus_search: bool,
 }
 
 /// UI layout dimensions
@@ -62,13 +67,59 @@ pub struct FileDialogTask {
     pub task: Option<Task<Option<PathBuf>>>,
 }
 
+/// Storage cache section of the settings page
+#[derive(Resource, Default)]
+pub struct CacheManagement {
+    /// Cache file contents as of the last finished action
+    pub summary: Option<CacheSummary>,
+    /// Action clicked in the UI, started by `run_cache_maintenance`
+    pub requested: Option<CacheMaintenance>,
+    /// Running action and its task
+    pub running: Option<(CacheMaintenance, Task<Result<CacheSummary, String>>)>,
+    /// Export/import file picker (resolves to the action to run)
+    pub dialog: Option<Task<Option<CacheMaintenance>>>,
+    /// Outcome of the last action
+    pub message: Option<String>,
+}
+
+impl CacheManagement {
+    /// An action or file picker is in progress
+    pub fn is_busy(&self) -> bool {
+        self.requested.is_some() || self.running.is_some() || self.dialog.is_some()
+    }
+}
+
 /// Sidebar settings (user preferences)
-#[derive(Resource, Debug)]
+#[derive(Resource, Debug, Clone, PartialEq)]
 pub struct SidebarSettings {
     /// Max history entries displayed (range: 10-30)
     pub history_limit: usize,
     /// Show hidden files (dotfiles) in visualization
     pub show_hidden_files: bool,
+    /// Refine file categories from the files' first bytes (opt-in: it opens
+    /// every unclassified file shown)
+    pub sniff_file_contents: bool,
+    /// Glob patterns excluded from listings and size totals (e.g. `node_modules`, `*.tmp`)
+    pub exclude_patterns: Vec<String>,
+    /// Glob patterns files must match to be shown (empty = all files)
+    pub include_patterns: Vec<String>,
+    /// Skip entries ignored by `.gitignore`
+    pub respect_gitignore: bool,
+    /// Count each hardlinked inode once per total
+    pub dedup_hardlinks: bool,
+    /// Don't cross into other mounted filesystems
+    pub one_filesystem: bool,
+    /// Drill into and size symlinked directories
+    pub follow_symlinks: bool,
+    /// Directory size calculations run in parallel (range: 1-16)
+    pub max_size_jobs: usize,
+    /// Cached directory sizes expire after this many hours (range: 1-168)
+    pub cache_ttl_hours: u64,
+    /// Keep cached sizes while their directory is unchanged, however old
+    /// (changes in nested folders made while the app is closed are missed)
+    pub cache_never_expire: bool,
+    /// Also revalidate cached sizes against the direct children (names, sizes, mtimes)
+    pub cache_check_listing: bool,
 }
 
 impl Default for SidebarSettings {
@@ -76,6 +127,17 @@ impl Default for SidebarSettings {
         Self {
             history_limit: 10,
             show_hidden_files: false,
+            sniff_file_contents: false,
+            exclude_patterns: Vec::new(),
+            include_patterns: Vec::new(),
+            respect_gitignore: false,
+            dedup_hardlinks: true,
+            one_filesystem: false,
+            follow_symlinks: false,
+            max_size_jobs: 4,
+            cache_ttl_hours: 1,
+            cache_never_expire: false,
+            cache_check_listing: false,
         }
     }
 }
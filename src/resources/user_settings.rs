@@ -0,0 +1,431 @@
+//! User settings persistence
+//!
+//! Preferences from the settings page (plus the camera config) are saved
+//! to `settings.toml` in the platform config directory and restored at
+//! startup. Saves wait until the settings stop changing (a slider drag is
+//! one write). Missing or invalid keys keep their defaults, out of range
+//! values are clamped. The visual
+//! encoding itself lives in `closm-probe.toml` (see `encoding_file`).
+
+use crate::resources::{CameraConfig, SidebarSettings, SizeMode, ThemeConfig, VisualConfig};
+use bevy::prelude::*;
+use std::fmt::Write as _;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use toml_edit::{DocumentMut, Item, TableLike};
+
+/// Seconds without further changes before the settings are written
+const SAVE_DELAY_SECS: f32 = 0.5;
+
+/// Where settings are saved, and what was last written there
+#[derive(Resource, Debug)]
+pub struct SettingsStore {
+    /// `settings.toml` (None without a config directory)
+    path: Option<PathBuf>,
+    /// File contents as last loaded or saved, so unchanged settings aren't rewritten
+    saved: String,
+    /// When the settings last changed without being saved (seconds since startup)
+    changed_at: Option<f32>,
+}
+
+impl Default for SettingsStore {
+    fn default() -> Self {
+        let path = directories::ProjectDirs::from("", "", "closm-probe")
+            .map(|dirs| dirs.config_dir().join("settings.toml"));
+        Self {
+            path,
+            saved: String::new(),
+            changed_at: None,
+        }
+    }
+}
+
+impl SettingsStore {
+    /// Apply the saved settings. Ok(false) if there is no file yet.
+    pub fn load(
+        &mut self,
+        sidebar: &mut SidebarSettings,
+        theme: &mut ThemeConfig,
+        visual: &mut VisualConfig,
+        camera: &mut CameraConfig,
+    ) -> Result<bool, String> {
+        let Some(path) = &self.path else {
+            return Ok(false);
+        };
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(format!("can't read {}: {}", path.display(), e)),
+        };
+        let doc: DocumentMut = text
+            .parse()
+            .map_err(|e| format!("{} is not valid TOML: {}", path.display(), e))?;
+        apply(&doc, sidebar, theme, visual, camera);
+        self.saved = text;
+        Ok(true)
+    }
+
+    /// Note a change at `now` (seconds since startup); the save waits for more
+    pub fn mark_changed(&mut self, now: f32) {
+        self.changed_at = Some(now);
+    }
+
+    /// True once a change has gone `SAVE_DELAY_SECS` without another
+    pub fn save_due(&self, now: f32) -> bool {
+        self.changed_at
+            .is_some_and(|changed_at| now - changed_at >= SAVE_DELAY_SECS)
+    }
+
+    /// Write the settings if they differ from the file
+    pub fn save(
+        &mut self,
+        sidebar: &SidebarSettings,
+        theme: &ThemeConfig,
+        visual: &VisualConfig,
+        camera: &CameraConfig,
+    ) -> Result<(), String> {
+        self.changed_at = None;
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let text = render(sidebar, theme, visual, camera);
+        if text == self.saved {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("can't create {}: {}", parent.display(), e))?;
+        }
+        std::fs::write(path, &text)
+            .map_err(|e| format!("can't write {}: {}", path.display(), e))?;
+        self.saved = text;
+        Ok(())
+    }
+
+    /// Location of the settings file
+    pub fn path(&self) -> Option<&PathBuf> {
+        self.path.as_ref()
+    }
+}
+
+/// Settings file contents
+fn render(
+    sidebar: &SidebarSettings,
+    theme: &ThemeConfig,
+    visual: &VisualConfig,
+    camera: &CameraConfig,
+) -> String {
+    let mut out = String::from("# CLOSM Probe settings, saved from the settings page\n");
+
+    out.push_str("\n[sidebar]\n");
+    let _ = writeln!(out, "history_limit = {}", sidebar.history_limit);
+    let _ = writeln!(out, "show_hidden_files = {}", sidebar.show_hidden_files);
+    let _ = writeln!(out, "sniff_file_contents = {}", sidebar.sniff_file_contents);
+    let _ = writeln!(
+        out,
+        "exclude_patterns = {}",
+        string_array(&sidebar.exclude_patterns)
+    );
+    let _ = writeln!(
+        out,
+        "include_patterns = {}",
+        string_array(&sidebar.include_patterns)
+    );
+    let _ = writeln!(out, "respect_gitignore = {}", sidebar.respect_gitignore);
+    let _ = writeln!(out, "dedup_hardlinks = {}", sidebar.dedup_hardlinks);
+    let _ = writeln!(out, "one_filesystem = {}", sidebar.one_filesystem);
+    let _ = writeln!(out, "follow_symlinks = {}", sidebar.follow_symlinks);
+    let _ = writeln!(out, "max_size_jobs = {}", sidebar.max_size_jobs);
+    let _ = writeln!(out, "cache_ttl_hours = {}", sidebar.cache_ttl_hours);
+    let _ = writeln!(out, "cache_never_expire = {}", sidebar.cache_never_expire);
+    let _ = writeln!(out, "cache_check_listing = {}", sidebar.cache_check_listing);
+
+    out.push_str("\n[theme]\n");
+    let _ = writeln!(out, "dark_mode = {}", theme.dark_mode);
+
+    out.push_str("\n[visual]\n");
+    let _ = writeln!(out, "size_mode = \"{}\"", size_mode_name(visual.size_mode));
+
+    out.push_str("\n[camera]\n");
+    let _ = writeln!(out, "zoom_min = {:?}", camera.zoom_min);
+    let _ = writeln!(out, "zoom_max = {:?}", camera.zoom_max);
+    let _ = writeln!(out, "pitch_limit = {:?}", camera.pitch_limit);
+    let _ = writeln!(out, "drilldown_duration = {:?}", camera.drilldown_duration);
+    let _ = writeln!(out, "return_duration = {:?}", camera.return_duration);
+    let _ = writeln!(out, "reset_duration = {:?}", camera.reset_duration);
+    out
+}
+
+/// Read what `render` wrote into the resources
+fn apply(
+    doc: &DocumentMut,
+    sidebar: &mut SidebarSettings,
+    theme: &mut ThemeConfig,
+    visual: &mut VisualConfig,
+    camera: &mut CameraConfig,
+) {
+    let s = Section::new(doc, "sidebar");
+    s.usize("history_limit", 10..=30, &mut sidebar.history_limit);
+    s.bool("show_hidden_files", &mut sidebar.show_hidden_files);
+    s.bool("sniff_file_contents", &mut sidebar.sniff_file_contents);
+    s.strings("exclude_patterns", &mut sidebar.exclude_patterns);
+    s.strings("include_patterns", &mut sidebar.include_patterns);
+    s.bool("respect_gitignore", &mut sidebar.respect_gitignore);
+    s.bool("dedup_hardlinks", &mut sidebar.dedup_hardlinks);
+    s.bool("one_filesystem", &mut sidebar.one_filesystem);
+    s.bool("follow_symlinks", &mut sidebar.follow_symlinks);
+    s.usize("max_size_jobs", 1..=16, &mut sidebar.max_size_jobs);
+    let mut ttl = sidebar.cache_ttl_hours as usize;
+    s.usize("cache_ttl_hours", 1..=168, &mut ttl);
+    sidebar.cache_ttl_hours = ttl as u64;
+    s.bool("cache_never_expire", &mut sidebar.cache_never_expire);
+    s.bool("cache_check_listing", &mut sidebar.cache_check_listing);
+
+    let s = Section::new(doc, "theme");
+    if s.bool("dark_mode", &mut theme.dark_mode) {
+        theme.apply_mode();
+    }
+
+    let s = Section::new(doc, "visual");
+    if let Some(name) = s.get("size_mode").and_then(Item::as_str) {
+        match [SizeMode::Apparent, SizeMode::Allocated, SizeMode::Both]
+            .into_iter()
+            .find(|mode| size_mode_name(*mode) == name)
+        {
+            Some(mode) => visual.size_mode = mode,
+            None => warn!("Ignoring unknown setting visual.size_mode = {:?}", name),
+        }
+    }
+
+    let s = Section::new(doc, "camera");
+    s.f32_in("zoom_min", 1.0..=50.0, &mut camera.zoom_min);
+    s.f32_in("zoom_max", 5.0..=500.0, &mut camera.zoom_max);
+    camera.zoom_max = camera.zoom_max.max(camera.zoom_min);
+    // Short of 90°, where the orbit camera flips
+    s.f32_in("pitch_limit", 0.0..=89.0, &mut camera.pitch_limit);
+    s.f32_in(
+        "drilldown_duration",
+        0.05..=5.0,
+        &mut camera.drilldown_duration,
+    );
+    s.f32_in("return_duration", 0.05..=5.0, &mut camera.return_duration);
+    s.f32_in("reset_duration", 0.05..=5.0, &mut camera.reset_duration);
+}
+
+fn size_mode_name(mode: SizeMode) -> &'static str {
+    match mode {
+        SizeMode::Apparent => "apparent",
+        SizeMode::Allocated => "allocated",
+        SizeMode::Both => "both",
+    }
+}
+
+/// `["a", "b"]` with TOML basic-string escapes
+fn string_array(values: &[String]) -> String {
+    let quoted: Vec<String> = values.iter().map(|value| quote(value)).collect();
+    format!("[{}]", quoted.join(", "))
+}
+
+fn quote(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04X}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// One `[table]` of a TOML file; each reader leaves the target
+/// unchanged (with a warning) if the key holds something else
+pub(crate) struct Section<'a> {
+    name: &'static str,
+    table: Option<&'a dyn TableLike>,
+}
+
+impl<'a> Section<'a> {
+    pub(crate) fn new(doc: &'a DocumentMut, name: &'static str) -> Self {
+        Self {
+            name,
+            table: doc.get(name).and_then(Item::as_table_like),
+        }
+    }
+
+    /// A table that isn't a top-level one (e.g. an entry of `[[name]]`)
+    pub(crate) fn table(name: &'static str, table: &'a dyn TableLike) -> Self {
+        Self {
+            name,
+            table: Some(table),
+        }
+    }
+
+    pub(crate) fn get(&self, key: &str) -> Option<&'a Item> {
+        self.table?.get(key)
+    }
+
+    pub(crate) fn invalid(&self, key: &str, item: &Item) {
+        warn!(
+            "Ignoring invalid setting {}.{} = {}",
+            self.name,
+            key,
+            item.type_name()
+        );
+    }
+
+    /// True if the key was present and valid
+    pub(crate) fn bool(&self, key: &str, target: &mut bool) -> bool {
+        let Some(item) = self.get(key) else {
+            return false;
+        };
+        match item.as_bool() {
+            Some(value) => *target = value,
+            None => self.invalid(key, item),
+        }
+        item.is_bool()
+    }
+
+    pub(crate) fn usize(&self, key: &str, range: RangeInclusive<usize>, target: &mut usize) {
+        let Some(item) = self.get(key) else {
+            return;
+        };
+        match item.as_integer().and_then(|v| usize::try_from(v).ok()) {
+            Some(value) => *target = value.clamp(*range.start(), *range.end()),
+            None => self.invalid(key, item),
+        }
+    }
+
+    pub(crate) fn f32(&self, key: &str, target: &mut f32) {
+        let Some(item) = self.get(key) else {
+            return;
+        };
+        // Whole numbers may be written without a fraction
+        let value = item
+            .as_float()
+            .or_else(|| item.as_integer().map(|v| v as f64))
+            .filter(|v| v.is_finite());
+        match value {
+            Some(value) => *target = value as f32,
+            None => self.invalid(key, item),
+        }
+    }
+
+    pub(crate) fn f32_in(&self, key: &str, range: RangeInclusive<f32>, target: &mut f32) {
+        self.f32(key, target);
+        *target = target.clamp(*range.start(), *range.end());
+    }
+
+    pub(crate) fn strings(&self, key: &str, target: &mut Vec<String>) {
+        let Some(item) = self.get(key) else {
+            return;
+        };
+        let values: Option<Vec<String>> = item
+            .as_array()
+            .and_then(|array| array.iter().map(|v| v.as_str().map(String::from)).collect());
+        match values {
+            Some(values) => *target = values,
+            None => self.invalid(key, item),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn defaults() -> (SidebarSettings, ThemeConfig, VisualConfig, CameraConfig) {
+        (
+            SidebarSettings::default(),
+            ThemeConfig::default(),
+            VisualConfig::default(),
+            CameraConfig::default(),
+        )
+    }
+
+    #[test]
+    fn test_settings_round_trip() {
+        let (mut sidebar, mut theme, mut visual, mut camera) = defaults();
+        sidebar.history_limit = 25;
+        sidebar.exclude_patterns = vec!["node_modules".into(), "say \"hi\"\\*.tmp".into()];
+        sidebar.cache_never_expire = true;
+        theme.dark_mode = false;
+        visual.size_mode = SizeMode::Allocated;
+        camera.drilldown_duration = 1.25;
+        let text = render(&sidebar, &theme, &visual, &camera);
+
+        let (mut sidebar2, mut theme2, mut visual2, mut camera2) = defaults();
+        let doc = text.parse().unwrap();
+        apply(&doc, &mut sidebar2, &mut theme2, &mut visual2, &mut camera2);
+        assert_eq!(sidebar2.history_limit, 25);
+        assert_eq!(sidebar2.exclude_patterns, sidebar.exclude_patterns);
+        assert!(sidebar2.cache_never_expire);
+        assert!(!theme2.dark_mode);
+        assert_eq!(visual2.size_mode, SizeMode::Allocated);
+        assert_eq!(camera2.drilldown_duration, 1.25);
+    }
+
+    #[test]
+    fn test_invalid_settings_keep_defaults() {
+        let text = "[sidebar]\n\
+                    history_limit = 500\n\
+                    max_size_jobs = \"many\"\n\
+                    show_hidden_files = true\n\
+                    [visual]\n\
+                    size_mode = \"huge\"\n";
+        let (mut sidebar, mut theme, mut visual, mut camera) = defaults();
+        let doc = text.parse().unwrap();
+        apply(&doc, &mut sidebar, &mut theme, &mut visual, &mut camera);
+        // Out of range values are clamped, wrong types ignored
+        assert_eq!(sidebar.history_limit, 30);
+        assert_eq!(
+            sidebar.max_size_jobs,
+            SidebarSettings::default().max_size_jobs
+        );
+        assert!(sidebar.show_hidden_files);
+        assert_eq!(visual.size_mode, SizeMode::default());
+        assert!(theme.dark_mode);
+    }
+
+    #[test]
+    fn test_camera_values_are_clamped() {
+        let text = "[camera]\n\
+                    zoom_min = 80.0\n\
+                    zoom_max = 10.0\n\
+                    pitch_limit = 120.0\n\
+                    drilldown_duration = -1.0\n";
+        let (mut sidebar, mut theme, mut visual, mut camera) = defaults();
+        let doc = text.parse().unwrap();
+        apply(&doc, &mut sidebar, &mut theme, &mut visual, &mut camera);
+        assert_eq!(camera.zoom_min, 50.0);
+        assert_eq!(camera.zoom_max, 50.0);
+        assert_eq!(camera.pitch_limit, 89.0);
+        assert_eq!(camera.drilldown_duration, 0.05);
+    }
+
+    #[test]
+    fn test_save_waits_for_changes_to_settle() {
+        let mut store = SettingsStore {
+            path: None,
+            saved: String::new(),
+            changed_at: None,
+        };
+        assert!(!store.save_due(10.0));
+        store.mark_changed(1.0);
+        assert!(!store.save_due(1.2));
+        store.mark_changed(1.2);
+        assert!(!store.save_due(1.5));
+        assert!(store.save_due(1.8));
+
+        let (sidebar, theme, visual, camera) = defaults();
+        store.save(&sidebar, &theme, &visual, &camera).unwrap();
+        assert!(!store.save_due(5.0));
+    }
+}
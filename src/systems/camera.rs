@@ -2,13 +2,17 @@
 //!
 //! Camera animation and controls.
 
-use crate::events::{RespawnCelestialsEvent, ViewResetEvent};
+use crate::components::{CelestialBody, FadeIn, PulseAnimation};
+use crate::events::ViewResetEvent;
 use crate::resources::*;
 use crate::states::*;
 use bevy::prelude::*;
 use bevy::render::camera::Viewport;
 use bevy_panorbit_camera::PanOrbitCamera;
 
+/// Distance from the star where a drill-up starts, in star radii
+const STAR_CLEARANCE: f32 = 2.4;
+
 /// Camera animation state
 #[derive(Component)]
 pub struct CameraAnimation {
@@ -24,30 +28,50 @@ pub struct CameraAnimation {
     pub start_radius: f32,
     /// Starting focus
     pub start_focus: Vec3,
+    /// The next system spawns during the animation and fades in
+    pub reveal: bool,
 }
 
 impl CameraAnimation {
     /// Create new drilldown animation
-    pub fn drilldown(target: Vec3, start_radius: f32, duration: f32) -> Self {
+    pub fn drilldown(target: Vec3, start_focus: Vec3, start_radius: f32, duration: f32) -> Self {
         Self {
             target,
             target_radius: 15.0, // Closer view after drilldown
             progress: 0.0,
             duration,
             start_radius,
-            start_focus: Vec3::ZERO,
+            start_focus,
+            reveal: false,
+        }
+    }
+
+    /// Create drill-up animation: zoom out from the star (of radius
+    /// `star_radius`) while the parent system fades in around it (also used
+    /// for jumps up the breadcrumb)
+    pub fn drill_up(current_focus: Vec3, star_radius: f32, config: &CameraConfig) -> Self {
+        Self {
+            target: Vec3::ZERO,
+            target_radius: config.view_radius.clamp(config.zoom_min, config.zoom_max),
+            progress: 0.0,
+            duration: config.return_duration,
+            // Just outside the star
+            start_radius: (star_radius * STAR_CLEARANCE).clamp(config.zoom_min, config.zoom_max),
+            start_focus: current_focus,
+            reveal: true,
         }
     }
 
     /// Create return animation
-    pub fn return_to_center(current_radius: f32, duration: f32) -> Self {
+    pub fn return_to_center(current_focus: Vec3, current_radius: f32, duration: f32) -> Self {
         Self {
             target: Vec3::ZERO,
             target_radius: 20.0, // Default view distance
             progress: 0.0,
             duration,
             start_radius: current_radius,
-            start_focus: Vec3::ZERO,
+            start_focus: current_focus,
+            reveal: false,
         }
     }
 }
@@ -57,31 +81,91 @@ fn ease_out_cubic(t: f32) -> f32 {
     1.0 - (1.0 - t).powi(3)
 }
 
-/// Animate camera transitions
+/// Animate camera transitions.
+/// The orbit camera eases towards its targets, so they move along.
 pub fn animate_camera(
     mut commands: Commands,
     time: Res<Time>,
     mut query: Query<(Entity, &mut CameraAnimation, &mut PanOrbitCamera)>,
     mut next_state: ResMut<NextState<ViewingMode>>,
-    mut respawn_events: EventWriter<RespawnCelestialsEvent>,
 ) {
     for (entity, mut animation, mut camera) in query.iter_mut() {
         animation.progress += time.delta_secs() / animation.duration;
 
-        if animation.progress >= 1.0 {
+        let (radius, focus) = if animation.progress >= 1.0 {
             // Animation complete
-            camera.radius = Some(animation.target_radius);
-            camera.focus = Vec3::ZERO; // Reset focus to origin for new scene
             commands.entity(entity).remove::<CameraAnimation>();
             next_state.set(ViewingMode::Idle);
-
-            // Trigger celestial respawn
-            respawn_events.send(RespawnCelestialsEvent);
+            (animation.target_radius, Vec3::ZERO) // Reset focus to origin for new scene
         } else {
             // Interpolate
             let t = ease_out_cubic(animation.progress);
-            camera.radius = Some(animation.start_radius.lerp(animation.target_radius, t));
-            camera.focus = animation.start_focus.lerp(animation.target, t);
+            (
+                animation.start_radius.lerp(animation.target_radius, t),
+                animation.start_focus.lerp(animation.target, t),
+            )
+        };
+        camera.radius = Some(radius);
+        camera.target_radius = radius;
+        camera.focus = focus;
+        camera.target_focus = focus;
+        camera.force_update = true;
+    }
+}
+
+/// Newly spawned celestials that aren't pulsing
+type SpawnedCelestials<'w, 's> = Query<
+    'w,
+    's,
+    (Entity, &'static MeshMaterial3d<StandardMaterial>),
+    (Added<CelestialBody>, Without<PulseAnimation>),
+>;
+
+/// Fade in celestials that spawn during a revealing animation
+pub fn start_fade_in(
+    mut commands: Commands,
+    animations: Query<&CameraAnimation>,
+    spawned: SpawnedCelestials,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let Some(animation) = animations.iter().find(|a| a.reveal) else {
+        return;
+    };
+    // Finish together with the camera
+    let duration = (animation.duration * (1.0 - animation.progress)).max(0.2);
+    for (entity, material_handle) in spawned.iter() {
+        let Some(material) = materials.get_mut(&material_handle.0) else {
+            continue;
+        };
+        commands.entity(entity).insert(FadeIn {
+            elapsed: 0.0,
+            duration,
+            restore: material.alpha_mode,
+        });
+        material.alpha_mode = AlphaMode::Blend;
+        material.base_color.set_alpha(0.0);
+    }
+}
+
+/// Raise the alpha of fading celestials, then restore their alpha mode
+pub fn animate_fade_in(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut FadeIn, &MeshMaterial3d<StandardMaterial>)>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for (entity, mut fade, material_handle) in query.iter_mut() {
+        fade.elapsed += time.delta_secs();
+        let t = (fade.elapsed / fade.duration).min(1.0);
+
+        if let Some(material) = materials.get_mut(&material_handle.0) {
+            material.base_color.set_alpha(ease_out_cubic(t));
+            if t >= 1.0 {
+                material.alpha_mode = fade.restore;
+            }
+        }
+        if t >= 1.0 {
+            commands.entity(entity).remove::<FadeIn>();
         }
     }
 }
@@ -100,6 +184,7 @@ pub fn handle_view_reset(
             commands
                 .entity(entity)
                 .insert(CameraAnimation::return_to_center(
+                    camera.focus,
                     current_radius,
                     config.reset_duration,
                 ));
@@ -13,6 +13,7 @@ pub fn cleanup_viewing(
     mut commands: Commands,
     celestials: Query<Entity, With<CelestialBody>>,
     asteroid_belts: Query<Entity, With<AsteroidBelt>>,
+    highlights: Query<Entity, With<Highlight>>,
     mut ui_state: ResMut<UiState>,
 ) {
     // Despawn all celestial bodies
@@ -22,6 +23,9 @@ pub fn cleanup_viewing(
     for entity in asteroid_belts.iter() {
         commands.entity(entity).despawn_recursive();
     }
+    for entity in highlights.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
 
     // Clear UI state
     ui_state.hovered_entity = None;
@@ -1,62 +1,221 @@
 //! Filesystem reading systems
 //!
-//! Synchronous directory reading using std::fs.
+//! Directory listings from the tree index or cache, falling back to
+//! background reads with std::fs outside the indexed root.
 
 #![allow(dead_code)]
 
-use crate::resources::{DirectoryCache, FileEntry};
+use crate::components::{AsteroidBelt, CelestialBody};
+use crate::events::RespawnCelestialsEvent;
+use crate::resources::*;
+use crate::states::AppState;
+use crate::systems::size_calculation::SizeJobQueue;
+use bevy::prelude::*;
+use bevy::tasks::{IoTaskPool, Task};
+use futures_lite::future;
 use std::fs;
 use std::path::PathBuf;
+use std::time::UNIX_EPOCH;
 
-/// Read directory contents, using cache if available
-pub fn read_directory(path: &PathBuf, cache: &mut DirectoryCache) -> Vec<FileEntry> {
-    // Check cache first
-    if let Some(entries) = cache.get(path) {
-        return entries;
+/// Request the listing for `path`, cancelling any read in progress
+///
+/// Served immediately from the tree index or cache; otherwise read on the
+/// IoTaskPool and picked up by `poll_directory_load`. Persisted sizes are
+/// looked up on the IoTaskPool too, since validating them stats each directory.
+pub fn request_directory(
+    path: &PathBuf,
+    load: &mut DirectoryLoad,
+    cache: &mut DirectoryCache,
+    index: &TreeIndex,
+    rules: &ScanRules,
+    max_display_items: usize,
+    persistent_cache: Option<&PersistentCache>,
+) {
+    let progress = load.begin(path.clone());
+    let reader = persistent_cache.map(PersistentCache::reader);
+
+    // Indexed directories never touch the filesystem, then check cache
+    let known = index
+        .entries(path)
+        .map(|mut entries| {
+            sort_entries(&mut entries);
+            let modified = index.get(path).map_or(UNIX_EPOCH, |node| node.modified);
+            (entries, modified)
+        })
+        .or_else(|| cache.get(path));
+    if let Some((entries, modified)) = known {
+        let listing = DirectoryListing {
+            path: path.clone(),
+            entries,
+            modified,
+            errors: Vec::new(),
+            cached: CachedSizes::default(),
+        };
+        let unknown: Vec<PathBuf> = listed_directories(&listing, rules)
+            .filter(|dir| index.directory_size(dir).is_none())
+            .cloned()
+            .collect();
+        match reader {
+            Some(reader) if !unknown.is_empty() => {
+                load.task = Some(IoTaskPool::get().spawn(async move {
+                    let cached = reader.lookup(unknown.iter().map(PathBuf::as_path));
+                    Some(DirectoryListing { cached, ..listing })
+                }));
+            }
+            _ => load.finish(listing),
+        }
+        return;
     }
 
-    // Read from filesystem
-    let entries = read_directory_sync(path);
+    // Read from filesystem in the background
+    let path = path.clone();
+    let rules = rules.clone();
+    let task = IoTaskPool::get().spawn(async move {
+        let mut listing = read_directory_sync(&path, &rules, max_display_items, &progress)?;
+        if let Some(reader) = reader {
+            listing.cached = lookup_cached_sizes(&reader, &listing, &rules);
+        }
+        Some(listing)
+    });
+    load.task = Some(task);
+}
 
-    // Cache the result
-    cache.insert(path.clone(), entries.clone());
+/// The listed directory and its drillable entries, the ones sized as directories
+pub(crate) fn listed_directories<'a>(
+    listing: &'a DirectoryListing,
+    rules: &'a ScanRules,
+) -> impl Iterator<Item = &'a PathBuf> {
+    std::iter::once(&listing.path).chain(
+        listing
+            .entries
+            .iter()
+            .filter(|entry| entry.is_drillable(rules.follow_symlinks))
+            .map(|entry| &entry.path),
+    )
+}
 
-    entries
+/// Persisted sizes for the listing's directories (blocking; run off the main thread)
+pub(crate) fn lookup_cached_sizes(
+    reader: &CacheReader,
+    listing: &DirectoryListing,
+    rules: &ScanRules,
+) -> CachedSizes {
+    reader.lookup(listed_directories(listing, rules).map(PathBuf::as_path))
 }
 
-/// Synchronous directory reading
-fn read_directory_sync(path: &PathBuf) -> Vec<FileEntry> {
+/// Synchronous directory reading (None if cancelled)
+///
+/// Child counts are filled in for the first `max_display_items` directories,
+/// the ones that get a grandchild ring. Unreadable entries are reported in
+/// the listing's `errors` instead of being dropped silently.
+pub(crate) fn read_directory_sync(
+    path: &PathBuf,
+    rules: &ScanRules,
+    max_display_items: usize,
+    progress: &LoadProgress,
+) -> Option<DirectoryListing> {
     let mut entries = Vec::new();
+    let mut errors = Vec::new();
+    let ignores = rules.ignores_for(path);
+    let bounds = rules.bounds(path);
+    let modified = fs::metadata(path)
+        .and_then(|m| m.modified())
+        .unwrap_or(UNIX_EPOCH);
 
-    if let Ok(read_dir) = fs::read_dir(path) {
-        for entry in read_dir.flatten() {
-            let entry_path = entry.path();
-            let name = entry
-                .file_name()
-                .to_string_lossy()
-                .to_string();
+    match fs::read_dir(path) {
+        Ok(read_dir) => {
+            for entry in read_dir {
+                if progress.is_cancelled() {
+                    return None;
+                }
+                progress.tick();
 
-            // Skip hidden files (Unix convention)
-            if name.starts_with('.') {
-                continue;
-            }
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        errors.push(ScanError::from_io(path, &e));
+                        continue;
+                    }
+                };
+                let entry_path = entry.path();
+                let name = entry
+                    .file_name()
+                    .to_string_lossy()
+                    .to_string();
+
+                // Symlinks are listed as links, not as what they point to
+                let metadata = match entry.metadata() {
+                    Ok(metadata) => metadata,
+                    Err(e) => {
+                        errors.push(ScanError::from_io(&entry_path, &e));
+                        continue;
+                    }
+                };
+                let symlink = if metadata.is_symlink() {
+                    SymlinkInfo::read(&entry_path)
+                } else {
+                    None
+                };
+
+                // Hidden files, exclude/include patterns, .gitignore
+                if !rules.allows(&entry_path, metadata.is_dir(), &ignores) {
+                    continue;
+                }
+                // Mount points, when staying on one filesystem
+                if metadata.is_dir() && !bounds.same_filesystem(&metadata) {
+                    continue;
+                }
+
+                // Followed file links count their target's size; other links
+                // count nothing (not their own size), like the walkers
+                let usage = match &symlink {
+                    Some(link) if rules.follow_symlinks && !link.broken && !link.target_is_dir => {
+                        fs::metadata(&entry_path)
+                            .map(|m| DiskUsage::from_metadata(&m))
+                            .unwrap_or_default()
+                    }
+                    _ if metadata.is_symlink() => DiskUsage::default(),
+                    _ => DiskUsage::from_metadata(&metadata),
+                };
 
-            if let Ok(metadata) = entry.metadata() {
                 let file_entry = FileEntry {
                     name,
                     path: entry_path,
-                    // Directory size calculation is deferred (see docs/reference/size-calculation-research.md)
-                    // Will be implemented with async/parallel approach after requirements finalization
-                    size_bytes: if metadata.is_dir() { 0 } else { metadata.len() },
-                    modified: metadata.modified().unwrap_or(std::time::UNIX_EPOCH),
+                    // Directory sizes come from the tree index or the size job queue
+                    usage,
+                    modified: metadata.modified().unwrap_or(UNIX_EPOCH),
                     is_directory: metadata.is_dir(),
+                    child_count: None,
+                    symlink,
                 };
                 entries.push(file_entry);
             }
         }
+        Err(e) => errors.push(ScanError::from_io(path, &e)),
     }
 
-    // Sort: directories first, then by name (case-insensitive)
+    sort_entries(&mut entries);
+
+    for entry in entries.iter_mut().take(max_display_items) {
+        if progress.is_cancelled() {
+            return None;
+        }
+        if entry.is_drillable(rules.follow_symlinks) {
+            entry.child_count = Some(count_directory_items(&entry.path, rules, &mut errors));
+        }
+    }
+
+    Some(DirectoryListing {
+        path: path.clone(),
+        entries,
+        modified,
+        errors,
+        cached: CachedSizes::default(),
+    })
+}
+
+/// Sort: directories first, then by name (case-insensitive)
+fn sort_entries(entries: &mut [FileEntry]) {
     entries.sort_by(|a, b| {
         match (a.is_directory, b.is_directory) {
             (true, false) => std::cmp::Ordering::Less,
@@ -64,43 +223,378 @@ fn read_directory_sync(path: &PathBuf) -> Vec<FileEntry> {
             _ => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
         }
     });
-
-    entries
 }
 
-/// Count items in a directory (for grandchild ring)
-pub fn count_directory_items(path: &PathBuf) -> usize {
-    fs::read_dir(path)
-        .map(|entries| {
-            entries
-                .flatten()
-                .filter(|e| {
-                    !e.file_name()
-                        .to_string_lossy()
-                        .starts_with('.')
-                })
-                .count()
-        })
-        .unwrap_or(0)
+/// Count items in a directory (for grandchild ring), reporting it (or
+/// entries in it) if unreadable
+pub fn count_directory_items(
+    path: &PathBuf,
+    rules: &ScanRules,
+    errors: &mut Vec<ScanError>,
+) -> usize {
+    let ignores = rules.ignores_for(path);
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(e) => {
+            errors.push(ScanError::from_io(path, &e));
+            return 0;
+        }
+    };
+
+    let mut count = 0;
+    for entry in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                errors.push(ScanError::from_io(path, &e));
+                continue;
+            }
+        };
+        let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+        if rules.allows(&entry.path(), is_dir, &ignores) {
+            count += 1;
+        }
+    }
+    count
 }
 
 /// Calculate directory size recursively (expensive, use sparingly)
-pub fn calculate_directory_size(path: &PathBuf) -> u64 {
-    let mut total = 0u64;
-
-    if let Ok(entries) = fs::read_dir(path) {
-        for entry in entries.flatten() {
-            let metadata = entry.metadata().ok();
-            if let Some(meta) = metadata {
-                if meta.is_file() {
-                    total += meta.len();
-                } else if meta.is_dir() {
-                    // Recursion - careful with deep directories
-                    total += calculate_directory_size(&entry.path());
+///
+/// Unreadable entries are skipped and reported alongside the (lower bound) size.
+pub fn calculate_directory_size(path: &PathBuf, rules: &ScanRules) -> (DiskUsage, Vec<ScanError>) {
+    let mut bounds = rules.bounds(path);
+    let mut errors = Vec::new();
+    let total = sum_directory(path, &mut bounds, &mut errors);
+    (total, errors)
+}
+
+fn sum_directory(
+    path: &PathBuf,
+    bounds: &mut WalkBounds,
+    errors: &mut Vec<ScanError>,
+) -> DiskUsage {
+    let mut total = DiskUsage::default();
+
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(e) => {
+            errors.push(ScanError::from_io(path, &e));
+            return total;
+        }
+    };
+    for entry in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                errors.push(ScanError::from_io(path, &e));
+                continue;
+            }
+        };
+        let mut metadata = match entry.metadata() {
+            Ok(metadata) => Some(metadata),
+            Err(e) => {
+                errors.push(ScanError::from_io(&entry.path(), &e));
+                None
+            }
+        };
+        if bounds.follows_symlinks() && metadata.as_ref().is_some_and(|m| m.is_symlink()) {
+            metadata = fs::metadata(entry.path()).ok();
+        }
+        if let Some(meta) = metadata {
+            if meta.is_file() {
+                if bounds.first_link(&meta) {
+                    total += DiskUsage::from_metadata(&meta);
                 }
+            } else if meta.is_dir()
+                && bounds.same_filesystem(&meta)
+                && bounds.enter_directory(&meta)
+            {
+                // Recursion - careful with deep directories
+                total += sum_directory(&entry.path(), bounds, errors);
             }
         }
     }
 
     total
 }
+
+/// System: Rebuild scan rules when filter settings change and refresh the current system
+#[allow(clippy::too_many_arguments)]
+pub fn apply_scan_rules(
+    mut commands: Commands,
+    settings: Res<SidebarSettings>,
+    mut rules: ResMut<ScanRules>,
+    mut cache: ResMut<DirectoryCache>,
+    mut index: ResMut<TreeIndex>,
+    mut index_task: ResMut<TreeIndexTask>,
+    persistent_cache: Option<ResMut<PersistentCache>>,
+    mut size_queue: ResMut<SizeJobQueue>,
+    mut scan_errors: ResMut<ScanErrors>,
+    app_state: Res<State<AppState>>,
+    celestials: Query<Entity, With<CelestialBody>>,
+    asteroid_belts: Query<Entity, With<AsteroidBelt>>,
+    mut respawn_events: EventWriter<RespawnCelestialsEvent>,
+) {
+    if !settings.is_changed() || rules.matches_settings(&settings) {
+        return;
+    }
+
+    *rules = ScanRules::from_settings(&settings);
+    cache.clear();
+    // Walks under the new rules report their own errors
+    scan_errors.clear();
+
+    // Cached sizes are namespaced by the rules they were calculated with
+    if let Some(mut persistent_cache) = persistent_cache {
+        persistent_cache.set_rules_fingerprint(rules.fingerprint());
+    }
+
+    // Running jobs would report sizes under the old rules
+    size_queue.cancel_all();
+
+    // The index was built with the old rules
+    *index = TreeIndex::default();
+    if let Some(root) = index_task.root.clone() {
+        index_task.start(root, rules.clone());
+    }
+
+    info!("Scan rules changed (fingerprint {:016x})", rules.fingerprint());
+
+    if *app_state.get() != AppState::Viewing {
+        return;
+    }
+
+    for entity in celestials.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    for entity in asteroid_belts.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    respawn_events.send(RespawnCelestialsEvent);
+}
+
+/// System: Apply changed cache TTL / revalidation settings to the persistent cache
+pub fn apply_cache_validation(
+    settings: Res<SidebarSettings>,
+    persistent_cache: Option<ResMut<PersistentCache>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    let Some(mut persistent_cache) = persistent_cache else {
+        return;
+    };
+    let validation = CacheValidation::from_settings(&settings);
+    if persistent_cache.validation() == validation {
+        return;
+    }
+    persistent_cache.set_validation(validation);
+    info!("Cache validation changed: {:?}", validation);
+}
+
+/// Seconds between checks for history written by other instances
+const CACHE_SYNC_INTERVAL_SECS: f32 = 2.0;
+
+/// Stored history, and the shown recent folders that no longer exist
+type HistorySync = Task<(Vec<PathBuf>, Vec<PathBuf>)>;
+
+/// Keep in step with other instances sharing the cache file: pick up the
+/// history they wrote, and take the file over once its owner has quit
+///
+/// The history is read on the IoTaskPool; with a shared cache that's a
+/// round trip to the owning instance.
+pub fn sync_persistent_cache(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut since_sync: Local<f32>,
+    mut history_task: Local<Option<HistorySync>>,
+    persistent_cache: Option<Res<PersistentCache>>,
+    mut recent: ResMut<RecentFolders>,
+) {
+    if let Some(task) = history_task.as_mut() {
+        let Some((stored, gone)) = future::block_on(future::poll_once(task)) else {
+            return;
+        };
+        *history_task = None;
+        // A union with what was opened here, not a replacement
+        let mut merged = RecentFolders {
+            entries: recent.entries.clone(),
+            max_entries: recent.max_entries,
+        };
+        merged.entries.retain(|entry| !gone.contains(entry));
+        merged.merge(stored);
+        if merged.entries != recent.entries {
+            recent.entries = merged.entries;
+        }
+    }
+
+    *since_sync += time.delta_secs();
+    if *since_sync < CACHE_SYNC_INTERVAL_SECS {
+        return;
+    }
+    *since_sync = 0.0;
+    let Some(persistent_cache) = persistent_cache else {
+        return;
+    };
+
+    if persistent_cache.is_disconnected() {
+        // Retried next interval if another instance got there first
+        if let Ok(mut reopened) = PersistentCache::new(persistent_cache.validation()) {
+            reopened.set_rules_fingerprint(persistent_cache.rules_fingerprint());
+            commands.insert_resource(reopened);
+        }
+        return;
+    }
+
+    // Folders that no longer exist are dropped from both lists
+    let reader = persistent_cache.reader();
+    let shown = recent.entries.clone();
+    *history_task = Some(IoTaskPool::get().spawn(async move {
+        let stored = reader
+            .load_history()
+            .into_iter()
+            .filter(|entry| entry.exists())
+            .collect();
+        let gone = shown.into_iter().filter(|entry| !entry.exists()).collect();
+        (stored, gone)
+    }));
+}
+
+/// System: Run storage cache actions from Settings and apply their outcome
+///
+/// Cleared sizes also drop the in-memory listings and the tree index and
+/// respawn the current system, so its directories are sized again.
+#[allow(clippy::too_many_arguments)]
+pub fn run_cache_maintenance(
+    mut commands: Commands,
+    ui_state: Res<UiState>,
+    mut settings_open: Local<bool>,
+    mut management: ResMut<CacheManagement>,
+    persistent_cache: Option<Res<PersistentCache>>,
+    mut cache: ResMut<DirectoryCache>,
+    mut index: ResMut<TreeIndex>,
+    mut index_task: ResMut<TreeIndexTask>,
+    rules: Res<ScanRules>,
+    mut size_queue: ResMut<SizeJobQueue>,
+    mut recent: ResMut<RecentFolders>,
+    mut history: ResMut<NavigationHistory>,
+    app_state: Res<State<AppState>>,
+    celestials: Query<Entity, With<CelestialBody>>,
+    asteroid_belts: Query<Entity, With<AsteroidBelt>>,
+    mut respawn_events: EventWriter<RespawnCelestialsEvent>,
+) {
+    // Fresh numbers whenever the settings page opens
+    let open = ui_state.main_view == MainView::Settings;
+    if open && !*settings_open && !management.is_busy() {
+        management.requested = Some(CacheMaintenance::Summarize);
+    }
+    *settings_open = open;
+
+    if let Some(task) = &mut management.dialog
+        && let Some(action) = future::block_on(future::poll_once(task))
+    {
+        management.dialog = None;
+        management.requested = action;
+    }
+
+    if management.running.is_none()
+        && let Some(persistent_cache) = &persistent_cache
+        && let Some(action) = management.requested.take()
+    {
+        let task = persistent_cache.maintain(action.clone());
+        management.running = Some((action, task));
+    }
+
+    let Some((_, task)) = &mut management.running else {
+        return;
+    };
+    let Some(result) = future::block_on(future::poll_once(task)) else {
+        return;
+    };
+    let Some((action, _)) = management.running.take() else {
+        return;
+    };
+
+    match result {
+        Ok(summary) => management.summary = Some(summary),
+        Err(e) => {
+            warn!("Cache maintenance ({:?}) failed: {}", action, e);
+            management.message = Some(format!("Failed: {}", e));
+            return;
+        }
+    }
+
+    match &action {
+        CacheMaintenance::Summarize => {}
+        CacheMaintenance::ClearSizes => {
+            management.message = Some("Cached sizes cleared".into());
+            cache.clear();
+            // Running jobs are re-queued by the respawn below
+            size_queue.cancel_all();
+            // Its totals would otherwise be served again without a walk
+            *index = TreeIndex::default();
+            if let Some(root) = index_task.root.clone() {
+                index_task.start(root, rules.clone());
+            }
+            info!("Persistent cache sizes cleared");
+
+            if *app_state.get() == AppState::Viewing {
+                for entity in celestials.iter().chain(asteroid_belts.iter()) {
+                    commands.entity(entity).despawn_recursive();
+                }
+                respawn_events.send(RespawnCelestialsEvent);
+            }
+        }
+        CacheMaintenance::ClearHistory => {
+            management.message = Some("History cleared".into());
+            recent.entries.clear();
+            history.back.clear();
+            history.forward.clear();
+            info!("Persistent cache history cleared");
+        }
+        CacheMaintenance::Compact => {
+            management.message = Some("Database compacted".into());
+            info!("Persistent cache compacted");
+        }
+        CacheMaintenance::Export(target) => {
+            management.message = Some(format!("Exported to {}", target.display()));
+            info!("Persistent cache exported to {}", target.display());
+        }
+        // Imported history is picked up by `sync_persistent_cache`
+        CacheMaintenance::Import(source) => {
+            management.message = Some(format!("Imported {}", source.display()));
+            info!("Persistent cache imported from {}", source.display());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::systems::size_calculation::CancelFlag;
+    use crate::utils::test_dir::TestDir;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unfollowed_symlinks_add_nothing_to_listing() {
+        let tmp = TestDir::new("listing-symlinks");
+        let root = tmp.path().to_path_buf();
+        fs::write(root.join("file.bin"), [0u8; 4096]).unwrap();
+        std::os::unix::fs::symlink(root.join("file.bin"), root.join("link")).unwrap();
+        std::os::unix::fs::symlink(root.join("missing"), root.join("broken")).unwrap();
+
+        let rules = ScanRules::default();
+        let listing = read_directory_sync(&root, &rules, 0, &LoadProgress::default()).unwrap();
+        let mut listed = DiskUsage::default();
+        for entry in &listing.entries {
+            if entry.symlink.is_some() {
+                assert_eq!(entry.usage, DiskUsage::default(), "{}", entry.name);
+            }
+            listed += entry.usage;
+        }
+
+        // The listing's direct files add up to what the index counts
+        let index = TreeIndex::build(&root, &rules, &CancelFlag::default()).unwrap();
+        assert_eq!(index.directory_size(&root), Some(listed));
+    }
+}
@@ -0,0 +1,217 @@
+//! Highlight systems
+//!
+//! Mirror the hovered and selected entities of `UiState` onto the `Hovered`
+//! and `Selected` markers, and draw a selection ring and a hover glow around
+//! the marked celestials.
+
+use crate::components::*;
+use crate::events::SelectionChangedEvent;
+use crate::resources::{CurrentDirectory, UiState};
+use crate::utils::{
+    create_highlight_ring_mesh, create_hover_material, create_selection_material,
+    create_sphere_mesh,
+};
+use bevy::pbr::NotShadowCaster;
+use bevy::prelude::*;
+use bevy::render::primitives::Aabb;
+use bevy_panorbit_camera::PanOrbitCamera;
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+/// Selection ring radius relative to the celestial's radius
+const SELECTION_RING_SCALE: f32 = 1.5;
+
+/// Hover glow radius relative to the celestial's radius
+const HOVER_GLOW_SCALE: f32 = 1.3;
+
+/// System: Sync the `Hovered`/`Selected` markers with `UiState`
+///
+/// Navigation clears the selection. A planet respawned in place (a changed
+/// file after a refresh) stays selected.
+#[allow(clippy::too_many_arguments)]
+pub fn sync_highlight_markers(
+    mut commands: Commands,
+    mut ui_state: ResMut<UiState>,
+    current_dir: Res<CurrentDirectory>,
+    celestials: Query<(Entity, &CelestialBody)>,
+    hovered: Query<Entity, With<Hovered>>,
+    selected: Query<Entity, With<Selected>>,
+    mut selected_path: Local<Option<PathBuf>>,
+    mut selection_events: EventWriter<SelectionChangedEvent>,
+) {
+    if current_dir.is_changed() {
+        *selected_path = None;
+        if ui_state.selected_entity.take().is_some() {
+            selection_events.send(SelectionChangedEvent { entity: None });
+        }
+    }
+
+    if let Some(entity) = ui_state.selected_entity
+        && !celestials.contains(entity)
+    {
+        let respawned = selected_path.as_ref().and_then(|path| {
+            celestials
+                .iter()
+                .find(|(_, body)| body.path == *path)
+                .map(|(entity, _)| entity)
+        });
+        ui_state.selected_entity = respawned;
+        selection_events.send(SelectionChangedEvent { entity: respawned });
+    }
+    *selected_path = ui_state
+        .selected_entity
+        .and_then(|entity| celestials.get(entity).ok())
+        .map(|(_, body)| body.path.clone());
+
+    for entity in selected.iter() {
+        if ui_state.selected_entity != Some(entity) {
+            commands.entity(entity).remove::<Selected>();
+        }
+    }
+    if let Some(entity) = ui_state.selected_entity
+        && !selected.contains(entity)
+    {
+        commands.entity(entity).try_insert(Selected);
+    }
+
+    for entity in hovered.iter() {
+        if ui_state.hovered_entity != Some(entity) {
+            commands.entity(entity).remove::<Hovered>();
+        }
+    }
+    if let Some(entity) = ui_state.hovered_entity
+        && celestials.contains(entity)
+        && !hovered.contains(entity)
+    {
+        commands.entity(entity).try_insert(Hovered);
+    }
+}
+
+/// Celestials with a highlight marker
+type HighlightTargets<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Transform,
+        Option<&'static Aabb>,
+        Has<Selected>,
+        Has<Hovered>,
+    ),
+    (With<CelestialBody>, Or<(With<Selected>, With<Hovered>)>),
+>;
+
+/// System: Spawn, move and remove the highlight meshes
+///
+/// Highlights follow their target's position and size, so they survive
+/// size updates and re-layouts.
+pub fn update_highlights(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    targets: HighlightTargets,
+    mut highlights: Query<(Entity, &Highlight, &mut Transform), Without<CelestialBody>>,
+    cameras: Query<&Transform, (With<PanOrbitCamera>, Without<Highlight>)>,
+) {
+    let camera_position = cameras.get_single().ok().map(|camera| camera.translation);
+
+    let mut shown = HashSet::new();
+    for (entity, highlight, mut transform) in highlights.iter_mut() {
+        let target = targets
+            .get(highlight.target)
+            .ok()
+            .filter(|(_, _, _, selected, hovered)| match highlight.kind {
+                HighlightKind::Selection => *selected,
+                HighlightKind::Hover => *hovered,
+            });
+        let Some((_, target_transform, aabb, ..)) = target else {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        };
+        *transform = highlight_transform(highlight.kind, target_transform, aabb, camera_position);
+        shown.insert((highlight.target, highlight.kind));
+    }
+
+    for (target, target_transform, aabb, selected, hovered) in targets.iter() {
+        for (kind, wanted) in [
+            (HighlightKind::Selection, selected),
+            (HighlightKind::Hover, hovered),
+        ] {
+            if !wanted || shown.contains(&(target, kind)) {
+                continue;
+            }
+            let (mesh, material) = match kind {
+                HighlightKind::Selection => (
+                    create_highlight_ring_mesh(&mut meshes),
+                    create_selection_material(&mut materials),
+                ),
+                HighlightKind::Hover => (
+                    create_sphere_mesh(1.0, &mut meshes),
+                    create_hover_material(&mut materials),
+                ),
+            };
+            commands.spawn((
+                Highlight { target, kind },
+                Mesh3d(mesh),
+                MeshMaterial3d(material),
+                highlight_transform(kind, target_transform, aabb, camera_position),
+                // The star's light must not be blocked by its own glow
+                NotShadowCaster,
+            ));
+        }
+    }
+}
+
+/// Placement of a highlight around its target; the ring faces the camera
+fn highlight_transform(
+    kind: HighlightKind,
+    target: &Transform,
+    aabb: Option<&Aabb>,
+    camera_position: Option<Vec3>,
+) -> Transform {
+    // File meshes bake their size in, directories scale a unit mesh
+    let radius =
+        aabb.map_or(1.0, |aabb| aabb.half_extents.max_element()) * target.scale.max_element();
+    match kind {
+        HighlightKind::Selection => {
+            let facing = camera_position
+                .and_then(|camera| (camera - target.translation).try_normalize())
+                .map_or(Quat::IDENTITY, |direction| {
+                    Quat::from_rotation_arc(Vec3::Y, direction)
+                });
+            Transform::from_translation(target.translation)
+                .with_rotation(facing)
+                .with_scale(Vec3::splat(radius * SELECTION_RING_SCALE))
+        }
+        HighlightKind::Hover => Transform::from_translation(target.translation)
+            .with_scale(Vec3::splat(radius * HOVER_GLOW_SCALE)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_highlight_follows_target_size() {
+        // A unit sphere scaled to 2 and a file mesh baked at size 2 look alike
+        let sphere = Aabb::from_min_max(Vec3::splat(-1.0), Vec3::splat(1.0));
+        let octahedron = Aabb::from_min_max(Vec3::splat(-2.0), Vec3::splat(2.0));
+        let scaled = Transform::from_xyz(5.0, 0.0, 0.0).with_scale(Vec3::splat(2.0));
+        let baked = Transform::from_xyz(5.0, 0.0, 0.0);
+
+        let glow = highlight_transform(HighlightKind::Hover, &scaled, Some(&sphere), None);
+        assert_eq!(glow.translation, scaled.translation);
+        assert_eq!(glow.scale, Vec3::splat(2.0 * HOVER_GLOW_SCALE));
+        let glow = highlight_transform(HighlightKind::Hover, &baked, Some(&octahedron), None);
+        assert_eq!(glow.scale, Vec3::splat(2.0 * HOVER_GLOW_SCALE));
+
+        // The ring's axis points at the camera
+        let camera = Some(Vec3::new(5.0, 10.0, 0.0));
+        let ring = highlight_transform(HighlightKind::Selection, &baked, Some(&octahedron), camera);
+        assert!((ring.rotation * Vec3::Y).abs_diff_eq(Vec3::Y, 1e-5));
+        let camera = Some(Vec3::new(5.0, 0.0, 10.0));
+        let ring = highlight_transform(HighlightKind::Selection, &baked, Some(&octahedron), camera);
+        assert!((ring.rotation * Vec3::Y).abs_diff_eq(Vec3::Z, 1e-5));
+    }
+}
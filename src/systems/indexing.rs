@@ -0,0 +1,84 @@
+//! Tree index systems
+//!
+//! Poll the background scan, apply its sizes to spawned planets and
+//! record the entries it couldn't read.
+
+use crate::components::{PendingSizeCalculation, PulseAnimation, Subtree};
+use crate::resources::{
+    CacheStamp, PersistentCache, ScanErrors, TreeIndex, TreeIndexTask, VisualConfig,
+};
+use crate::systems::size_calculation::{PendingDirectories, SizeJobQueue};
+use crate::utils::{calculate_size, calculate_star_scale};
+use bevy::prelude::*;
+use futures_lite::future;
+
+/// System: Install the finished tree index and resolve pending planet sizes
+#[allow(clippy::too_many_arguments)]
+pub fn poll_tree_index(
+    mut commands: Commands,
+    mut index_task: ResMut<TreeIndexTask>,
+    mut index: ResMut<TreeIndex>,
+    mut celestials: PendingDirectories,
+    config: Res<VisualConfig>,
+    persistent_cache: Option<Res<PersistentCache>>,
+    mut size_queue: ResMut<SizeJobQueue>,
+    mut scan_errors: ResMut<ScanErrors>,
+) {
+    let Some(ref mut task) = index_task.task else {
+        return;
+    };
+    let Some(result) = future::block_on(future::poll_once(task)) else {
+        return;
+    };
+    index_task.task = None;
+    let Some(result) = result else {
+        return;
+    };
+    *index = result;
+
+    // The scan saw the whole root, so its errors replace any reported earlier
+    if let Some(root) = index.root().map(|r| r.to_path_buf()) {
+        scan_errors.clear_below(&root);
+    }
+    let errors = index.take_errors();
+    scan_errors.record(errors);
+
+    // Planets (and the star) spawned before the scan finished are still waiting on walkers
+    for (entity, mut body, mut transform, planet, is_star) in celestials.iter_mut() {
+        if !is_star && !planet.is_some_and(|p| p.is_directory) {
+            continue;
+        }
+        let Some(usage) = index.directory_size(&body.path) else {
+            continue;
+        };
+
+        body.usage = usage;
+        size_queue.remove(&body.path);
+        let bytes = usage.get(config.size_mode);
+        transform.scale = Vec3::splat(if is_star {
+            calculate_star_scale(bytes, &config)
+        } else {
+            calculate_size(bytes, true, &config)
+        });
+
+        commands
+            .entity(entity)
+            .remove::<PulseAnimation>()
+            .remove::<PendingSizeCalculation>();
+
+        // The scan couldn't stamp directories up front; only persist the ones
+        // that weren't modified since it walked them
+        let stamp = index
+            .get(&body.path)
+            .and_then(|node| CacheStamp::read_unmodified(&body.path, node.modified));
+        if let (Some(cache), Some(stamp)) = (&persistent_cache, stamp) {
+            cache.write_size(&body.path, usage, stamp);
+        }
+        if let Some(stats) = index.directory_stats(&body.path) {
+            if let (Some(cache), Some(stamp)) = (&persistent_cache, stamp) {
+                cache.write_stats(&body.path, &stats, stamp);
+            }
+            commands.entity(entity).insert(Subtree { stats });
+        }
+    }
+}
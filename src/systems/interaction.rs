@@ -1,18 +1,23 @@
 //! Interaction systems
 //!
-//! Handle user input: hover, selection, drilldown.
+//! Handle user input: hover, selection, drilldown, drill-up, back/forward
+//! and keyboard navigation.
 
 use crate::components::*;
 use crate::events::{
-    DrillDownEvent, NavigateToEvent, RespawnCelestialsEvent, SelectionChangedEvent, ViewResetEvent,
+    DrillDownEvent, DrillUpEvent, NavigateBackEvent, NavigateForwardEvent, NavigateToEvent,
+    RespawnCelestialsEvent, SelectionChangedEvent, ViewResetEvent,
 };
 use crate::resources::*;
 use crate::states::*;
 use crate::systems::camera::CameraAnimation;
-use crate::utils::window_to_viewport_cursor;
+use crate::utils::{orbital_angle, window_to_viewport_cursor};
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 use bevy::window::PrimaryWindow;
+use bevy_egui::{egui, EguiContexts, EguiInput};
 use bevy_panorbit_camera::PanOrbitCamera;
+use std::path::{Path, PathBuf};
 
 /// Track double-click timing
 #[derive(Resource, Default)]
@@ -23,6 +28,82 @@ pub struct ClickState {
 
 const DOUBLE_CLICK_THRESHOLD: f64 = 0.3; // 300ms
 
+/// Back/forward request waiting for the current animation to finish
+#[derive(Debug, Clone, Copy)]
+pub enum HistoryStep {
+    Back,
+    Forward,
+}
+
+/// Current directory, breadcrumb and history, as one system parameter
+#[derive(SystemParam)]
+pub struct Navigation<'w> {
+    current_dir: ResMut<'w, CurrentDirectory>,
+    breadcrumb: ResMut<'w, Breadcrumb>,
+    history: ResMut<'w, NavigationHistory>,
+    recent: ResMut<'w, RecentFolders>,
+    persistent_cache: Option<Res<'w, PersistentCache>>,
+}
+
+impl Navigation<'_> {
+    /// Directory being visualized
+    pub fn current(&self) -> Option<&Path> {
+        self.current_dir.path.as_deref()
+    }
+
+    /// Start a session at a folder the user opened, restoring its back stack
+    pub fn open(&mut self, path: PathBuf) {
+        let back = self
+            .persistent_cache
+            .as_ref()
+            .map(|cache| cache.load_session(&path))
+            .unwrap_or_default();
+        self.history.back = back.into_iter().filter(|p| p.is_dir()).collect();
+        self.history.forward.clear();
+        self.current_dir.root = Some(path.clone());
+        // Only opened folders are recent ones, not every folder visited
+        self.recent.add(path.clone());
+        if let Some(ref cache) = self.persistent_cache {
+            cache.write_history(&self.recent.entries);
+        }
+        self.arrive(path);
+    }
+
+    /// Make `path` the current directory, remembering the one we leave
+    pub fn go_to(&mut self, path: PathBuf) {
+        if let Some(current_path) = self.current_dir.path.clone() {
+            self.history.push(current_path);
+        }
+        self.arrive(path);
+    }
+
+    /// Step back in the history; None if there is nothing to go back to
+    pub fn go_back(&mut self) -> Option<PathBuf> {
+        let current_path = self.current_dir.path.clone()?;
+        let path = self.history.go_back(current_path)?;
+        self.arrive(path.clone());
+        Some(path)
+    }
+
+    /// Step forward in the history; None if there is nothing to go forward to
+    pub fn go_forward(&mut self) -> Option<PathBuf> {
+        let current_path = self.current_dir.path.clone()?;
+        let path = self.history.go_forward(current_path)?;
+        self.arrive(path.clone());
+        Some(path)
+    }
+
+    fn arrive(&mut self, path: PathBuf) {
+        *self.breadcrumb = Breadcrumb::from_path(&path);
+        self.current_dir.path = Some(path);
+
+        // Persist the session's back stack
+        if let (Some(cache), Some(root)) = (&self.persistent_cache, &self.current_dir.root) {
+            cache.write_session(root, &self.history.back);
+        }
+    }
+}
+
 /// Handle hover detection via raycasting
 pub fn update_hover(
     mut ui_state: ResMut<UiState>,
@@ -149,24 +230,15 @@ pub fn handle_selection(
     click_state.last_click_entity = hovered;
 }
 
-/// Handle double-click drilldown
-pub fn handle_drilldown(
-    mut commands: Commands,
+/// Turn a double-click into a drilldown request (double-clicking the star drills up)
+pub fn handle_double_click(
     ui_state: Res<UiState>,
     click_state: Res<ClickState>,
     mouse: Res<ButtonInput<MouseButton>>,
     time: Res<Time>,
-    drillables: Query<(&CelestialBody, &GlobalTransform), With<Drillable>>,
-    mut current_dir: ResMut<CurrentDirectory>,
-    mut breadcrumb: ResMut<Breadcrumb>,
-    mut history: ResMut<NavigationHistory>,
+    drillables: Query<(&CelestialBody, Has<Star>), With<Drillable>>,
     mut drilldown_events: EventWriter<DrillDownEvent>,
-    camera_query: Query<(Entity, &PanOrbitCamera)>,
-    config: Res<CameraConfig>,
-    mut next_state: ResMut<NextState<ViewingMode>>,
-    celestials: Query<Entity, With<CelestialBody>>,
-    asteroid_belts: Query<Entity, With<AsteroidBelt>>,
-    persistent_cache: Option<Res<PersistentCache>>,
+    mut drill_up_events: EventWriter<DrillUpEvent>,
 ) {
     // No drilldown when 3D scene is hidden
     if ui_state.main_view != MainView::Universe {
@@ -186,61 +258,155 @@ pub fn handle_drilldown(
         if current_time - last_time < DOUBLE_CLICK_THRESHOLD {
             if ui_state.hovered_entity == Some(last_entity) {
                 // Double-click on drillable entity
-                if let Ok((celestial, transform)) = drillables.get(last_entity) {
-                    // Push current directory to history
-                    if let Some(current_path) = &current_dir.path {
-                        history.push(current_path.clone());
-
-                        // Persist history
-                        if let Some(ref cache) = persistent_cache {
-                            cache.write_history(&history.entries);
-                        }
+                if let Ok((celestial, is_star)) = drillables.get(last_entity) {
+                    // The star is the current directory: go to its parent
+                    if is_star {
+                        drill_up_events.send(DrillUpEvent);
+                        return;
                     }
 
-                    // Update current directory
-                    current_dir.path = Some(celestial.path.clone());
-                    *breadcrumb = Breadcrumb::from_path(&celestial.path);
-
-                    // Send drilldown event
                     drilldown_events.send(DrillDownEvent {
                         entity: last_entity,
                         path: celestial.path.clone(),
                     });
-
-                    // Start camera animation
-                    for (cam_entity, camera) in camera_query.iter() {
-                        let current_radius = camera.radius.unwrap_or(20.0);
-                        commands.entity(cam_entity).insert(CameraAnimation::drilldown(
-                            transform.translation(),
-                            current_radius,
-                            config.drilldown_duration,
-                        ));
-                    }
-                    next_state.set(ViewingMode::Animating);
-
-                    // Cleanup existing celestials (will respawn on animation complete)
-                    for entity in celestials.iter() {
-                        commands.entity(entity).despawn_recursive();
-                    }
-                    for entity in asteroid_belts.iter() {
-                        commands.entity(entity).despawn_recursive();
-                    }
-
-                    info!("Drilldown to: {}", celestial.path.display());
                 }
             }
         }
     }
 }
 
-/// Handle keyboard shortcuts (runs globally — all states)
+/// Handle drilldown into a planet (double-click, Enter)
+pub fn handle_drilldown(
+    mut commands: Commands,
+    mut events: EventReader<DrillDownEvent>,
+    mode: Res<State<ViewingMode>>,
+    planets: Query<&GlobalTransform, With<Planet>>,
+    mut navigation: Navigation,
+    camera_query: Query<(Entity, &PanOrbitCamera)>,
+    config: Res<CameraConfig>,
+    mut next_state: ResMut<NextState<ViewingMode>>,
+    celestials: Query<Entity, With<CelestialBody>>,
+    asteroid_belts: Query<Entity, With<AsteroidBelt>>,
+    mut respawn_events: EventWriter<RespawnCelestialsEvent>,
+) {
+    // One drilldown per frame
+    let Some(event) = events.read().last() else {
+        return;
+    };
+    if *mode.get() == ViewingMode::Animating {
+        return;
+    }
+
+    navigation.go_to(event.path.clone());
+
+    // Start camera animation
+    let target = planets
+        .get(event.entity)
+        .map_or(Vec3::ZERO, |transform| transform.translation());
+    for (cam_entity, camera) in camera_query.iter() {
+        let current_radius = camera.radius.unwrap_or(20.0);
+        commands
+            .entity(cam_entity)
+            .insert(CameraAnimation::drilldown(
+                target,
+                camera.focus,
+                current_radius,
+                config.drilldown_duration,
+            ));
+    }
+    next_state.set(ViewingMode::Animating);
+
+    // Cleanup existing celestials; the new listing loads during the
+    // animation and spawns once it completes
+    for entity in celestials.iter().chain(asteroid_belts.iter()) {
+        commands.entity(entity).despawn_recursive();
+    }
+    respawn_events.send(RespawnCelestialsEvent);
+
+    info!("Drilldown to: {}", event.path.display());
+}
+
+/// Handle drill-up to the parent directory (star double-click, Backspace,
+/// breadcrumb button)
+#[allow(clippy::too_many_arguments)]
+pub fn handle_drill_up(
+    mut commands: Commands,
+    mut events: EventReader<DrillUpEvent>,
+    mode: Res<State<ViewingMode>>,
+    mut navigation: Navigation,
+    camera_query: Query<(Entity, &PanOrbitCamera)>,
+    config: Res<CameraConfig>,
+    visual: Res<VisualConfig>,
+    stars: Query<&Transform, With<Star>>,
+    mut next_state: ResMut<NextState<ViewingMode>>,
+    celestials: Query<Entity, With<CelestialBody>>,
+    asteroid_belts: Query<Entity, With<AsteroidBelt>>,
+    mut respawn_events: EventWriter<RespawnCelestialsEvent>,
+) {
+    // Several requests in one frame still go up a single level
+    if events.read().count() == 0 {
+        return;
+    }
+    if *mode.get() == ViewingMode::Animating {
+        return;
+    }
+    let Some(parent) = navigation.current().and_then(Path::parent) else {
+        return;
+    };
+    let parent = parent.to_path_buf();
+
+    navigation.go_to(parent.clone());
+
+    // Zoom out from the star; the parent system spawns and fades in meanwhile
+    let star_radius = star_radius(&stars, &visual);
+    for (cam_entity, camera) in camera_query.iter() {
+        commands
+            .entity(cam_entity)
+            .insert(CameraAnimation::drill_up(
+                camera.focus,
+                star_radius,
+                &config,
+            ));
+    }
+    next_state.set(ViewingMode::Animating);
+
+    for entity in celestials.iter().chain(asteroid_belts.iter()) {
+        commands.entity(entity).despawn_recursive();
+    }
+    respawn_events.send(RespawnCelestialsEvent);
+
+    info!("Drill up to: {}", parent.display());
+}
+
+/// Radius of the star on screen (its mesh is `star_size`, scaled by its total)
+fn star_radius(stars: &Query<&Transform, With<Star>>, visual: &VisualConfig) -> f32 {
+    stars.iter().next().map_or(visual.star_size, |transform| {
+        visual.star_size * transform.scale.x
+    })
+}
+
+/// Handle keyboard shortcuts and the mouse's back/forward buttons
+/// (runs globally — all states)
+#[allow(clippy::too_many_arguments)]
 pub fn handle_keyboard(
+    mut contexts: EguiContexts,
     keyboard: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
     mut ui_state: ResMut<UiState>,
+    current_dir: Res<CurrentDirectory>,
     mut selection_events: EventWriter<SelectionChangedEvent>,
     mut view_reset_events: EventWriter<ViewResetEvent>,
+    mut drill_up_events: EventWriter<DrillUpEvent>,
+    mut navigate_events: EventWriter<NavigateToEvent>,
+    mut back_events: EventWriter<NavigateBackEvent>,
+    mut forward_events: EventWriter<NavigateForwardEvent>,
     state: Res<State<AppState>>,
 ) {
+    // Keys typed into a text field are not shortcuts
+    if contexts.ctx_mut().wants_keyboard_input() {
+        return;
+    }
+
     // Esc: close Settings first, then clear selection
     if keyboard.just_pressed(KeyCode::Escape) {
         if ui_state.main_view == MainView::Settings {
@@ -258,49 +424,294 @@ pub fn handle_keyboard(
     {
         view_reset_events.send(ViewResetEvent);
     }
+
+    // Backspace - drill up to the parent (Viewing + Universe only)
+    if keyboard.just_pressed(KeyCode::Backspace)
+        && *state.get() == AppState::Viewing
+        && ui_state.main_view == MainView::Universe
+    {
+        drill_up_events.send(DrillUpEvent);
+    }
+
+    // Alt+Left/Right, mouse buttons 4/5 - back/forward (Viewing + Universe only)
+    if *state.get() == AppState::Viewing && ui_state.main_view == MainView::Universe {
+        let alt = keyboard.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]);
+        if (alt && keyboard.just_pressed(KeyCode::ArrowLeft))
+            || mouse.just_pressed(MouseButton::Back)
+        {
+            back_events.send(NavigateBackEvent);
+        }
+        if (alt && keyboard.just_pressed(KeyCode::ArrowRight))
+            || mouse.just_pressed(MouseButton::Forward)
+        {
+            forward_events.send(NavigateForwardEvent);
+        }
+    }
+
+    // Home - back to the opened folder, / - find a planet (Viewing + Universe only)
+    if *state.get() == AppState::Viewing && ui_state.main_view == MainView::Universe {
+        if keyboard.just_pressed(KeyCode::Home)
+            && let Some(root) = &current_dir.root
+            && current_dir.path.as_ref() != Some(root)
+        {
+            navigate_events.send(NavigateToEvent { path: root.clone() });
+        }
+        if keyboard.any_just_pressed([KeyCode::Slash, KeyCode::NumpadDivide]) {
+            ui_state.focus_search = true;
+        }
+    }
 }
 
-/// Handle breadcrumb navigation
+/// Keys of keyboard and history navigation (Backspace, Alt+arrows, Home),
+/// which egui would otherwise use to move focus between (and press) its
+/// widgets or to scroll
+const NAVIGATION_KEYS: [egui::Key; 8] = [
+    egui::Key::Tab,
+    egui::Key::ArrowLeft,
+    egui::Key::ArrowRight,
+    egui::Key::ArrowUp,
+    egui::Key::ArrowDown,
+    egui::Key::Enter,
+    egui::Key::Backspace,
+    egui::Key::Home,
+];
+
+/// Keep the navigation keys from egui while no text field is being edited
+/// (runs between egui's input processing and its pass)
+pub fn reserve_navigation_keys(
+    mut contexts: EguiContexts,
+    ui_state: Res<UiState>,
+    mut inputs: Query<&mut EguiInput>,
+) {
+    if ui_state.main_view != MainView::Universe || contexts.ctx_mut().wants_keyboard_input() {
+        return;
+    }
+    for mut input in inputs.iter_mut() {
+        input.events.retain(
+            |event| !matches!(event, egui::Event::Key { key, .. } if NAVIGATION_KEYS.contains(key)),
+        );
+    }
+}
+
+/// Planets in orbital order, with whether they can be drilled into
+type OrbitingPlanets<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static CelestialBody,
+        &'static GlobalTransform,
+        Has<Drillable>,
+    ),
+    With<Planet>,
+>;
+
+/// Keyboard navigation of the system: Tab/arrows cycle the selection in
+/// orbital order, Enter drills into it, and the search field selects the
+/// first match. The camera eases onto the selected planet.
+#[allow(clippy::too_many_arguments)]
+pub fn handle_keyboard_navigation(
+    mut contexts: EguiContexts,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mode: Res<State<ViewingMode>>,
+    mut ui_state: ResMut<UiState>,
+    planets: OrbitingPlanets,
+    mut camera_query: Query<&mut PanOrbitCamera>,
+    mut selection_events: EventWriter<SelectionChangedEvent>,
+    mut drilldown_events: EventWriter<DrillDownEvent>,
+    mut last_search: Local<String>,
+) {
+    if ui_state.main_view != MainView::Universe || *mode.get() == ViewingMode::Animating {
+        return;
+    }
+
+    let mut ordered: Vec<_> = planets.iter().collect();
+    ordered.sort_by(|a, b| {
+        orbital_angle(a.2.translation()).total_cmp(&orbital_angle(b.2.translation()))
+    });
+
+    let mut selected = None;
+    if ui_state.search != *last_search {
+        // A changed search selects its first match
+        last_search.clone_from(&ui_state.search);
+        let query = ui_state.search.trim().to_lowercase();
+        if !query.is_empty() {
+            selected = ordered
+                .iter()
+                .find(|(_, body, ..)| body.name.to_lowercase().contains(&query))
+                .map(|(entity, ..)| *entity);
+        }
+    } else if !contexts.ctx_mut().wants_keyboard_input() {
+        let shift = keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+        let alt = keyboard.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]);
+        let tab = keyboard.just_pressed(KeyCode::Tab);
+        let next = (tab && !shift)
+            || (!alt && keyboard.any_just_pressed([KeyCode::ArrowRight, KeyCode::ArrowDown]));
+        let previous = (tab && shift)
+            || (!alt && keyboard.any_just_pressed([KeyCode::ArrowLeft, KeyCode::ArrowUp]));
+
+        let current = ordered
+            .iter()
+            .position(|(entity, ..)| Some(*entity) == ui_state.selected_entity);
+        let step = match (next, previous) {
+            (true, false) => Some(1),
+            (false, true) => Some(ordered.len().saturating_sub(1)),
+            _ => None,
+        };
+        if let Some(step) = step
+            && !ordered.is_empty()
+        {
+            // Nothing selected: next starts at the first planet, previous at the last
+            let index = match current {
+                Some(index) => (index + step) % ordered.len(),
+                None if next => 0,
+                None => ordered.len() - 1,
+            };
+            selected = Some(ordered[index].0);
+        }
+
+        if keyboard.any_just_pressed([KeyCode::Enter, KeyCode::NumpadEnter])
+            && let Some((entity, body, _, true)) = current.map(|index| ordered[index])
+        {
+            drilldown_events.send(DrillDownEvent {
+                entity,
+                path: body.path.clone(),
+            });
+            return;
+        }
+    }
+
+    let Some(entity) = selected else {
+        return;
+    };
+    if ui_state.selected_entity != Some(entity) {
+        ui_state.selected_entity = Some(entity);
+        selection_events.send(SelectionChangedEvent {
+            entity: Some(entity),
+        });
+    }
+    // The orbit camera eases its focus towards the target
+    if let Ok((_, _, transform, _)) = planets.get(entity) {
+        for mut camera in camera_query.iter_mut() {
+            camera.target_focus = transform.translation();
+        }
+    }
+}
+
+/// Handle breadcrumb navigation (also Home and recent folders), zooming
+/// out while the destination fades in
 pub fn handle_navigate_to(
     mut commands: Commands,
     mut events: EventReader<NavigateToEvent>,
-    mut current_dir: ResMut<CurrentDirectory>,
-    mut breadcrumb: ResMut<Breadcrumb>,
-    mut history: ResMut<NavigationHistory>,
+    mode: Res<State<ViewingMode>>,
+    mut navigation: Navigation,
+    camera_query: Query<(Entity, &PanOrbitCamera)>,
+    config: Res<CameraConfig>,
+    visual: Res<VisualConfig>,
+    stars: Query<&Transform, With<Star>>,
+    mut next_state: ResMut<NextState<ViewingMode>>,
     celestials: Query<Entity, With<CelestialBody>>,
     asteroid_belts: Query<Entity, With<AsteroidBelt>>,
     mut respawn_events: EventWriter<RespawnCelestialsEvent>,
-    persistent_cache: Option<Res<PersistentCache>>,
     mut ui_state: ResMut<UiState>,
 ) {
-    for event in events.read() {
-        // Navigation → always return to Universe
-        ui_state.main_view = MainView::Universe;
-        // Push current directory to history
-        if let Some(current_path) = &current_dir.path {
-            history.push(current_path.clone());
-
-            // Persist history
-            if let Some(ref cache) = persistent_cache {
-                cache.write_history(&history.entries);
-            }
-        }
+    // One navigation per frame, the latest request wins
+    let Some(event) = events.read().last() else {
+        return;
+    };
+    if *mode.get() == ViewingMode::Animating {
+        return;
+    }
 
-        // Update current directory
-        current_dir.path = Some(event.path.clone());
-        *breadcrumb = Breadcrumb::from_path(&event.path);
+    // Navigation → always return to Universe
+    ui_state.main_view = MainView::Universe;
+    navigation.go_to(event.path.clone());
+
+    // The camera may be focused on a planet; bring it back to the new star
+    let star_radius = star_radius(&stars, &visual);
+    for (cam_entity, camera) in camera_query.iter() {
+        commands
+            .entity(cam_entity)
+            .insert(CameraAnimation::drill_up(
+                camera.focus,
+                star_radius,
+                &config,
+            ));
+    }
+    next_state.set(ViewingMode::Animating);
 
-        // Cleanup existing celestials
-        for entity in celestials.iter() {
-            commands.entity(entity).despawn_recursive();
-        }
-        for entity in asteroid_belts.iter() {
-            commands.entity(entity).despawn_recursive();
-        }
+    // Cleanup existing celestials
+    for entity in celestials.iter().chain(asteroid_belts.iter()) {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    // Trigger respawn
+    respawn_events.send(RespawnCelestialsEvent);
 
-        // Trigger respawn
-        respawn_events.send(RespawnCelestialsEvent);
+    info!("Navigated to: {}", event.path.display());
+}
 
-        info!("Navigated to: {}", event.path.display());
+/// Handle back/forward navigation (breadcrumb buttons, Alt+Left/Right,
+/// mouse buttons 4/5) with the drilldown camera transition
+#[allow(clippy::too_many_arguments)]
+pub fn handle_history_navigation(
+    mut commands: Commands,
+    mut back_events: EventReader<NavigateBackEvent>,
+    mut forward_events: EventReader<NavigateForwardEvent>,
+    mode: Res<State<ViewingMode>>,
+    mut navigation: Navigation,
+    planets: Query<(&CelestialBody, &GlobalTransform), With<Planet>>,
+    camera_query: Query<(Entity, &PanOrbitCamera)>,
+    config: Res<CameraConfig>,
+    mut next_state: ResMut<NextState<ViewingMode>>,
+    celestials: Query<Entity, With<CelestialBody>>,
+    asteroid_belts: Query<Entity, With<AsteroidBelt>>,
+    mut respawn_events: EventWriter<RespawnCelestialsEvent>,
+    mut pending: Local<Option<HistoryStep>>,
+) {
+    // One step per frame (back wins if both were asked for). Events expire
+    // after two frames, so a step asked for mid-animation is kept here
+    let back = back_events.read().count() > 0;
+    let forward = forward_events.read().count() > 0;
+    if back {
+        *pending = Some(HistoryStep::Back);
+    } else if forward {
+        *pending = Some(HistoryStep::Forward);
     }
+    if *mode.get() == ViewingMode::Animating {
+        return;
+    }
+    let path = match pending.take() {
+        Some(HistoryStep::Back) => navigation.go_back(),
+        Some(HistoryStep::Forward) => navigation.go_forward(),
+        None => return,
+    };
+    let Some(path) = path else {
+        return;
+    };
+
+    // Fly into the planet if the destination is in view, else into the star
+    let target = planets
+        .iter()
+        .find(|(body, _)| body.path == path)
+        .map_or(Vec3::ZERO, |(_, transform)| transform.translation());
+    for (cam_entity, camera) in camera_query.iter() {
+        let current_radius = camera.radius.unwrap_or(20.0);
+        commands
+            .entity(cam_entity)
+            .insert(CameraAnimation::drilldown(
+                target,
+                camera.focus,
+                current_radius,
+                config.drilldown_duration,
+            ));
+    }
+    next_state.set(ViewingMode::Animating);
+
+    for entity in celestials.iter().chain(asteroid_belts.iter()) {
+        commands.entity(entity).despawn_recursive();
+    }
+    respawn_events.send(RespawnCelestialsEvent);
+
+    info!("History navigation to: {}", path.display());
 }
@@ -0,0 +1,36 @@
+//! Directory loading systems
+//!
+//! Poll background directory reads and leave the Loading state
+//! once the first listing is ready.
+
+use crate::resources::{DirectoryCache, DirectoryLoad, ScanErrors};
+use crate::states::AppState;
+use bevy::prelude::*;
+
+/// System: Collect a finished directory read, cache it and record unreadable entries
+pub fn poll_directory_load(
+    mut load: ResMut<DirectoryLoad>,
+    mut cache: ResMut<DirectoryCache>,
+    mut scan_errors: ResMut<ScanErrors>,
+) {
+    let Some(mut listing) = load.poll() else {
+        return;
+    };
+
+    info!(
+        "Read {} entries from {} ({} unreadable)",
+        listing.entries.len(),
+        listing.path.display(),
+        listing.errors.len()
+    );
+    scan_errors.record(std::mem::take(&mut listing.errors));
+    cache.insert(listing.path.clone(), listing.entries.clone(), listing.modified);
+    load.ready = Some(listing);
+}
+
+/// System: Enter Viewing once the selected folder's listing is ready
+pub fn finish_loading(load: Res<DirectoryLoad>, mut next_state: ResMut<NextState<AppState>>) {
+    if load.ready.is_some() {
+        next_state.set(AppState::Viewing);
+    }
+}
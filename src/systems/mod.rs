@@ -3,16 +3,34 @@
 pub mod camera;
 pub mod cleanup;
 pub mod filesystem;
+pub mod highlight;
+pub mod indexing;
 pub mod interaction;
+pub mod loading;
 pub mod setup;
 pub mod size_calculation;
+pub mod sniffing;
 pub mod spawning;
 pub mod ui;
+pub mod warnings;
+pub mod watching;
 
 pub use camera::*;
 pub use cleanup::*;
+pub use filesystem::{
+    apply_cache_validation, apply_scan_rules, run_cache_maintenance, sync_persistent_cache,
+};
+pub use highlight::*;
+pub use indexing::*;
 pub use interaction::*;
+pub use loading::*;
 pub use setup::*;
-pub use size_calculation::{animate_pulse, update_celestial_sizes, SizeCalculationChannel};
+pub use size_calculation::{
+    animate_pulse, apply_subtree_brightness, derive_star_size, dispatch_size_jobs,
+    update_celestial_sizes, SizeCalculationChannel, SizeJobQueue,
+};
+pub use sniffing::*;
 pub use spawning::*;
 pub use ui::*;
+pub use warnings::*;
+pub use watching::*;
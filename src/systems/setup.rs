@@ -24,7 +24,7 @@ pub fn setup_camera(mut commands: Commands, config: Res<CameraConfig>) {
         Bloom::NATURAL,
         Transform::from_xyz(0.0, 10.0, 20.0).looking_at(Vec3::ZERO, Vec3::Y),
         PanOrbitCamera {
-            radius: Some(20.0),
+            radius: Some(config.view_radius),
             pitch: Some(0.4), // ~23 degrees
             yaw: Some(0.0),
             pitch_lower_limit: Some(-config.pitch_limit.to_radians()),
@@ -162,32 +162,121 @@ fn hash_f32(seed: u32, offset: u32) -> f32 {
     hash_u32(seed, offset) as f32 / u32::MAX as f32
 }
 
-/// Initialize persistent cache and load persisted navigation history
+/// Initialize persistent cache and load the persisted recent folders
 pub fn initialize_persistent_cache(
     mut commands: Commands,
-    mut history: ResMut<NavigationHistory>,
+    mut recent: ResMut<RecentFolders>,
+    rules: Res<ScanRules>,
+    settings: Res<SidebarSettings>,
+    mut status: ResMut<CacheStatus>,
 ) {
-    if let Some(cache) = PersistentCache::new(3600) {
-        // Load persisted history entries (filter to paths that still exist)
-        let persisted = cache.load_history();
-        for entry in persisted {
-            if entry.exists() && !history.entries.contains(&entry) {
-                history.entries.push(entry);
+    match PersistentCache::new(CacheValidation::from_settings(&settings)) {
+        Ok(mut cache) => {
+            cache.set_rules_fingerprint(rules.fingerprint());
+            if let Some(recovery) = cache.recovery() {
+                *status = CacheStatus::Recovered(recovery.clone());
             }
+
+            // Load persisted recent folders (filter to paths that still exist)
+            let persisted = cache.load_history();
+            for entry in persisted {
+                if entry.exists() && !recent.entries.contains(&entry) {
+                    recent.entries.push(entry);
+                }
+            }
+            let max = recent.max_entries;
+            recent.entries.truncate(max);
+
+            if !recent.entries.is_empty() {
+                info!(
+                    "Loaded {} recent folders from persistent cache",
+                    recent.entries.len()
+                );
+            }
+
+            commands.insert_resource(cache);
         }
-        let max = history.max_entries;
-        history.entries.truncate(max);
-
-        if !history.entries.is_empty() {
-            info!(
-                "Loaded {} history entries from persistent cache",
-                history.entries.len()
-            );
+        Err(reason) => {
+            warn!("Persistent cache unavailable, running without persistence");
+            *status = CacheStatus::Unavailable { reason };
         }
+    }
+}
 
-        commands.insert_resource(cache);
-    } else {
-        warn!("Persistent cache unavailable, running without persistence");
+/// Restore the settings saved from the settings page (after `setup_theme`,
+/// so a saved theme wins over the OS one)
+pub fn load_user_settings(
+    mut store: ResMut<SettingsStore>,
+    mut sidebar_settings: ResMut<SidebarSettings>,
+    mut theme_config: ResMut<ThemeConfig>,
+    mut visual_config: ResMut<VisualConfig>,
+    mut camera_config: ResMut<CameraConfig>,
+) {
+    match store.load(
+        &mut sidebar_settings,
+        &mut theme_config,
+        &mut visual_config,
+        &mut camera_config,
+    ) {
+        Ok(true) => {
+            if let Some(path) = store.path() {
+                info!("Loaded settings from {}", path.display());
+            }
+        }
+        Ok(false) => {}
+        Err(e) => warn!("Failed to load settings, using defaults: {}", e),
+    }
+}
+
+/// Save the settings once they stop changing, whichever system changed them
+pub fn save_user_settings(
+    time: Res<Time>,
+    mut store: ResMut<SettingsStore>,
+    sidebar_settings: Res<SidebarSettings>,
+    theme_config: Res<ThemeConfig>,
+    visual_config: Res<VisualConfig>,
+    camera_config: Res<CameraConfig>,
+    mut started: Local<bool>,
+) {
+    // Everything reads as changed on the first run, right after loading
+    if !*started {
+        *started = true;
+        return;
+    }
+    let now = time.elapsed_secs();
+    if sidebar_settings.is_changed()
+        || theme_config.is_changed()
+        || visual_config.is_changed()
+        || camera_config.is_changed()
+    {
+        store.mark_changed(now);
+    }
+    if !store.save_due(now) {
+        return;
+    }
+    if let Err(e) = store.save(
+        &sidebar_settings,
+        &theme_config,
+        &visual_config,
+        &camera_config,
+    ) {
+        warn!("Failed to save settings: {}", e);
+    }
+}
+
+/// Apply the visual encoding from `closm-probe.toml`
+pub fn load_visual_encoding(
+    encoding_file: Res<EncodingFile>,
+    mut visual_config: ResMut<VisualConfig>,
+) {
+    match encoding_file.load(&mut visual_config) {
+        Ok(true) => {
+            if let Some(path) = encoding_file.path() {
+                info!("Loaded visual encoding from {}", path.display());
+            }
+        }
+        Ok(false) => {}
+        Err(e) => warn!("Failed to load visual encoding, using defaults: {}", e),
     }
 }
 
@@ -1,32 +1,87 @@
 //! jwalk-based parallel directory size calculation
 //!
-//! Fallback implementation for non-macOS platforms.
+//! Used on non-macOS platforms, and on macOS for jobs that need subtree stats.
 
-#![cfg(not(target_os = "macos"))]
-
-use super::SizeResult;
+use super::{CancelFlag, SizeJob, SizeResult};
+use crate::resources::{CacheStamp, DiskUsage, ScanError, ScanRules, SubtreeStats};
 use bevy::tasks::IoTaskPool;
 use crossbeam_channel::Sender;
-use std::path::PathBuf;
+use std::path::Path;
 
-/// Spawn background calculations using jwalk parallel traversal
-pub fn spawn_calculations(paths: Vec<PathBuf>, sender: Sender<SizeResult>) {
+/// Spawn a background calculation using jwalk parallel traversal
+pub fn spawn_calculation(job: SizeJob, rules: ScanRules, sender: Sender<SizeResult>) {
     IoTaskPool::get()
         .spawn(async move {
-            for path in paths {
-                let size = calculate_with_jwalk(&path);
-                let _ = sender.send(SizeResult { path, size });
-            }
+            // Stamped first, so changes during the walk make the cached result stale
+            let stamp = CacheStamp::read(&job.path);
+            let Some((usage, stats, errors)) = calculate_with_jwalk(&job.path, &rules, &job.cancel)
+            else {
+                return;
+            };
+            let _ = sender.send(SizeResult {
+                path: job.path,
+                usage,
+                stats: Some(stats),
+                fingerprint: rules.fingerprint(),
+                batch: job.batch,
+                stamp,
+                errors,
+            });
         })
         .detach();
 }
 
-/// Calculate directory size using jwalk parallel traversal
-fn calculate_with_jwalk(path: &PathBuf) -> u64 {
-    jwalk::WalkDir::new(path)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().is_file())
-        .map(|e| e.metadata().map(|m| m.len()).unwrap_or(0))
-        .sum()
+/// Calculate directory size using jwalk parallel traversal (None if cancelled)
+///
+/// Unreadable entries are skipped and reported alongside the (lower bound) size.
+fn calculate_with_jwalk(
+    path: &Path,
+    rules: &ScanRules,
+    cancel: &CancelFlag,
+) -> Option<(DiskUsage, SubtreeStats, Vec<ScanError>)> {
+    let mut total = DiskUsage::default();
+    let mut stats = SubtreeStats::default();
+    let mut errors = Vec::new();
+    let mut bounds = rules.bounds(path);
+    for entry in rules.walker(path) {
+        if cancel.is_cancelled() {
+            return None;
+        }
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                errors.push(ScanError::from_walk(&e, path));
+                continue;
+            }
+        };
+        if let Some(e) = &entry.read_children_error {
+            errors.push(ScanError::from_walk(e, &entry.path()));
+        }
+        // Linked directories are descended by the walker; linked files count their target
+        let metadata = if !entry.file_type().is_symlink() {
+            match entry.metadata() {
+                Ok(metadata) => Some(metadata),
+                Err(e) => {
+                    errors.push(ScanError::from_walk(&e, &entry.path()));
+                    None
+                }
+            }
+        } else if bounds.follows_symlinks() {
+            std::fs::metadata(entry.path()).ok()
+        } else {
+            None
+        };
+        let Some(metadata) = metadata else {
+            continue;
+        };
+        if entry.depth == 0 {
+            stats.touch(&metadata);
+            continue;
+        }
+        stats.add_entry(&entry.path(), &metadata);
+        if metadata.is_file() && bounds.first_link(&metadata) {
+            total += DiskUsage::from_metadata(&metadata);
+        }
+    }
+    Some((total, stats, errors))
 }
@@ -1,45 +1,161 @@
 //! macOS directory size calculation
 //!
-//! Uses `du` command for fast directory size calculation.
+//! Uses `du` command for fast directory size calculation
+//! (`-sk` for allocated blocks, `-skA` for apparent size). du prints whole
+//! kilobytes, so apparent sizes are up to 1023 bytes above the walkers'.
 
-use super::SizeResult;
+use super::{CancelFlag, SizeJob, SizeResult};
+use crate::resources::{
+    CacheStamp, DiskUsage, IgnoreStack, ScanError, ScanErrorKind, ScanRules, SubtreeStats,
+    WalkBounds,
+};
 use bevy::log::info;
 use crossbeam_channel::Sender;
+use std::io::Read;
 use std::path::PathBuf;
-use std::process::Command;
+use std::process::{Command, Stdio};
 use std::thread;
+use std::time::Duration;
 
-/// Spawn background calculations using du command
-pub fn spawn_calculations(paths: Vec<PathBuf>, sender: Sender<SizeResult>) {
-    info!("Spawning size calculations for {} directories", paths.len());
+/// How often a running `du` checks for cancellation
+const DU_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Spawn a background calculation using du command
+pub fn spawn_calculation(job: SizeJob, rules: ScanRules, sender: Sender<SizeResult>) {
+    info!("Spawning size calculation for {}", job.path.display());
 
     // Use std::thread for reliable background execution
     thread::spawn(move || {
-        for path in paths {
-            let size = calculate_with_du(&path);
-            let _ = sender.send(SizeResult { path, size });
-        }
+        // Stamped first, so changes during the walk make the cached result stale
+        let stamp = CacheStamp::read(&job.path);
+        let Some((usage, stats, errors)) = calculate_with_du(&job.path, &rules, &job.cancel) else {
+            return;
+        };
+        let _ = sender.send(SizeResult {
+            path: job.path,
+            usage,
+            stats,
+            fingerprint: rules.fingerprint(),
+            batch: job.batch,
+            stamp,
+            errors,
+        });
     });
 }
 
-/// Calculate directory size using `du -sk` command
-/// Returns size in bytes
-fn calculate_with_du(path: &PathBuf) -> u64 {
+/// Outcome of a single `du` invocation
+enum DuRun {
+    /// Size in bytes, and the entries du couldn't read
+    Size(u64, Vec<ScanError>),
+    Cancelled,
+    Failed,
+}
+
+/// Calculate directory size using `du -sk` (allocated) and `du -skA` (apparent)
+/// Returns None if cancelled
+///
+/// du only reports totals, so jobs that need subtree stats use the jwalk
+/// walker instead (see `spawn_size_job`).
+fn calculate_with_du(
+    path: &PathBuf,
+    rules: &ScanRules,
+    cancel: &CancelFlag,
+) -> Option<(DiskUsage, Option<SubtreeStats>, Vec<ScanError>)> {
+    // Rules du can't express (gitignore, include patterns) use the filtered walk
+    let Some(masks) = rules.du_masks() else {
+        return calculate_fallback(path, rules, cancel);
+    };
+
     let path_str = match path.to_str() {
         Some(s) => s,
-        None => return calculate_fallback(path),
+        None => return calculate_fallback(path, rules, cancel),
     };
 
-    // du -sk: summarize, kilobytes
-    let output = Command::new("du")
-        .args(["-sk", path_str])
-        .output();
+    let flags = rules.du_flags();
+    // Both runs meet the same unreadable entries; keep the first run's report
+    let (allocated, errors) = match run_du(path_str, &flags, &masks, false, cancel) {
+        DuRun::Size(size, errors) => (size, errors),
+        DuRun::Cancelled => return None,
+        DuRun::Failed => return calculate_fallback(path, rules, cancel),
+    };
+    let apparent = match run_du(path_str, &flags, &masks, true, cancel) {
+        DuRun::Size(size, _) => size,
+        DuRun::Cancelled => return None,
+        DuRun::Failed => return calculate_fallback(path, rules, cancel),
+    };
+
+    Some((
+        DiskUsage {
+            apparent,
+            allocated,
+        },
+        None,
+        errors,
+    ))
+}
 
-    match output {
-        Ok(output) if output.status.success() => {
-            parse_du_output(&String::from_utf8_lossy(&output.stdout))
+/// Run `du -sk` once, polling so a cancelled job kills du instead of waiting for it
+fn run_du(
+    path_str: &str,
+    flags: &[&str],
+    masks: &[String],
+    apparent: bool,
+    cancel: &CancelFlag,
+) -> DuRun {
+    // du -sk: summarize, kilobytes; -A: apparent size; -l/-x/-L: hardlinks,
+    // one filesystem, follow symlinks; -I: skip entries matching mask
+    let mut command = Command::new("du");
+    command.arg(if apparent { "-skA" } else { "-sk" });
+    command.args(flags);
+    for mask in masks {
+        command.args(["-I", mask]);
+    }
+    let child = command
+        .arg(path_str)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+    let Ok(mut child) = child else {
+        return DuRun::Failed;
+    };
+
+    // Drain stderr while du runs, so a long error list can't fill the pipe and block it
+    let stderr = child.stderr.take().map(|mut pipe| {
+        thread::spawn(move || {
+            let mut text = String::new();
+            let _ = pipe.read_to_string(&mut text);
+            text
+        })
+    });
+
+    loop {
+        if cancel.is_cancelled() {
+            let _ = child.kill();
+            let _ = child.wait();
+            return DuRun::Cancelled;
+        }
+        match child.try_wait() {
+            Ok(Some(_)) => break,
+            Ok(None) => thread::sleep(DU_POLL_INTERVAL),
+            Err(_) => return DuRun::Failed,
         }
-        _ => calculate_fallback(path),
+    }
+
+    let errors = stderr
+        .and_then(|reader| reader.join().ok())
+        .map(|text| parse_du_errors(&text))
+        .unwrap_or_default();
+
+    // du skips unreadable entries and exits non-zero; its total is then a lower bound
+    match child.wait_with_output() {
+        Ok(output) if output.status.success() || !errors.is_empty() => {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            if stdout.trim().is_empty() {
+                return DuRun::Failed;
+            }
+            DuRun::Size(parse_du_output(&stdout), errors)
+        }
+        _ => DuRun::Failed,
     }
 }
 
@@ -54,25 +170,120 @@ fn parse_du_output(output: &str) -> u64 {
         .unwrap_or(0)
 }
 
-/// Fallback to standard filesystem traversal if Spotlight fails
-fn calculate_fallback(path: &PathBuf) -> u64 {
+/// Parse du diagnostics: "du: /path/to/entry: Permission denied"
+fn parse_du_errors(stderr: &str) -> Vec<ScanError> {
+    stderr
+        .lines()
+        .filter_map(|line| line.strip_prefix("du: "))
+        .filter_map(|line| line.rsplit_once(": "))
+        .map(|(path, message)| ScanError {
+            path: PathBuf::from(path),
+            kind: match message {
+                "Permission denied" | "Operation not permitted" => ScanErrorKind::PermissionDenied,
+                "No such file or directory" => ScanErrorKind::NotFound,
+                "Too many levels of symbolic links" => ScanErrorKind::Loop,
+                _ => ScanErrorKind::Other,
+            },
+            message: message.to_string(),
+        })
+        .collect()
+}
+
+/// Fallback to standard filesystem traversal if du fails
+fn calculate_fallback(
+    path: &PathBuf,
+    rules: &ScanRules,
+    cancel: &CancelFlag,
+) -> Option<(DiskUsage, Option<SubtreeStats>, Vec<ScanError>)> {
+    let mut bounds = rules.bounds(path);
+    let mut stats = SubtreeStats::default();
+    let mut errors = Vec::new();
+    if let Ok(metadata) = std::fs::metadata(path) {
+        stats.touch(&metadata);
+    }
+    let usage = walk_fallback(
+        path,
+        rules,
+        &rules.ignores_for(path),
+        &mut bounds,
+        &mut stats,
+        &mut errors,
+        cancel,
+    )?;
+    Some((usage, Some(stats), errors))
+}
+
+fn walk_fallback(
+    path: &PathBuf,
+    rules: &ScanRules,
+    ignores: &IgnoreStack,
+    bounds: &mut WalkBounds,
+    stats: &mut SubtreeStats,
+    errors: &mut Vec<ScanError>,
+    cancel: &CancelFlag,
+) -> Option<DiskUsage> {
     use std::fs;
 
-    let mut total = 0u64;
+    let mut total = DiskUsage::default();
+
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(e) => {
+            errors.push(ScanError::from_io(path, &e));
+            return Some(total);
+        }
+    };
 
-    if let Ok(entries) = fs::read_dir(path) {
-        for entry in entries.flatten() {
-            if let Ok(metadata) = entry.metadata() {
-                if metadata.is_file() {
-                    total += metadata.len();
-                } else if metadata.is_dir() {
-                    total += calculate_fallback(&entry.path());
-                }
+    for entry in entries {
+        if cancel.is_cancelled() {
+            return None;
+        }
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                errors.push(ScanError::from_io(path, &e));
+                continue;
             }
+        };
+        let entry_path = entry.path();
+        let mut metadata = entry.metadata();
+        if bounds.follows_symlinks() && metadata.as_ref().is_ok_and(|m| m.is_symlink()) {
+            metadata = fs::metadata(&entry_path);
+        }
+        let metadata = match metadata {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                errors.push(ScanError::from_io(&entry_path, &e));
+                continue;
+            }
+        };
+        if !rules.allows(&entry_path, metadata.is_dir(), ignores) {
+            continue;
+        }
+        stats.add_entry(&entry_path, &metadata);
+        if metadata.is_file() {
+            if bounds.first_link(&metadata) {
+                total += DiskUsage::from_metadata(&metadata);
+            }
+        } else if metadata.is_dir()
+            && bounds.same_filesystem(&metadata)
+            && bounds.enter_directory(&metadata)
+        {
+            let mut child_ignores = ignores.clone();
+            rules.descend(&mut child_ignores, &entry_path);
+            total += walk_fallback(
+                &entry_path,
+                rules,
+                &child_ignores,
+                bounds,
+                stats,
+                errors,
+                cancel,
+            )?;
         }
     }
 
-    total
+    Some(total)
 }
 
 #[cfg(test)]
@@ -96,4 +307,16 @@ mod tests {
         let output = "100\t/Users/test/path with spaces\n";
         assert_eq!(parse_du_output(output), 100 * 1024);
     }
+
+    #[test]
+    fn test_parse_du_errors() {
+        let stderr = "du: /Users/test/private: Permission denied\n\
+                      du: /Users/test/a: b/gone: No such file or directory\n";
+        let errors = parse_du_errors(stderr);
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].path, PathBuf::from("/Users/test/private"));
+        assert_eq!(errors[0].kind, ScanErrorKind::PermissionDenied);
+        assert_eq!(errors[1].path, PathBuf::from("/Users/test/a: b/gone"));
+        assert_eq!(errors[1].kind, ScanErrorKind::NotFound);
+    }
 }
@@ -1,28 +1,91 @@
 //! Platform-specific directory size calculation
 //!
 //! - macOS: Uses `du` command for fast calculation (with pure Rust fallback)
-//! - Other platforms: Uses jwalk crate for parallel traversal
+//!   when only the total is needed
+//! - Other platforms, and jobs that need subtree stats: Uses jwalk crate for
+//!   parallel traversal
+//! - Jobs are scheduled and cancelled by `SizeJobQueue`
 
-mod macos_du; // macOS implementation (du command)
+// macOS implementation (du command); compiled everywhere so its tests run
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+mod macos_du;
 
-#[cfg(not(target_os = "macos"))]
 mod jwalk_calc;
 
+mod queue;
+
+pub use queue::{dispatch_size_jobs, CancelFlag, SizeJob, SizeJobQueue};
+
 use bevy::prelude::*;
 use crossbeam_channel::{bounded, Receiver, Sender};
 use std::path::PathBuf;
 
-use crate::components::{CelestialBody, Planet, PendingSizeCalculation, PulseAnimation};
-use crate::resources::{PersistentCache, VisualConfig};
-use crate::utils::calculate_size;
+use crate::components::{
+    AsteroidBelt, Brightness, CelestialBody, FileType, PendingSizeCalculation, Planet, PulseAnimation, Star,
+    Subtree,
+};
+use crate::resources::{
+    CacheStamp, DiskUsage, PersistentCache, ScanError, ScanErrors, ScanRules, SubtreeStats, VisualConfig,
+};
+use crate::utils::{calculate_brightness, calculate_size, calculate_star_scale, celestial_emissive};
 
 /// Result of a size calculation
 #[derive(Debug, Clone)]
 pub struct SizeResult {
     pub path: PathBuf,
-    pub size: u64,
+    /// Apparent and allocated size
+    pub usage: DiskUsage,
+    /// File/folder counts, largest file and newest mtime (None if the
+    /// job didn't need them and the worker only saw totals, e.g. `du`)
+    pub stats: Option<SubtreeStats>,
+    /// `ScanRules` fingerprint the size was calculated with
+    pub fingerprint: u64,
+    /// `SizeJobQueue` batch the job belonged to
+    pub batch: u64,
+    /// Directory stamp taken before the walk (None if it couldn't be read)
+    pub stamp: Option<CacheStamp>,
+    /// Entries the walk couldn't read (the size is a lower bound if any)
+    pub errors: Vec<ScanError>,
 }
 
+/// Directories (planets and the star) still waiting for their size
+pub type PendingDirectories<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static mut CelestialBody,
+        &'static mut Transform,
+        Option<&'static Planet>,
+        Has<Star>,
+    ),
+    With<PendingSizeCalculation>,
+>;
+
+/// The star while its total is unknown
+pub type PendingStar<'w, 's> = Query<
+    'w,
+    's,
+    (Entity, &'static mut CelestialBody, &'static mut Transform),
+    (With<Star>, With<PendingSizeCalculation>),
+>;
+
+/// Sizes of the planets around the star, and whether each is still being calculated
+pub type PlanetSizes<'w, 's> = Query<
+    'w,
+    's,
+    (&'static CelestialBody, Has<PendingSizeCalculation>),
+    (With<Planet>, Without<Star>),
+>;
+
+/// Planets whose size is known (the ones a stats pass reports on)
+pub type SizedPlanets<'w, 's> = Query<
+    'w,
+    's,
+    (Entity, &'static CelestialBody),
+    (With<Planet>, Without<PendingSizeCalculation>),
+>;
+
 /// Channel for receiving size calculation results
 #[derive(Resource)]
 pub struct SizeCalculationChannel {
@@ -37,44 +100,93 @@ impl Default for SizeCalculationChannel {
     }
 }
 
-/// Spawn background size calculations for directory entries
-pub fn spawn_size_calculations(paths: Vec<PathBuf>, sender: Sender<SizeResult>) {
-    if paths.is_empty() {
+/// Start a single size job on the platform-specific worker
+pub fn spawn_size_job(job: SizeJob, rules: &ScanRules, sender: Sender<SizeResult>) {
+    // du only reports totals, so jobs that need stats walk instead
+    #[cfg(target_os = "macos")]
+    if !job.needs_stats {
+        macos_du::spawn_calculation(job, rules.clone(), sender);
         return;
     }
 
-    #[cfg(target_os = "macos")]
-    macos_du::spawn_calculations(paths, sender);
-
-    #[cfg(not(target_os = "macos"))]
-    jwalk_calc::spawn_calculations(paths, sender);
+    jwalk_calc::spawn_calculation(job, rules.clone(), sender);
 }
 
 /// System: Update celestial body sizes from background calculations
+#[allow(clippy::too_many_arguments)]
 pub fn update_celestial_sizes(
     mut commands: Commands,
     channel: Res<SizeCalculationChannel>,
-    mut celestials: Query<
-        (Entity, &mut CelestialBody, &mut Transform, &Planet),
-        With<PendingSizeCalculation>,
-    >,
+    mut celestials: PendingDirectories,
     config: Res<VisualConfig>,
     persistent_cache: Option<Res<PersistentCache>>,
+    rules: Res<ScanRules>,
+    mut queue: ResMut<SizeJobQueue>,
+    mut scan_errors: ResMut<ScanErrors>,
+    mut belts: Query<&mut AsteroidBelt>,
+    sized_planets: SizedPlanets,
 ) {
     // Process all available results (non-blocking)
-    while let Ok(result) = channel.receiver.try_recv() {
+    while let Ok(mut result) = channel.receiver.try_recv() {
+        // Finished after its batch was cancelled
+        if !queue.complete(&result) {
+            continue;
+        }
+        // Calculated before the scan rules changed
+        if result.fingerprint != rules.fingerprint() {
+            continue;
+        }
+
+        // The job walked the whole subtree, so its errors replace older ones
+        scan_errors.clear_below(&result.path);
+        scan_errors.record(std::mem::take(&mut result.errors));
+
         info!(
-            "Received size result: {} = {} bytes",
+            "Received size result: {} = {} bytes ({} allocated)",
             result.path.display(),
-            result.size
+            result.usage.apparent,
+            result.usage.allocated
         );
-        for (entity, mut body, mut transform, planet) in celestials.iter_mut() {
-            if body.path == result.path && planet.is_directory {
+
+        // Second pass: stats for a planet whose size came without them
+        if let Some(stats) = &result.stats
+            && let Some((entity, _)) = sized_planets.iter().find(|(_, b)| b.path == result.path)
+        {
+            if let (Some(cache), Some(stamp)) = (&persistent_cache, result.stamp) {
+                cache.write_stats(&result.path, stats, stamp);
+            }
+            commands.entity(entity).insert(Subtree {
+                stats: stats.clone(),
+            });
+            continue;
+        }
+
+        // Directory folded into the asteroid belt: no planet, only its share
+        for mut belt in belts.iter_mut() {
+            if let Some(size) = belt.directories.get_mut(&result.path) {
+                *size = Some(result.usage);
+                if let (Some(cache), Some(stamp)) = (&persistent_cache, result.stamp) {
+                    cache.write_size(&result.path, result.usage, stamp);
+                    if let Some(stats) = &result.stats {
+                        cache.write_stats(&result.path, stats, stamp);
+                    }
+                }
+            }
+        }
+
+        for (entity, mut body, mut transform, planet, is_star) in celestials.iter_mut() {
+            let is_directory = is_star || planet.is_some_and(|p| p.is_directory);
+            if body.path == result.path && is_directory {
                 // Update size
-                body.size_bytes = result.size;
+                body.usage = result.usage;
 
                 // Recalculate visual size
-                let new_size = calculate_size(result.size, true, &config);
+                let bytes = result.usage.get(config.size_mode);
+                let new_size = if is_star {
+                    calculate_star_scale(bytes, &config)
+                } else {
+                    calculate_size(bytes, true, &config)
+                };
                 transform.scale = Vec3::splat(new_size);
 
                 // Stop animation
@@ -84,13 +196,23 @@ pub fn update_celestial_sizes(
                     .remove::<PendingSizeCalculation>();
 
                 // Persist to disk cache
-                if let Some(ref cache) = persistent_cache {
-                    cache.write_size(&result.path, result.size);
+                if let (Some(cache), Some(stamp)) = (&persistent_cache, result.stamp) {
+                    cache.write_size(&result.path, result.usage, stamp);
+                    if let Some(stats) = &result.stats {
+                        cache.write_stats(&result.path, stats, stamp);
+                    }
+                }
+                if let Some(stats) = result.stats.take() {
+                    commands.entity(entity).insert(Subtree { stats });
+                } else if !is_star {
+                    // Sized by `du`; walk it again for its stats once all sizes are in
+                    queue.enqueue_stats(&result.path);
                 }
 
                 info!(
                     "Size calculated: {} = {} bytes",
-                    body.name, result.size
+                    body.name,
+                    result.usage.get(config.size_mode)
                 );
 
                 break;
@@ -99,6 +221,44 @@ pub fn update_celestial_sizes(
     }
 }
 
+/// System: Size a star without a known total from its planets and asteroid belt
+///
+/// Every entry of the directory is either a planet or in the belt, so once
+/// all of them are sized their sum is the directory's total, as in the
+/// persistent cache's roll-up. The star never gets a walk of its own.
+pub fn derive_star_size(
+    mut commands: Commands,
+    mut stars: PendingStar,
+    planets: PlanetSizes,
+    belts: Query<&AsteroidBelt>,
+    config: Res<VisualConfig>,
+) {
+    let Ok((entity, mut body, mut transform)) = stars.get_single_mut() else {
+        return;
+    };
+    if planets.iter().any(|(_, pending)| pending) || belts.iter().any(AsteroidBelt::is_sizing) {
+        return;
+    }
+
+    let mut usage = DiskUsage::default();
+    for (planet, _) in planets.iter() {
+        usage += planet.usage;
+    }
+    for belt in belts.iter() {
+        usage += belt.usage();
+    }
+
+    body.usage = usage;
+    transform.scale = Vec3::splat(calculate_star_scale(usage.get(config.size_mode), &config));
+    commands.entity(entity).remove::<PendingSizeCalculation>();
+
+    info!(
+        "Star size derived from its children: {} = {} bytes",
+        body.name,
+        usage.get(config.size_mode)
+    );
+}
+
 /// System: Animate pulsing effect for pending calculations
 pub fn animate_pulse(
     time: Res<Time>,
@@ -117,3 +277,24 @@ pub fn animate_pulse(
         }
     }
 }
+
+/// System: Re-light planets by their newest descendant once subtree stats arrive
+pub fn apply_subtree_brightness(
+    mut planets: Query<
+        (&Subtree, &mut Brightness, &FileType, &MeshMaterial3d<StandardMaterial>),
+        Changed<Subtree>,
+    >,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    config: Res<VisualConfig>,
+) {
+    for (subtree, mut brightness, file_type, material_handle) in planets.iter_mut() {
+        let updated = calculate_brightness(subtree.stats.newest_modified, &config);
+        if updated.value == brightness.value {
+            continue;
+        }
+        brightness.value = updated.value;
+        if let Some(material) = materials.get_mut(&material_handle.0) {
+            material.emissive = celestial_emissive(*file_type, updated.value, &config);
+        }
+    }
+}
@@ -0,0 +1,307 @@
+//! Size calculation job queue
+//!
+//! Schedules directory size jobs for the current directory: the hovered
+//! and selected planets go first, a bounded number run in parallel, and
+//! the whole batch is cancelled when the user navigates elsewhere. Planets
+//! sized without subtree stats are walked again for them once every size
+//! is known.
+
+use super::{spawn_size_job, SizeCalculationChannel, SizeResult};
+use crate::components::CelestialBody;
+use crate::resources::{CurrentDirectory, ScanRules, SidebarSettings, UiState};
+use bevy::prelude::*;
+use std::collections::{HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Cancellation flag shared by all jobs of a batch
+#[derive(Debug, Clone, Default)]
+pub struct CancelFlag(Arc<AtomicBool>);
+
+impl CancelFlag {
+    /// Ask all jobs holding this flag to stop
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// True once cancelled (workers poll this between entries)
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Single directory size job handed to a worker
+#[derive(Debug, Clone)]
+pub struct SizeJob {
+    pub path: PathBuf,
+    /// Batch the job belongs to (results from older batches are dropped)
+    pub batch: u64,
+    pub cancel: CancelFlag,
+    /// True for the second pass that collects subtree stats of a planet
+    /// whose size job didn't (so it can't be answered by `du`)
+    #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+    pub needs_stats: bool,
+}
+
+/// Pending and running size jobs for the current directory
+#[derive(Resource, Debug, Default)]
+pub struct SizeJobQueue {
+    /// Directory whose children are being sized
+    directory: Option<PathBuf>,
+    /// Waiting jobs, front runs first
+    pending: VecDeque<PathBuf>,
+    /// Planets still missing subtree stats, walked once `pending` is empty
+    pending_stats: VecDeque<PathBuf>,
+    /// Jobs handed to workers and not yet reported
+    running: HashSet<PathBuf>,
+    /// Running jobs whose directory changed meanwhile (rerun when they report)
+    stale: HashSet<PathBuf>,
+    /// Current batch id (bumped on cancel)
+    batch: u64,
+    /// Cancellation flag of the current batch
+    cancel: CancelFlag,
+}
+
+impl SizeJobQueue {
+    /// Queue jobs for children of `directory`; jobs for any other directory are cancelled
+    pub fn enqueue(&mut self, directory: &Path, paths: Vec<PathBuf>) {
+        if self.directory.as_deref() != Some(directory) {
+            self.cancel_all();
+            self.directory = Some(directory.to_path_buf());
+        }
+        for path in paths {
+            if !self.running.contains(&path) && !self.pending.contains(&path) {
+                self.pending.push_back(path);
+            }
+        }
+    }
+
+    /// Queue jobs again after their directories changed on disk
+    ///
+    /// A job that is already running would report the old size, so its
+    /// result is dropped and the job rerun instead.
+    pub fn requeue(&mut self, directory: &Path, paths: Vec<PathBuf>) {
+        if self.directory.as_deref() != Some(directory) {
+            self.enqueue(directory, paths);
+            return;
+        }
+        for path in paths {
+            if self.running.contains(&path) {
+                self.stale.insert(path);
+            } else if !self.pending.contains(&path) {
+                self.pending.push_back(path);
+            }
+        }
+    }
+
+    /// Cancel running jobs and drop pending ones
+    pub fn cancel_all(&mut self) {
+        if !self.pending.is_empty() || !self.running.is_empty() {
+            info!(
+                "Cancelling size jobs ({} queued, {} running)",
+                self.pending.len(),
+                self.running.len()
+            );
+        }
+        self.cancel.cancel();
+        self.cancel = CancelFlag::default();
+        self.batch += 1;
+        self.pending.clear();
+        self.pending_stats.clear();
+        self.running.clear();
+        self.stale.clear();
+    }
+
+    /// Drop a pending job whose size became known elsewhere
+    pub fn remove(&mut self, path: &Path) {
+        self.pending.retain(|p| p != path);
+        self.pending_stats.retain(|p| p != path);
+    }
+
+    /// Queue a planet's stats walk after all size jobs (its size is already known)
+    pub fn enqueue_stats(&mut self, path: &Path) {
+        if !self.pending_stats.iter().any(|p| p == path) {
+            self.pending_stats.push_back(path.to_path_buf());
+        }
+    }
+
+    /// Move a pending job to the front of its queue
+    pub fn prioritize(&mut self, path: &Path) {
+        for pending in [&mut self.pending, &mut self.pending_stats] {
+            if let Some(position) = pending.iter().position(|p| p == path)
+                && let Some(job) = pending.remove(position)
+            {
+                pending.push_front(job);
+            }
+        }
+    }
+
+    /// Mark a result's job as finished. Returns false for results of cancelled
+    /// batches and of jobs requeued while running.
+    pub fn complete(&mut self, result: &SizeResult) -> bool {
+        if result.batch != self.batch {
+            return false;
+        }
+        self.running.remove(&result.path);
+        if self.stale.remove(&result.path) {
+            self.pending.push_back(result.path.clone());
+            return false;
+        }
+        true
+    }
+
+    /// Jobs waiting for a worker
+    pub fn queued(&self) -> usize {
+        self.pending.len() + self.pending_stats.len()
+    }
+
+    /// Jobs currently running
+    pub fn running(&self) -> usize {
+        self.running.len()
+    }
+
+    /// True if nothing is queued or running
+    pub fn is_idle(&self) -> bool {
+        self.pending.is_empty() && self.pending_stats.is_empty() && self.running.is_empty()
+    }
+
+    /// Next job, if fewer than `limit` are running; stats walks only start
+    /// once no size job is waiting
+    fn next_job(&mut self, limit: usize) -> Option<SizeJob> {
+        if self.running.len() >= limit.max(1) {
+            return None;
+        }
+        let (path, needs_stats) = match self.pending.pop_front() {
+            Some(path) => (path, false),
+            None => (self.pending_stats.pop_front()?, true),
+        };
+        self.running.insert(path.clone());
+        Some(SizeJob {
+            path,
+            batch: self.batch,
+            cancel: self.cancel.clone(),
+            needs_stats,
+        })
+    }
+}
+
+/// System: Cancel stale jobs, prioritize hovered/selected planets and start jobs up to the limit
+pub fn dispatch_size_jobs(
+    mut queue: ResMut<SizeJobQueue>,
+    current_dir: Res<CurrentDirectory>,
+    ui_state: Res<UiState>,
+    celestials: Query<&CelestialBody>,
+    settings: Res<SidebarSettings>,
+    rules: Res<ScanRules>,
+    channel: Res<SizeCalculationChannel>,
+) {
+    // Navigated away (or opened another folder) before the batch finished
+    if queue.directory.is_some() && queue.directory != current_dir.path {
+        queue.cancel_all();
+        queue.directory = None;
+    }
+
+    if queue.pending.is_empty() && queue.pending_stats.is_empty() {
+        return;
+    }
+
+    // Hovered ends up in front of selected
+    for entity in [ui_state.selected_entity, ui_state.hovered_entity]
+        .into_iter()
+        .flatten()
+    {
+        if let Ok(body) = celestials.get(entity) {
+            queue.prioritize(&body.path);
+        }
+    }
+
+    while let Some(job) = queue.next_job(settings.max_size_jobs) {
+        spawn_size_job(job, &rules, channel.sender.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(queue: &SizeJobQueue, path: &str) -> SizeResult {
+        SizeResult {
+            path: PathBuf::from(path),
+            usage: Default::default(),
+            stats: None,
+            fingerprint: 0,
+            batch: queue.batch,
+            stamp: None,
+            errors: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_concurrency_limit_and_priority() {
+        let mut queue = SizeJobQueue::default();
+        let paths = ["/r/a", "/r/b", "/r/c"].map(PathBuf::from).to_vec();
+        queue.enqueue(Path::new("/r"), paths);
+        queue.prioritize(Path::new("/r/c"));
+
+        assert_eq!(queue.next_job(2).unwrap().path, PathBuf::from("/r/c"));
+        assert_eq!(queue.next_job(2).unwrap().path, PathBuf::from("/r/a"));
+        assert!(queue.next_job(2).is_none());
+
+        assert!(queue.complete(&result(&queue, "/r/c")));
+        assert_eq!(queue.next_job(2).unwrap().path, PathBuf::from("/r/b"));
+    }
+
+    #[test]
+    fn test_new_directory_cancels_batch() {
+        let mut queue = SizeJobQueue::default();
+        queue.enqueue(Path::new("/r"), vec![PathBuf::from("/r/a")]);
+        let job = queue.next_job(4).unwrap();
+        let stale = result(&queue, "/r/a");
+
+        queue.enqueue(Path::new("/s"), vec![PathBuf::from("/s/x")]);
+
+        assert!(job.cancel.is_cancelled());
+        assert!(!queue.complete(&stale));
+        assert_eq!(queue.running(), 0);
+        assert_eq!(queue.queued(), 1);
+    }
+
+    #[test]
+    fn test_requeue_reruns_running_job() {
+        let mut queue = SizeJobQueue::default();
+        queue.enqueue(Path::new("/r"), vec![PathBuf::from("/r/a")]);
+        queue.next_job(4).unwrap();
+
+        queue.requeue(Path::new("/r"), vec![PathBuf::from("/r/a"), PathBuf::from("/r/b")]);
+        assert_eq!(queue.queued(), 1);
+
+        // The result predates the change: dropped, and the job queued again
+        assert!(!queue.complete(&result(&queue, "/r/a")));
+        assert_eq!(queue.running(), 0);
+        assert_eq!(queue.queued(), 2);
+    }
+
+    #[test]
+    fn test_stats_pass_runs_after_size_jobs() {
+        let mut queue = SizeJobQueue::default();
+        let paths = ["/r/a", "/r/b"].map(PathBuf::from).to_vec();
+        queue.enqueue(Path::new("/r"), paths);
+        let job = queue.next_job(1).unwrap();
+        assert!(!job.needs_stats);
+
+        // /r/a was sized without stats: its walk waits for /r/b's size
+        assert!(queue.complete(&result(&queue, "/r/a")));
+        queue.enqueue_stats(Path::new("/r/a"));
+        let job = queue.next_job(1).unwrap();
+        assert_eq!(job.path, PathBuf::from("/r/b"));
+        assert!(!job.needs_stats);
+        assert!(queue.complete(&result(&queue, "/r/b")));
+
+        let job = queue.next_job(1).unwrap();
+        assert_eq!(job.path, PathBuf::from("/r/a"));
+        assert!(job.needs_stats);
+        assert!(queue.complete(&result(&queue, "/r/a")));
+        assert!(queue.is_idle());
+    }
+}
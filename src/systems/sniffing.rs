@@ -0,0 +1,115 @@
+//! Content sniffing systems
+//!
+//! File planets that weren't classified by a name glob get their first
+//! bytes read on the IO task pool. A recognized signature refines the
+//! category and material once the read finishes.
+
+use crate::components::*;
+use crate::events::RespawnCelestialsEvent;
+use crate::resources::*;
+use crate::states::AppState;
+use crate::utils::{celestial_emissive, read_header, sniff, Sniffed};
+use bevy::prelude::*;
+use bevy::tasks::{IoTaskPool, Task};
+use futures_lite::future;
+
+/// Header read of a file planet in progress
+#[derive(Component)]
+pub struct ContentSniff(Task<Option<Sniffed>>);
+
+/// System: Start reading the headers of newly spawned file planets
+pub fn start_content_sniffing(
+    mut commands: Commands,
+    settings: Res<SidebarSettings>,
+    planets: Query<(Entity, &CelestialBody, &TypeSource), Added<TypeSource>>,
+) {
+    if !settings.sniff_file_contents {
+        return;
+    }
+    let pool = IoTaskPool::get();
+    for (entity, body, source) in planets.iter() {
+        // A glob names the file explicitly
+        if *source == TypeSource::Name {
+            continue;
+        }
+        let path = body.path.clone();
+        let task = pool.spawn(async move { read_header(&path).ok().and_then(|h| sniff(&h)) });
+        commands.entity(entity).insert(ContentSniff(task));
+    }
+}
+
+/// File planets with a header read in progress
+type SniffingPlanets<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static mut ContentSniff,
+        &'static mut FileType,
+        &'static mut TypeSource,
+        &'static Brightness,
+        &'static MeshMaterial3d<StandardMaterial>,
+    ),
+>;
+
+/// System: Re-classify file planets whose header read finished
+pub fn apply_content_sniffing(
+    mut commands: Commands,
+    config: Res<VisualConfig>,
+    mut planets: SniffingPlanets,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for (entity, mut read, mut file_type, mut source, brightness, material_handle) in
+        planets.iter_mut()
+    {
+        let Some(result) = future::block_on(future::poll_once(&mut read.0)) else {
+            continue;
+        };
+        commands.entity(entity).remove::<ContentSniff>();
+
+        let Some(sniffed) = result else {
+            continue;
+        };
+        // Weak signatures only classify what the extension didn't
+        if !sniffed.strong && *source != TypeSource::Unmatched {
+            continue;
+        }
+        // The category may have been removed from closm-probe.toml
+        let Some(category) = config.categories.find(sniffed.category) else {
+            continue;
+        };
+        *source = TypeSource::Content(sniffed.format);
+        if *file_type == FileType::File(category) {
+            continue;
+        }
+        *file_type = FileType::File(category);
+        if let Some(material) = materials.get_mut(&material_handle.0) {
+            material.base_color = config.color(*file_type);
+            material.emissive = celestial_emissive(*file_type, brightness.value, &config);
+        }
+    }
+}
+
+/// System: Respawn the current system when content sniffing is switched on or off
+pub fn apply_sniff_setting(
+    mut commands: Commands,
+    settings: Res<SidebarSettings>,
+    mut last_enabled: Local<Option<bool>>,
+    app_state: Res<State<AppState>>,
+    celestials: Query<Entity, With<CelestialBody>>,
+    asteroid_belts: Query<Entity, With<AsteroidBelt>>,
+    mut respawn_events: EventWriter<RespawnCelestialsEvent>,
+) {
+    let previous = last_enabled.replace(settings.sniff_file_contents);
+    if previous.is_none_or(|enabled| enabled == settings.sniff_file_contents) {
+        return;
+    }
+
+    if *app_state.get() != AppState::Viewing {
+        return;
+    }
+    for entity in celestials.iter().chain(asteroid_belts.iter()) {
+        commands.entity(entity).despawn_recursive();
+    }
+    respawn_events.send(RespawnCelestialsEvent);
+}
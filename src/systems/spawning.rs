@@ -8,48 +8,111 @@ use crate::bundles::*;
 use crate::components::*;
 use crate::events::RespawnCelestialsEvent;
 use crate::resources::*;
-use crate::systems::filesystem::{count_directory_items, read_directory};
-use crate::systems::size_calculation::{spawn_size_calculations, SizeCalculationChannel};
+use crate::states::AppState;
+use crate::systems::camera::CameraAnimation;
+use crate::systems::filesystem::request_directory;
+use crate::systems::size_calculation::SizeJobQueue;
 use crate::utils::*;
 use bevy::prelude::*;
+use std::path::{Path, PathBuf};
 
-/// Spawn celestial bodies for the current directory
+/// System: Spawn celestial bodies once the current directory's listing is ready.
+/// Waits for camera animations to finish unless they reveal the new system.
 pub fn spawn_celestials(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
     current_dir: Res<CurrentDirectory>,
-    mut cache: ResMut<DirectoryCache>,
+    mut load: ResMut<DirectoryLoad>,
+    index: Res<TreeIndex>,
+    rules: Res<ScanRules>,
     config: Res<VisualConfig>,
-    size_channel: Res<SizeCalculationChannel>,
+    mut size_queue: ResMut<SizeJobQueue>,
     persistent_cache: Option<Res<PersistentCache>>,
+    animations: Query<&CameraAnimation>,
 ) {
+    if animations.iter().any(|a| !a.reveal) {
+        return;
+    }
     let Some(path) = &current_dir.path else {
         return;
     };
+    // Listings for directories we already left are never taken
+    let Some(listing) = load.take_ready(path) else {
+        return;
+    };
 
-    // Read directory contents
-    let entries = read_directory(path, &mut cache);
+    let spawned = spawn_system(
+        &mut commands,
+        &mut meshes,
+        &mut materials,
+        &listing,
+        &index,
+        &rules,
+        &config,
+        &mut size_queue,
+        persistent_cache.as_deref(),
+    );
+
+    info!("Spawned {} celestials for {}", spawned, path.display());
+}
+
+/// Spawn star, planets and asteroid belt for a directory listing.
+/// Returns the number of spawned celestials (including the star).
+#[allow(clippy::too_many_arguments)]
+fn spawn_system(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    listing: &DirectoryListing,
+    index: &TreeIndex,
+    rules: &ScanRules,
+    config: &VisualConfig,
+    size_queue: &mut SizeJobQueue,
+    persistent_cache: Option<&PersistentCache>,
+) -> usize {
+    let path = &listing.path;
+    let entries = &listing.entries;
+
+    // Lets the persistent cache roll this directory's total up from its children
+    if let Some(persistent_cache) = persistent_cache {
+        persistent_cache.write_manifest(path, entries, rules);
+    }
 
     // Spawn the central star
-    let star_mesh = create_sphere_mesh(config.star_size, &mut meshes);
-    let star_material = create_star_material(&mut materials);
+    let star_mesh = create_sphere_mesh(config.star_size, meshes);
+    let star_material = create_star_material(materials);
 
     let star_name = path
         .file_name()
         .map(|n| n.to_string_lossy().to_string())
         .unwrap_or_else(|| "/".to_string());
 
+    // Total size comes from the same sources as planets; unknown totals are
+    // added up from the planets and the belt once they are sized
+    // (`derive_star_size`), rather than walking the subtree a second time
+    let star_size = known_directory_size(path, index, &listing.cached);
+    let star_scale = star_size.map_or(1.0, |usage| {
+        calculate_star_scale(usage.get(config.size_mode), config)
+    });
+
     let star_entity = commands
         .spawn(StarBundle::new(
             star_name,
             path.clone(),
-            0, // Size not calculated for current dir
-            std::time::UNIX_EPOCH,
+            star_size.unwrap_or_default(),
+            listing.modified,
+            star_scale,
             star_mesh,
             star_material,
         ))
         .id();
+    if star_size.is_none() {
+        commands.entity(star_entity).insert(PendingSizeCalculation);
+    }
+    if let Some(stats) = known_directory_stats(path, index, &listing.cached) {
+        commands.entity(star_entity).insert(Subtree { stats });
+    }
 
     // Add point light to star
     commands.entity(star_entity).with_children(|parent| {
@@ -72,102 +135,291 @@ pub fn spawn_celestials(
     let mut pending_calculations = Vec::new();
 
     // Spawn planets for each entry
-    for (index, entry) in display_entries.iter().enumerate() {
-        let position = calculate_orbital_position(index, total_display, ORBIT_RADIUS);
-        let brightness = calculate_brightness(entry.modified);
-
-        if entry.is_directory {
-            // Check persistent cache for pre-calculated size
-            let cached_size = persistent_cache
-                .as_ref()
-                .and_then(|pc| pc.get_size(&entry.path));
-
-            let (effective_size_bytes, has_cached_size) = match cached_size {
-                Some(size) => (size, true),
-                None => (entry.size_bytes, false),
-            };
-
-            // Directory planet (unit sphere, sized via transform.scale)
-            let size = calculate_size(effective_size_bytes, true, &config);
-            let mesh = create_sphere_mesh(1.0, &mut meshes);
-            let material = create_celestial_material(
-                FileType::Directory,
-                brightness.value,
-                &mut materials,
-            );
-
-            let bundle = DirectoryPlanetBundle::new(
-                entry.name.clone(),
-                entry.path.clone(),
-                effective_size_bytes,
-                entry.modified,
-                brightness,
-                position,
-                size,
-                mesh,
-                material,
-            );
-
-            let planet_entity = if has_cached_size {
-                // Cached: spawn at final size, no pulse animation
-                commands.spawn(bundle).id()
-            } else {
-                // Uncached: spawn with pulse animation, queue calculation
-                commands
-                    .spawn((bundle, PulseAnimation::default(), PendingSizeCalculation))
-                    .id()
-            };
-
-            if !has_cached_size {
+    for (index_in_orbit, entry) in display_entries.iter().enumerate() {
+        let position = calculate_orbital_position(index_in_orbit, total_display, config.orbit_radius);
+        spawn_planet(
+            commands,
+            meshes,
+            materials,
+            entry,
+            position,
+            index,
+            rules,
+            config,
+            &listing.cached,
+            &mut pending_calculations,
+        );
+    }
+
+    // Spawn asteroid belt for overflow items; its directories are sized after the planets
+    if overflow_count > 0 {
+        let belt = fold_overflow(entries, index, rules, config, &listing.cached);
+        pending_calculations.extend(unsized_directories(&belt));
+        commands.spawn((belt, Transform::default()));
+        info!("Overflow: {} items in asteroid belt", overflow_count);
+    }
+
+    // Queue background size calculations (replaces jobs for other directories)
+    size_queue.enqueue(path, pending_calculations);
+
+    display_entries.len() + 1 // +1 for star
+}
+
+/// Spawn the planet for a single entry at `position`, returning its entity.
+/// Directories without a known size are pushed onto `pending_calculations`.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn spawn_planet(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    entry: &FileEntry,
+    position: Vec3,
+    index: &TreeIndex,
+    rules: &ScanRules,
+    config: &VisualConfig,
+    cached: &CachedSizes,
+    pending_calculations: &mut Vec<PathBuf>,
+) -> Entity {
+    let stats = entry
+        .is_drillable(rules.follow_symlinks)
+        .then(|| known_directory_stats(&entry.path, index, cached))
+        .flatten();
+    // Directories glow with the most recent activity anywhere below them
+    let brightness = calculate_brightness(
+        stats.as_ref().map_or(entry.modified, |s| s.newest_modified),
+        config,
+    );
+
+    if let Some(link) = &entry.symlink {
+        // Symlink planet (torus); directory links act like directories when followed
+        let traversable = entry.is_drillable(rules.follow_symlinks);
+        let known_size = if traversable {
+            known_directory_size(&entry.path, index, cached)
+        } else {
+            Some(entry.usage)
+        };
+        let usage = known_size.unwrap_or(entry.usage);
+
+        let size = calculate_size(usage.get(config.size_mode), traversable, config);
+        let mesh = create_torus_mesh(meshes);
+        let material = if link.broken {
+            create_broken_link_material(materials)
+        } else {
+            create_celestial_material(FileType::Symlink, brightness.value, config, materials)
+        };
+
+        let mut planet = commands.spawn(SymlinkPlanetBundle::new(
+            entry.name.clone(),
+            entry.path.clone(),
+            usage,
+            entry.modified,
+            link.clone(),
+            traversable,
+            brightness,
+            position,
+            size,
+            mesh,
+            material,
+        ));
+
+        if let Some(stats) = stats {
+            planet.insert(Subtree { stats });
+        }
+        if traversable {
+            planet.insert(Drillable);
+            if known_size.is_none() {
                 pending_calculations.push(entry.path.clone());
+                planet.insert((PulseAnimation::default(), PendingSizeCalculation));
             }
-
-            // Check for grandchildren and add ring if any
-            let grandchild_count = count_directory_items(&entry.path);
+            let grandchild_count = entry.child_count.unwrap_or(0);
             if grandchild_count > 0 {
-                commands.entity(planet_entity).insert(GrandchildRing {
+                planet.insert(GrandchildRing {
                     count: grandchild_count,
                 });
             }
+        }
+        planet.id()
+    } else if entry.is_directory {
+        let known_size = known_directory_size(&entry.path, index, cached);
+
+        let (usage, has_known_size) = match known_size {
+            Some(usage) => (usage, true),
+            None => (entry.usage, false),
+        };
+
+        // Directory planet (unit sphere, sized via transform.scale)
+        let size = calculate_size(usage.get(config.size_mode), true, config);
+        let mesh = create_sphere_mesh(1.0, meshes);
+        let material =
+            create_celestial_material(FileType::Directory, brightness.value, config, materials);
+
+        let bundle = DirectoryPlanetBundle::new(
+            entry.name.clone(),
+            entry.path.clone(),
+            usage,
+            entry.modified,
+            brightness,
+            position,
+            size,
+            mesh,
+            material,
+        );
+
+        let planet_entity = if has_known_size {
+            // Known: spawn at final size, no pulse animation
+            commands.spawn(bundle).id()
         } else {
-            // File planet (octahedron)
-            let file_type =
-                FileType::from_extension(entry.path.extension().and_then(|e| e.to_str()));
-            let size = calculate_size(entry.size_bytes, false, &config);
-            let mesh = create_octahedron_mesh(size, &mut meshes);
-            let material =
-                create_celestial_material(file_type, brightness.value, &mut materials);
-
-            commands.spawn(FilePlanetBundle::new(
+            // Unknown: spawn with pulse animation, queue calculation
+            pending_calculations.push(entry.path.clone());
+            commands
+                .spawn((bundle, PulseAnimation::default(), PendingSizeCalculation))
+                .id()
+        };
+
+        if let Some(stats) = stats {
+            commands.entity(planet_entity).insert(Subtree { stats });
+        }
+
+        // Check for grandchildren and add ring if any
+        let grandchild_count = entry.child_count.unwrap_or(0);
+        if grandchild_count > 0 {
+            commands.entity(planet_entity).insert(GrandchildRing {
+                count: grandchild_count,
+            });
+        }
+        planet_entity
+    } else {
+        // File planet (octahedron)
+        let (category, type_source) = config.categories.classify(&entry.name);
+        let file_type = FileType::File(category);
+        let size = calculate_size(entry.usage.get(config.size_mode), false, config);
+        let mesh = create_octahedron_mesh(size, meshes);
+        let material = create_celestial_material(file_type, brightness.value, config, materials);
+
+        commands
+            .spawn(FilePlanetBundle::new(
                 entry.name.clone(),
                 entry.path.clone(),
-                entry.size_bytes,
+                entry.usage,
                 entry.modified,
                 file_type,
+                type_source,
                 brightness,
                 position,
                 mesh,
                 material,
-            ));
+            ))
+            .id()
+    }
+}
+
+/// Asteroid belt for the entries beyond `max_display_items`, with their known sizes
+pub(crate) fn fold_overflow(
+    entries: &[FileEntry],
+    index: &TreeIndex,
+    rules: &ScanRules,
+    config: &VisualConfig,
+    cached: &CachedSizes,
+) -> AsteroidBelt {
+    let mut belt = AsteroidBelt::default();
+    for entry in entries.iter().skip(config.max_display_items) {
+        belt.count += 1;
+        if entry.is_drillable(rules.follow_symlinks) {
+            let size = known_directory_size(&entry.path, index, cached);
+            belt.directories.insert(entry.path.clone(), size);
+        } else {
+            belt.files += entry.usage;
         }
     }
+    belt
+}
+
+/// Belt directories whose size still has to be calculated
+pub(crate) fn unsized_directories(belt: &AsteroidBelt) -> impl Iterator<Item = PathBuf> + '_ {
+    belt.directories
+        .iter()
+        .filter(|(_, size)| size.is_none())
+        .map(|(path, _)| path.clone())
+}
 
-    // Spawn background size calculations
-    if !pending_calculations.is_empty() {
-        spawn_size_calculations(pending_calculations, size_channel.sender.clone());
+/// Tree index first, then the listing's persisted sizes for pre-calculated size
+fn known_directory_size(path: &Path, index: &TreeIndex, cached: &CachedSizes) -> Option<DiskUsage> {
+    index.directory_size(path).or_else(|| cached.size(path))
+}
+
+/// Tree index first, then the listing's persisted sizes for file/folder counts
+fn known_directory_stats(
+    path: &Path,
+    index: &TreeIndex,
+    cached: &CachedSizes,
+) -> Option<SubtreeStats> {
+    index.directory_stats(path).or_else(|| cached.stats(path))
+}
+
+/// System: Respawn the current system when the size mode changes
+///
+/// Both sizes are already known, so this only re-scales (file meshes bake
+/// their size in, hence a respawn rather than a transform update).
+pub fn apply_size_mode(
+    mut commands: Commands,
+    config: Res<VisualConfig>,
+    mut last_mode: Local<Option<SizeMode>>,
+    app_state: Res<State<AppState>>,
+    scene_entities: Query<Entity, SceneEntities>,
+    mut respawn_events: EventWriter<RespawnCelestialsEvent>,
+) {
+    let previous = last_mode.replace(config.size_mode);
+    if previous.is_none_or(|mode| mode == config.size_mode) {
+        return;
     }
 
-    // Spawn asteroid belt for overflow items
-    if overflow_count > 0 {
-        commands.spawn((AsteroidBelt { count: overflow_count }, Transform::default()));
-        info!("Overflow: {} items in asteroid belt", overflow_count);
+    info!("Size mode changed to {:?}", config.size_mode);
+
+    if *app_state.get() != AppState::Viewing {
+        return;
     }
+    for entity in scene_entities.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    respawn_events.send(RespawnCelestialsEvent);
+}
 
-    info!(
-        "Spawned {} celestials for {}",
-        display_entries.len() + 1, // +1 for star
-        path.display()
-    );
+/// System: Re-apply `closm-probe.toml` when it changes and respawn the
+/// current system with the new materials and layout
+#[allow(clippy::too_many_arguments)]
+pub fn reload_visual_encoding(
+    mut commands: Commands,
+    mut encoding_file: ResMut<EncodingFile>,
+    mut config: ResMut<VisualConfig>,
+    mut cache: ResMut<DirectoryCache>,
+    app_state: Res<State<AppState>>,
+    celestials: Query<Entity, With<CelestialBody>>,
+    asteroid_belts: Query<Entity, With<AsteroidBelt>>,
+    mut respawn_events: EventWriter<RespawnCelestialsEvent>,
+) {
+    if !encoding_file.poll_changed() {
+        return;
+    }
+
+    let max_display_items = config.max_display_items;
+    if let Err(e) = encoding_file.load(&mut config) {
+        warn!("Keeping the current visual encoding: {}", e);
+        return;
+    }
+    info!("Visual encoding reloaded");
+
+    // Listings only count grandchildren of the displayed directories
+    if config.max_display_items != max_display_items {
+        cache.clear();
+    }
+
+    if *app_state.get() != AppState::Viewing {
+        return;
+    }
+    for entity in celestials.iter().chain(asteroid_belts.iter()) {
+        commands.entity(entity).despawn_recursive();
+    }
+    respawn_events.send(RespawnCelestialsEvent);
 }
 
 /// Despawn all celestial bodies
@@ -184,16 +436,18 @@ pub fn despawn_celestials(
     }
 }
 
-/// Handle respawn event - spawns celestials when navigation occurs
+/// Handle respawn event - requests the current directory's listing
+///
+/// `spawn_celestials` spawns it once ready (immediately for indexed or
+/// cached directories).
 pub fn handle_respawn_celestials(
     mut events: EventReader<RespawnCelestialsEvent>,
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
     current_dir: Res<CurrentDirectory>,
+    mut load: ResMut<DirectoryLoad>,
     mut cache: ResMut<DirectoryCache>,
+    index: Res<TreeIndex>,
+    rules: Res<ScanRules>,
     config: Res<VisualConfig>,
-    size_channel: Res<SizeCalculationChannel>,
     persistent_cache: Option<Res<PersistentCache>>,
 ) {
     // Only process if there's an event
@@ -208,134 +462,13 @@ pub fn handle_respawn_celestials(
         return;
     };
 
-    // Read directory contents
-    let entries = read_directory(path, &mut cache);
-
-    // Spawn the central star
-    let star_mesh = create_sphere_mesh(config.star_size, &mut meshes);
-    let star_material = create_star_material(&mut materials);
-
-    let star_name = path
-        .file_name()
-        .map(|n| n.to_string_lossy().to_string())
-        .unwrap_or_else(|| "/".to_string());
-
-    let star_entity = commands
-        .spawn(StarBundle::new(
-            star_name,
-            path.clone(),
-            0,
-            std::time::UNIX_EPOCH,
-            star_mesh,
-            star_material,
-        ))
-        .id();
-
-    // Add point light to star
-    commands.entity(star_entity).with_children(|parent| {
-        parent.spawn((
-            PointLight {
-                intensity: 2_000_000.0,
-                shadows_enabled: true,
-                ..default()
-            },
-            Transform::default(),
-        ));
-    });
-
-    // Limit items to max display count
-    let display_entries: Vec<_> = entries.iter().take(config.max_display_items).collect();
-    let overflow_count = entries.len().saturating_sub(config.max_display_items);
-    let total_display = display_entries.len();
-
-    // Collect directory paths for size calculation
-    let mut pending_calculations = Vec::new();
-
-    // Spawn planets for each entry
-    for (index, entry) in display_entries.iter().enumerate() {
-        let position = calculate_orbital_position(index, total_display, ORBIT_RADIUS);
-        let brightness = calculate_brightness(entry.modified);
-
-        if entry.is_directory {
-            // Check persistent cache for pre-calculated size
-            let cached_size = persistent_cache
-                .as_ref()
-                .and_then(|pc| pc.get_size(&entry.path));
-
-            let (effective_size_bytes, has_cached_size) = match cached_size {
-                Some(size) => (size, true),
-                None => (entry.size_bytes, false),
-            };
-
-            let size = calculate_size(effective_size_bytes, true, &config);
-            let mesh = create_sphere_mesh(1.0, &mut meshes);
-            let material =
-                create_celestial_material(FileType::Directory, brightness.value, &mut materials);
-
-            let bundle = DirectoryPlanetBundle::new(
-                entry.name.clone(),
-                entry.path.clone(),
-                effective_size_bytes,
-                entry.modified,
-                brightness,
-                position,
-                size,
-                mesh,
-                material,
-            );
-
-            let planet_entity = if has_cached_size {
-                commands.spawn(bundle).id()
-            } else {
-                commands
-                    .spawn((bundle, PulseAnimation::default(), PendingSizeCalculation))
-                    .id()
-            };
-
-            if !has_cached_size {
-                pending_calculations.push(entry.path.clone());
-            }
-
-            let grandchild_count = count_directory_items(&entry.path);
-            if grandchild_count > 0 {
-                commands.entity(planet_entity).insert(GrandchildRing {
-                    count: grandchild_count,
-                });
-            }
-        } else {
-            let file_type =
-                FileType::from_extension(entry.path.extension().and_then(|e| e.to_str()));
-            let size = calculate_size(entry.size_bytes, false, &config);
-            let mesh = create_octahedron_mesh(size, &mut meshes);
-            let material =
-                create_celestial_material(file_type, brightness.value, &mut materials);
-
-            commands.spawn(FilePlanetBundle::new(
-                entry.name.clone(),
-                entry.path.clone(),
-                entry.size_bytes,
-                entry.modified,
-                file_type,
-                brightness,
-                position,
-                mesh,
-                material,
-            ));
-        }
-    }
-
-    // Spawn background size calculations
-    if !pending_calculations.is_empty() {
-        spawn_size_calculations(pending_calculations, size_channel.sender.clone());
-    }
-
-    if overflow_count > 0 {
-        commands.spawn((AsteroidBelt { count: overflow_count }, Transform::default()));
-    }
-
-    info!(
-        "Respawned {} celestials for {}",
-        display_entries.len() + 1,
-        path.display()
+    request_directory(
+        path,
+        &mut load,
+        &mut cache,
+        &index,
+        &rules,
+        config.max_display_items,
+        persistent_cache.as_deref(),
     );
 }
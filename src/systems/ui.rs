@@ -9,10 +9,15 @@ use crate::components::*;
 use crate::events::*;
 use crate::resources::*;
 use crate::states::*;
+use crate::systems::filesystem::request_directory;
+use crate::systems::interaction::Navigation;
+use crate::systems::size_calculation::SizeJobQueue;
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 use bevy::tasks::IoTaskPool;
 use bevy_egui::{egui, EguiContexts};
 use futures_lite::future;
+use std::collections::BTreeMap;
 use std::path::Path;
 
 /// Embedded font: Noto Sans JP (supports Japanese, CJK)
@@ -55,6 +60,7 @@ fn sidebar_header_bg() -> egui::Color32 {
     egui::Color32::from_rgba_unmultiplied(35, 35, 55, 240)
 }
 const ACCENT_COLOR: egui::Color32 = egui::Color32::from_rgb(100, 180, 255);
+const WARNING_COLOR: egui::Color32 = egui::Color32::from_rgb(251, 191, 36);
 const FOOTER_HEIGHT: f32 = 44.0;
 
 // ── Shared sidebar components ──
@@ -120,13 +126,13 @@ fn render_open_folder_button(
 
 fn render_history_entries<F>(
     ui: &mut egui::Ui,
-    history: &NavigationHistory,
+    recent: &RecentFolders,
     limit: usize,
     mut on_click: F,
 ) where
     F: FnMut(&std::path::PathBuf),
 {
-    if history.entries.is_empty() {
+    if recent.entries.is_empty() {
         ui.horizontal(|ui| {
             ui.add_space(24.0);
             ui.label(
@@ -136,7 +142,7 @@ fn render_history_entries<F>(
             );
         });
     } else {
-        for entry in history.entries.iter().take(limit) {
+        for entry in recent.entries.iter().take(limit) {
             let folder_name = entry
                 .file_name()
                 .map(|n| n.to_string_lossy().to_string())
@@ -170,6 +176,167 @@ fn render_history_entries<F>(
     }
 }
 
+/// Unreadable entries shown in the sidebar before collapsing into "N more"
+const SCAN_ERROR_LIMIT: usize = 50;
+
+fn render_scan_errors(ui: &mut egui::Ui, scan_errors: &ScanErrors, limit: usize) {
+    for error in scan_errors.iter().take(limit) {
+        ui.horizontal(|ui| {
+            ui.add_space(16.0);
+            ui.vertical(|ui| {
+                ui.label(
+                    egui::RichText::new(shorten_path(&error.path))
+                        .color(egui::Color32::from_rgb(200, 200, 220)),
+                )
+                .on_hover_text(format!("{}\n{}", error.path.display(), error.message));
+                ui.label(
+                    egui::RichText::new(error.kind.label())
+                        .color(WARNING_COLOR)
+                        .small(),
+                );
+            });
+        });
+        ui.add_space(2.0);
+    }
+    if scan_errors.len() > limit {
+        ui.horizontal(|ui| {
+            ui.add_space(24.0);
+            ui.label(
+                egui::RichText::new(format!("{} more", scan_errors.len() - limit))
+                    .color(egui::Color32::from_rgb(100, 100, 120))
+                    .italics(),
+            );
+        });
+    }
+}
+
+/// Planet color as an egui color
+fn egui_color(color: Color) -> egui::Color32 {
+    let [r, g, b, _] = color.to_srgba().to_u8_array();
+    egui::Color32::from_rgb(r, g, b)
+}
+
+/// File categories of the current system with their planet counts
+fn render_type_legend(
+    ui: &mut egui::Ui,
+    file_types: &Query<&FileType, With<Planet>>,
+    config: &VisualConfig,
+) {
+    let mut counts = BTreeMap::new();
+    for file_type in file_types.iter() {
+        if let FileType::File(category) = file_type {
+            *counts.entry(*category).or_insert(0usize) += 1;
+        }
+    }
+    for (category, count) in counts {
+        let category = config.categories.get(category);
+        ui.horizontal(|ui| {
+            ui.add_space(16.0);
+            ui.label(egui::RichText::new("●").color(egui_color(category.color)));
+            ui.label(
+                egui::RichText::new(&category.name).color(egui::Color32::from_rgb(200, 200, 220)),
+            );
+            ui.label(
+                egui::RichText::new(count.to_string())
+                    .color(egui::Color32::from_rgb(120, 120, 140))
+                    .small(),
+            );
+        });
+    }
+}
+
+/// Persistent cache problem found at startup, shown until dismissed
+fn render_cache_notice(ui: &mut egui::Ui, status: &mut CacheStatus) {
+    let (title, detail) = match &*status {
+        CacheStatus::Recovered(recovery) => (
+            "Cache was reset",
+            format!(
+                "The cache file was unusable ({}). A fresh one was created; \
+                 the old file is kept at {}.",
+                recovery.reason,
+                recovery.backup.display()
+            ),
+        ),
+        CacheStatus::Unavailable { reason } => (
+            "Cache unavailable",
+            format!("Sizes and history won't be saved ({}).", reason),
+        ),
+        CacheStatus::Ready | CacheStatus::Dismissed => return,
+    };
+
+    ui.horizontal(|ui| {
+        ui.add_space(16.0);
+        ui.vertical(|ui| {
+            ui.label(egui::RichText::new(title).color(WARNING_COLOR).strong());
+            ui.label(
+                egui::RichText::new(detail)
+                    .color(egui::Color32::from_rgb(160, 160, 180))
+                    .small(),
+            );
+            if ui.small_button("Dismiss").clicked() {
+                *status = CacheStatus::Dismissed;
+            }
+        });
+    });
+    ui.add_space(16.0);
+}
+
+/// Sizes of the current system's children: the planets (and whether their size
+/// is still being calculated) and the entries folded into the asteroid belt
+#[derive(SystemParam)]
+pub struct PlanetSizes<'w, 's> {
+    planets: Query<'w, 's, (&'static CelestialBody, Has<PendingSizeCalculation>), With<Planet>>,
+    belts: Query<'w, 's, &'static AsteroidBelt>,
+}
+
+/// Size line(s) for a celestial; the star shows its total and the planets' share of it
+fn render_usage(
+    ui: &mut egui::Ui,
+    body: &CelestialBody,
+    star: Option<bool>,
+    planets: &PlanetSizes,
+    mode: SizeMode,
+    color: egui::Color32,
+) {
+    let Some(pending) = star else {
+        ui.label(egui::RichText::new(format_usage(body.usage, mode)).color(color));
+        return;
+    };
+    if pending {
+        ui.label(egui::RichText::new("Total: calculating...").color(color));
+        return;
+    }
+    ui.label(egui::RichText::new(format!("Total: {}", format_usage(body.usage, mode))).color(color));
+    if let Some(share) = children_share(body, planets, mode) {
+        ui.label(egui::RichText::new(share).color(egui::Color32::from_rgb(160, 160, 180)));
+    }
+}
+
+/// "Children account for X%" (planets and asteroid belt; None for an empty total)
+fn children_share(star: &CelestialBody, planets: &PlanetSizes, mode: SizeMode) -> Option<String> {
+    let total = star.usage.get(mode);
+    if total == 0 {
+        return None;
+    }
+    let mut children = 0u64;
+    let mut sizing = false;
+    for (body, pending) in planets.planets.iter() {
+        children += body.usage.get(mode);
+        sizing |= pending;
+    }
+    for belt in planets.belts.iter() {
+        children += belt.usage().get(mode);
+        sizing |= belt.is_sizing();
+    }
+    // Hardlinks shared between children are counted once in the total only
+    let percent = (children as f64 / total as f64 * 100.0).min(100.0);
+    Some(if sizing {
+        format!("Children account for {:.0}% so far", percent)
+    } else {
+        format!("Children account for {:.0}%", percent)
+    })
+}
+
 /// Settings bar at the bottom of sidebar (Zone 3). Toggles MainView.
 fn render_settings_bar(ui: &mut egui::Ui, ui_state: &mut UiState) {
     let is_active = ui_state.main_view == MainView::Settings;
@@ -240,13 +407,30 @@ fn shorten_path(path: &Path) -> String {
 
 // ── Settings page (shared helper, not a system) ──
 
-/// Draw the settings page as a CentralPanel.
+/// Everything the settings page edits, as one system parameter
+#[derive(SystemParam)]
+pub struct SettingsPage<'w> {
+    sidebar_settings: ResMut<'w, SidebarSettings>,
+    theme_config: ResMut<'w, ThemeConfig>,
+    visual_config: ResMut<'w, VisualConfig>,
+    cache_management: ResMut<'w, CacheManagement>,
+    persistent_cache: Option<Res<'w, PersistentCache>>,
+}
+
+/// Draw the settings page as a CentralPanel (`save_user_settings` writes the changes).
 /// Called from within sidebar systems — same frame, no ordering issues.
-fn draw_settings_page(
-    ctx: &egui::Context,
-    sidebar_settings: &mut SidebarSettings,
-    theme_config: &mut ThemeConfig,
-) {
+fn draw_settings_page(ctx: &egui::Context, page: &mut SettingsPage) {
+    let SettingsPage {
+        sidebar_settings,
+        theme_config,
+        visual_config,
+        cache_management,
+        persistent_cache,
+    } = page;
+
+    // Widgets edit a copy, so the settings only count as changed when one was
+    let mut sidebar = (**sidebar_settings).clone();
+
     egui::CentralPanel::default()
         .frame(
             egui::Frame::none()
@@ -325,12 +509,12 @@ fn draw_settings_page(
                     });
                     ui.horizontal(|ui| {
                         ui.add_space(16.0);
-                        let mut limit = sidebar_settings.history_limit as f32;
+                        let mut limit = sidebar.history_limit as f32;
                         let slider = egui::Slider::new(&mut limit, 10.0..=30.0)
                             .step_by(1.0)
                             .show_value(true);
                         if ui.add(slider).changed() {
-                            sidebar_settings.history_limit = limit as usize;
+                            sidebar.history_limit = limit as usize;
                         }
                     });
 
@@ -339,14 +523,381 @@ fn draw_settings_page(
                     ui.horizontal(|ui| {
                         ui.add_space(16.0);
                         ui.checkbox(
-                            &mut sidebar_settings.show_hidden_files,
+                            &mut sidebar.show_hidden_files,
                             egui::RichText::new("Show hidden files")
                                 .color(egui::Color32::from_rgb(200, 200, 220)),
                         );
                     });
+
+                    ui.add_space(12.0);
+
+                    ui.horizontal(|ui| {
+                        ui.add_space(16.0);
+                        ui.checkbox(
+                            &mut sidebar.sniff_file_contents,
+                            egui::RichText::new("Detect file types from contents")
+                                .color(egui::Color32::from_rgb(200, 200, 220)),
+                        );
+                    });
+
+                    ui.add_space(12.0);
+
+                    ui.horizontal(|ui| {
+                        ui.add_space(16.0);
+                        ui.label(
+                            egui::RichText::new("Size")
+                                .color(egui::Color32::from_rgb(200, 200, 220)),
+                        );
+                        ui.add_space(16.0);
+                        for (mode, label) in [
+                            (SizeMode::Apparent, "Apparent"),
+                            (SizeMode::Allocated, "On disk"),
+                            (SizeMode::Both, "Both"),
+                        ] {
+                            let selected = visual_config.size_mode == mode;
+                            if ui.selectable_label(selected, label).clicked() && !selected {
+                                visual_config.size_mode = mode;
+                            }
+                        }
+                    });
+
+                    ui.add_space(28.0);
+
+                    // Filters
+                    ui.label(
+                        egui::RichText::new("Filters")
+                            .color(egui::Color32::from_rgb(160, 160, 180))
+                            .size(14.0)
+                            .strong(),
+                    );
+                    ui.add_space(12.0);
+
+                    ui.horizontal(|ui| {
+                        ui.add_space(16.0);
+                        ui.checkbox(
+                            &mut sidebar.respect_gitignore,
+                            egui::RichText::new("Respect .gitignore")
+                                .color(egui::Color32::from_rgb(200, 200, 220)),
+                        );
+                    });
+
+                    ui.add_space(12.0);
+
+                    ui.horizontal(|ui| {
+                        ui.add_space(16.0);
+                        ui.checkbox(
+                            &mut sidebar.dedup_hardlinks,
+                            egui::RichText::new("Count hardlinks once")
+                                .color(egui::Color32::from_rgb(200, 200, 220)),
+                        );
+                    });
+
+                    ui.add_space(12.0);
+
+                    ui.horizontal(|ui| {
+                        ui.add_space(16.0);
+                        ui.checkbox(
+                            &mut sidebar.one_filesystem,
+                            egui::RichText::new("Stay on one filesystem")
+                                .color(egui::Color32::from_rgb(200, 200, 220)),
+                        );
+                    });
+
+                    ui.add_space(12.0);
+
+                    ui.horizontal(|ui| {
+                        ui.add_space(16.0);
+                        ui.checkbox(
+                            &mut sidebar.follow_symlinks,
+                            egui::RichText::new("Follow directory symlinks")
+                                .color(egui::Color32::from_rgb(200, 200, 220)),
+                        );
+                    });
+
+                    ui.add_space(12.0);
+
+                    ui.horizontal(|ui| {
+                        ui.add_space(16.0);
+                        ui.label(
+                            egui::RichText::new("Exclude patterns")
+                                .color(egui::Color32::from_rgb(200, 200, 220)),
+                        );
+                    });
+                    ui.horizontal(|ui| {
+                        ui.add_space(16.0);
+                        pattern_list_editor(
+                            ui,
+                            "exclude_patterns",
+                            "node_modules, target, *.tmp",
+                            &mut sidebar.exclude_patterns,
+                        );
+                    });
+
+                    ui.add_space(12.0);
+
+                    ui.horizontal(|ui| {
+                        ui.add_space(16.0);
+                        ui.label(
+                            egui::RichText::new("Only show files matching")
+                                .color(egui::Color32::from_rgb(200, 200, 220)),
+                        );
+                    });
+                    ui.horizontal(|ui| {
+                        ui.add_space(16.0);
+                        pattern_list_editor(
+                            ui,
+                            "include_patterns",
+                            "*.rs, *.toml (empty = all files)",
+                            &mut sidebar.include_patterns,
+                        );
+                    });
+
+                    ui.add_space(28.0);
+
+                    // Performance
+                    ui.label(
+                        egui::RichText::new("Performance")
+                            .color(egui::Color32::from_rgb(160, 160, 180))
+                            .size(14.0)
+                            .strong(),
+                    );
+                    ui.add_space(12.0);
+
+                    ui.horizontal(|ui| {
+                        ui.add_space(16.0);
+                        ui.label(
+                            egui::RichText::new("Parallel size calculations")
+                                .color(egui::Color32::from_rgb(200, 200, 220)),
+                        );
+                    });
+                    ui.horizontal(|ui| {
+                        ui.add_space(16.0);
+                        let mut jobs = sidebar.max_size_jobs as f32;
+                        let slider = egui::Slider::new(&mut jobs, 1.0..=16.0)
+                            .step_by(1.0)
+                            .show_value(true);
+                        if ui.add(slider).changed() {
+                            sidebar.max_size_jobs = jobs as usize;
+                        }
+                    });
+
+                    ui.add_space(12.0);
+
+                    ui.horizontal(|ui| {
+                        ui.add_space(16.0);
+                        ui.label(
+                            egui::RichText::new("Cached sizes expire after (hours)")
+                                .color(egui::Color32::from_rgb(200, 200, 220)),
+                        );
+                    });
+                    ui.horizontal(|ui| {
+                        ui.add_space(16.0);
+                        let mut hours = sidebar.cache_ttl_hours as f32;
+                        let slider = egui::Slider::new(&mut hours, 1.0..=168.0)
+                            .step_by(1.0)
+                            .show_value(true);
+                        if ui
+                            .add_enabled(!sidebar.cache_never_expire, slider)
+                            .changed()
+                        {
+                            sidebar.cache_ttl_hours = hours as u64;
+                        }
+                    });
+
+                    ui.add_space(12.0);
+
+                    ui.horizontal(|ui| {
+                        ui.add_space(16.0);
+                        ui.checkbox(
+                            &mut sidebar.cache_never_expire,
+                            egui::RichText::new("Never expire if unchanged")
+                                .color(egui::Color32::from_rgb(200, 200, 220)),
+                        );
+                    });
+                    if sidebar.cache_never_expire {
+                        ui.horizontal(|ui| {
+                            ui.add_space(16.0);
+                            ui.label(
+                                egui::RichText::new(
+                                    "Changes in nested folders made while the app is closed \
+                                     are not detected",
+                                )
+                                .color(egui::Color32::from_rgb(160, 160, 180))
+                                .small(),
+                            );
+                        });
+                    }
+
+                    ui.add_space(12.0);
+
+                    ui.horizontal(|ui| {
+                        ui.add_space(16.0);
+                        ui.checkbox(
+                            &mut sidebar.cache_check_listing,
+                            egui::RichText::new("Also compare folder contents")
+                                .color(egui::Color32::from_rgb(200, 200, 220)),
+                        );
+                    });
+
+                    ui.add_space(28.0);
+
+                    // Storage cache
+                    ui.label(
+                        egui::RichText::new("Storage cache")
+                            .color(egui::Color32::from_rgb(160, 160, 180))
+                            .size(14.0)
+                            .strong(),
+                    );
+                    ui.add_space(12.0);
+
+                    storage_cache_section(ui, cache_management, persistent_cache.is_some());
                 });
             });
         });
+
+    sidebar_settings.set_if_neq(sidebar);
+}
+
+/// Cache file details and maintenance actions
+fn storage_cache_section(ui: &mut egui::Ui, management: &mut CacheManagement, available: bool) {
+    let text = egui::Color32::from_rgb(200, 200, 220);
+    let dim = egui::Color32::from_rgb(160, 160, 180);
+
+    if !available {
+        ui.horizontal(|ui| {
+            ui.add_space(16.0);
+            ui.label(
+                egui::RichText::new("Not available: sizes and history aren't saved")
+                    .color(dim)
+                    .italics(),
+            );
+        });
+        return;
+    }
+
+    if let Some(summary) = &management.summary {
+        let mut rows = vec![
+            ("Location", summary.path.display().to_string()),
+            ("File size", format_size(summary.file_bytes)),
+        ];
+        if !summary.shared {
+            rows.push(("Cached sizes", format_count(summary.sizes)));
+            if let (Some(oldest), Some(newest)) = (summary.oldest, summary.newest) {
+                rows.push(("Oldest entry", format_relative_time(oldest)));
+                rows.push(("Newest entry", format_relative_time(newest)));
+            }
+            rows.push(("History entries", format_count(summary.history)));
+        }
+        for (label, value) in rows {
+            ui.horizontal(|ui| {
+                ui.add_space(16.0);
+                ui.label(egui::RichText::new(label).color(text));
+                ui.add_space(16.0);
+                ui.label(egui::RichText::new(value).color(dim));
+            });
+        }
+        ui.add_space(12.0);
+    }
+
+    let shared = management.summary.as_ref().is_some_and(|s| s.shared);
+    if shared {
+        ui.horizontal(|ui| {
+            ui.add_space(16.0);
+            ui.label(
+                egui::RichText::new("Managed by another open window")
+                    .color(dim)
+                    .italics(),
+            );
+        });
+        return;
+    }
+
+    let enabled = !management.is_busy() && management.summary.is_some();
+    ui.horizontal(|ui| {
+        ui.add_space(16.0);
+        for (label, action) in [
+            ("Clear sizes", CacheMaintenance::ClearSizes),
+            ("Clear history", CacheMaintenance::ClearHistory),
+            ("Compact", CacheMaintenance::Compact),
+        ] {
+            if ui.add_enabled(enabled, egui::Button::new(label)).clicked() {
+                management.requested = Some(action);
+            }
+        }
+    });
+    ui.add_space(8.0);
+    ui.horizontal(|ui| {
+        ui.add_space(16.0);
+        if ui
+            .add_enabled(enabled, egui::Button::new("Export..."))
+            .clicked()
+        {
+            management.dialog = Some(IoTaskPool::get().spawn(async move {
+                let handle = rfd::AsyncFileDialog::new()
+                    .set_file_name("closm-probe-cache.redb")
+                    .save_file()
+                    .await;
+                handle.map(|h| CacheMaintenance::Export(h.path().to_path_buf()))
+            }));
+        }
+        if ui
+            .add_enabled(enabled, egui::Button::new("Import..."))
+            .clicked()
+        {
+            management.dialog = Some(IoTaskPool::get().spawn(async move {
+                let handle = rfd::AsyncFileDialog::new()
+                    .add_filter("Cache", &["redb"])
+                    .pick_file()
+                    .await;
+                handle.map(|h| CacheMaintenance::Import(h.path().to_path_buf()))
+            }));
+        }
+    });
+
+    if management.is_busy() {
+        ui.add_space(8.0);
+        ui.horizontal(|ui| {
+            ui.add_space(16.0);
+            ui.spinner();
+            ui.label(egui::RichText::new("Working...").color(dim).small());
+        });
+    } else if let Some(message) = &management.message {
+        ui.add_space(8.0);
+        ui.horizontal(|ui| {
+            ui.add_space(16.0);
+            ui.label(egui::RichText::new(message).color(dim).small());
+        });
+    }
+}
+
+/// Comma-separated glob list editor. Edits are buffered in egui memory
+/// and applied when the field loses focus, so separators can be typed.
+fn pattern_list_editor(
+    ui: &mut egui::Ui,
+    id_source: &str,
+    hint: &str,
+    patterns: &mut Vec<String>,
+) {
+    let id = egui::Id::new(id_source);
+    let mut text = ui
+        .data_mut(|d| d.get_temp::<String>(id))
+        .unwrap_or_else(|| patterns.join(", "));
+
+    let response = ui.add(
+        egui::TextEdit::singleline(&mut text)
+            .hint_text(hint)
+            .desired_width(320.0),
+    );
+
+    if response.lost_focus() {
+        ui.data_mut(|d| d.remove::<String>(id));
+        let parsed = parse_pattern_list(&text);
+        if parsed != *patterns {
+            *patterns = parsed;
+        }
+    } else if response.has_focus() {
+        ui.data_mut(|d| d.insert_temp(id, text));
+    }
 }
 
 // ══════════════════════════════════════════════════════
@@ -354,16 +905,16 @@ fn draw_settings_page(
 //  Each renders ALL UI for its state in one function.
 // ══════════════════════════════════════════════════════
 
-/// Empty state: Sidebar + (Settings page if active)
+/// Empty/Loading state: Sidebar + (Settings page if active)
 pub fn render_startup_ui(
     mut contexts: EguiContexts,
     mut dialog_task: ResMut<FileDialogTask>,
     layout: Res<UiLayout>,
-    history: Res<NavigationHistory>,
+    recent: Res<RecentFolders>,
     mut pending_folder: ResMut<PendingFolderSelection>,
-    mut sidebar_settings: ResMut<SidebarSettings>,
     mut ui_state: ResMut<UiState>,
-    mut theme_config: ResMut<ThemeConfig>,
+    mut settings_page: SettingsPage,
+    mut cache_status: ResMut<CacheStatus>,
 ) {
     let ctx = contexts.ctx_mut();
     let task_running = dialog_task.task.is_some();
@@ -386,12 +937,14 @@ pub fn render_startup_ui(
                 .max_height(scroll_h)
                 .auto_shrink(false)
                 .show(ui, |ui| {
+                    render_cache_notice(ui, &mut cache_status);
+
                     section_label(ui, "Recent");
                     ui.add_space(8.0);
 
-                    let limit = sidebar_settings.history_limit;
+                    let limit = settings_page.sidebar_settings.history_limit;
                     let mut clicked_path = None;
-                    render_history_entries(ui, &history, limit, |entry| {
+                    render_history_entries(ui, &recent, limit, |entry| {
                         clicked_path = Some(entry.clone());
                     });
                     if let Some(path) = clicked_path {
@@ -406,7 +959,7 @@ pub fn render_startup_ui(
 
     // ── Main Content (same frame, after sidebar) ──
     if ui_state.main_view == MainView::Settings {
-        draw_settings_page(ctx, &mut sidebar_settings, &mut theme_config);
+        draw_settings_page(ctx, &mut settings_page);
     }
 }
 
@@ -414,14 +967,19 @@ pub fn render_startup_ui(
 pub fn render_sidebar(
     mut contexts: EguiContexts,
     mut ui_state: ResMut<UiState>,
-    celestials: Query<&CelestialBody>,
+    celestials: Query<(&CelestialBody, Has<Star>, Has<PendingSizeCalculation>)>,
+    planets: PlanetSizes,
     layout: Res<UiLayout>,
-    history: Res<NavigationHistory>,
+    recent: Res<RecentFolders>,
     current_dir: Res<CurrentDirectory>,
     mut dialog_task: ResMut<FileDialogTask>,
     mut navigate_events: EventWriter<NavigateToEvent>,
-    mut sidebar_settings: ResMut<SidebarSettings>,
-    mut theme_config: ResMut<ThemeConfig>,
+    mut settings_page: SettingsPage,
+    index_task: Res<TreeIndexTask>,
+    size_queue: Res<SizeJobQueue>,
+    scan_errors: Res<ScanErrors>,
+    mut cache_status: ResMut<CacheStatus>,
+    file_types: Query<&FileType, With<Planet>>,
 ) {
     let ctx = contexts.ctx_mut();
     let task_running = dialog_task.task.is_some();
@@ -457,6 +1015,30 @@ pub fn render_sidebar(
                                 egui::RichText::new(format!(" {}", folder_name))
                                     .color(egui::Color32::WHITE),
                             );
+                            if index_task.is_running() {
+                                ui.horizontal(|ui| {
+                                    ui.spinner();
+                                    ui.label(
+                                        egui::RichText::new("Indexing...")
+                                            .color(egui::Color32::from_rgb(160, 160, 180))
+                                            .small(),
+                                    );
+                                });
+                            }
+                            if !size_queue.is_idle() {
+                                ui.horizontal(|ui| {
+                                    ui.spinner();
+                                    ui.label(
+                                        egui::RichText::new(format!(
+                                            "Sizing: {} running, {} queued",
+                                            size_queue.running(),
+                                            size_queue.queued()
+                                        ))
+                                        .color(egui::Color32::from_rgb(160, 160, 180))
+                                        .small(),
+                                    );
+                                });
+                            }
                         });
                 });
             }
@@ -469,12 +1051,14 @@ pub fn render_sidebar(
                 .max_height(scroll_h)
                 .auto_shrink(false)
                 .show(ui, |ui| {
+                    render_cache_notice(ui, &mut cache_status);
+
                     section_label(ui, "Recent");
                     ui.add_space(8.0);
 
-                    let limit = sidebar_settings.history_limit;
+                    let limit = settings_page.sidebar_settings.history_limit;
                     let mut clicked_path = None;
-                    render_history_entries(ui, &history, limit, |entry| {
+                    render_history_entries(ui, &recent, limit, |entry| {
                         clicked_path = Some(entry.clone());
                     });
                     if let Some(path) = clicked_path {
@@ -489,7 +1073,7 @@ pub fn render_sidebar(
                     ui.add_space(8.0);
 
                     if let Some(entity) = ui_state.selected_entity {
-                        if let Ok(celestial) = celestials.get(entity) {
+                        if let Ok((celestial, is_star, pending)) = celestials.get(entity) {
                             ui.horizontal(|ui| {
                                 ui.add_space(16.0);
                                 ui.vertical(|ui| {
@@ -498,9 +1082,13 @@ pub fn render_sidebar(
                                             .color(egui::Color32::WHITE)
                                             .strong(),
                                     );
-                                    ui.label(
-                                        egui::RichText::new(format_size(celestial.size_bytes))
-                                            .color(egui::Color32::from_rgb(160, 160, 180)),
+                                    render_usage(
+                                        ui,
+                                        celestial,
+                                        is_star.then_some(pending),
+                                        &planets,
+                                        settings_page.visual_config.size_mode,
+                                        egui::Color32::from_rgb(160, 160, 180),
                                     );
                                     ui.label(
                                         egui::RichText::new(format_relative_time(
@@ -521,6 +1109,20 @@ pub fn render_sidebar(
                             );
                         });
                     }
+
+                    if file_types.iter().any(|t| matches!(t, FileType::File(_))) {
+                        ui.add_space(24.0);
+                        section_label(ui, "Types");
+                        ui.add_space(8.0);
+                        render_type_legend(ui, &file_types, &settings_page.visual_config);
+                    }
+
+                    if !scan_errors.is_empty() {
+                        ui.add_space(24.0);
+                        section_label(ui, &format!("Unreadable ({})", scan_errors.len()));
+                        ui.add_space(8.0);
+                        render_scan_errors(ui, &scan_errors, SCAN_ERROR_LIMIT);
+                    }
                 });
 
             // Zone 3: Fixed Bottom
@@ -529,7 +1131,7 @@ pub fn render_sidebar(
 
     // ── Main Content (same frame, after sidebar) ──
     if ui_state.main_view == MainView::Settings {
-        draw_settings_page(ctx, &mut sidebar_settings, &mut theme_config);
+        draw_settings_page(ctx, &mut settings_page);
     }
 }
 
@@ -553,26 +1155,41 @@ pub fn poll_file_dialog(
 /// Check for pending folder selection and transition state
 pub fn check_folder_selection(
     mut pending_folder: ResMut<PendingFolderSelection>,
-    mut current_dir: ResMut<CurrentDirectory>,
-    mut breadcrumb: ResMut<Breadcrumb>,
-    mut history: ResMut<NavigationHistory>,
+    mut navigation: Navigation,
     mut next_state: ResMut<NextState<AppState>>,
     mut folder_events: EventWriter<FolderSelectedEvent>,
-    persistent_cache: Option<Res<PersistentCache>>,
     mut ui_state: ResMut<UiState>,
+    mut index: ResMut<TreeIndex>,
+    mut index_task: ResMut<TreeIndexTask>,
+    rules: Res<ScanRules>,
+    mut load: ResMut<DirectoryLoad>,
+    mut cache: ResMut<DirectoryCache>,
+    config: Res<VisualConfig>,
+    mut scan_errors: ResMut<ScanErrors>,
+    persistent_cache: Option<Res<PersistentCache>>,
 ) {
     if let Some(path) = pending_folder.path.take() {
-        current_dir.path = Some(path.clone());
-        *breadcrumb = Breadcrumb::from_path(&path);
-
-        history.push(path.clone());
-
-        if let Some(ref cache) = persistent_cache {
-            cache.write_history(&history.entries);
-        }
+        // A new session; its back stack is restored from the persistent cache
+        navigation.open(path.clone());
+
+        // One background scan per opened root; levels below it are served from the index
+        *index = TreeIndex::default();
+        scan_errors.clear();
+        index_task.start(path.clone(), rules.clone());
+
+        // Read the top level in the background; Loading → Viewing once it's ready
+        request_directory(
+            &path,
+            &mut load,
+            &mut cache,
+            &index,
+            &rules,
+            config.max_display_items,
+            persistent_cache.as_deref(),
+        );
 
         folder_events.send(FolderSelectedEvent { path: path.clone() });
-        next_state.set(AppState::Viewing);
+        next_state.set(AppState::Loading);
         ui_state.main_view = MainView::Universe;
 
         info!("Folder selected: {}", path.display());
@@ -582,12 +1199,17 @@ pub fn check_folder_selection(
 // ── Overlays (Viewing + Universe only) ──
 
 /// Render breadcrumb navigation overlay
+#[allow(clippy::too_many_arguments)]
 pub fn render_breadcrumb(
     mut contexts: EguiContexts,
     breadcrumb: Res<Breadcrumb>,
     mut navigate_events: EventWriter<NavigateToEvent>,
+    mut drill_up_events: EventWriter<DrillUpEvent>,
+    mut back_events: EventWriter<NavigateBackEvent>,
+    mut forward_events: EventWriter<NavigateForwardEvent>,
+    history: Res<NavigationHistory>,
     layout: Res<UiLayout>,
-    ui_state: Res<UiState>,
+    mut ui_state: ResMut<UiState>,
 ) {
     if ui_state.main_view != MainView::Universe {
         return;
@@ -595,6 +1217,7 @@ pub fn render_breadcrumb(
 
     let ctx = contexts.ctx_mut();
     let x_offset = layout.sidebar_width + layout.padding;
+    let ui_state = &mut *ui_state;
 
     egui::Area::new(egui::Id::new("breadcrumb"))
         .fixed_pos(egui::pos2(x_offset, layout.padding))
@@ -605,6 +1228,34 @@ pub fn render_breadcrumb(
                 .inner_margin(egui::Margin::symmetric(12.0, 8.0))
                 .show(ui, |ui| {
                     ui.horizontal(|ui| {
+                        if ui
+                            .add_enabled(!history.back.is_empty(), egui::Button::new("⬅").small())
+                            .on_hover_text("Back (Alt+Left)")
+                            .clicked()
+                        {
+                            back_events.send(NavigateBackEvent);
+                        }
+                        if ui
+                            .add_enabled(
+                                !history.forward.is_empty(),
+                                egui::Button::new("➡").small(),
+                            )
+                            .on_hover_text("Forward (Alt+Right)")
+                            .clicked()
+                        {
+                            forward_events.send(NavigateForwardEvent);
+                        }
+
+                        // Up to the parent folder, like double-clicking the star
+                        let has_parent = breadcrumb.segments.len() > 1;
+                        if ui
+                            .add_enabled(has_parent, egui::Button::new("⬆").small())
+                            .on_hover_text("Parent folder (Backspace)")
+                            .clicked()
+                        {
+                            drill_up_events.send(DrillUpEvent);
+                        }
+
                         for (i, segment) in breadcrumb.segments.iter().enumerate() {
                             if i > 0 {
                                 ui.label(
@@ -628,18 +1279,51 @@ pub fn render_breadcrumb(
                                 });
                             }
                         }
+
+                        // Typing selects the first matching planet
+                        ui.separator();
+                        let search = ui.add(
+                            egui::TextEdit::singleline(&mut ui_state.search)
+                                .hint_text("Find (/)")
+                                .desired_width(120.0),
+                        );
+                        if std::mem::take(&mut ui_state.focus_search) {
+                            search.request_focus();
+                        }
                     });
                 });
         });
 }
 
+/// Celestials the tooltip can describe
+type HoverTargets<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static CelestialBody,
+        &'static GlobalTransform,
+        Option<&'static SymlinkTarget>,
+        Option<&'static Subtree>,
+        Option<&'static FileType>,
+        Option<&'static TypeSource>,
+        Has<Star>,
+        Has<PendingSizeCalculation>,
+    ),
+>;
+
 /// Render tooltip for hovered entity
+#[allow(clippy::too_many_arguments)]
 pub fn render_tooltip(
     mut contexts: EguiContexts,
     ui_state: Res<UiState>,
-    celestials: Query<(&CelestialBody, &GlobalTransform)>,
+    celestials: HoverTargets,
+    planets: PlanetSizes,
     camera_query: Query<(&Camera, &GlobalTransform)>,
     _windows: Query<&Window>,
+    index: Res<TreeIndex>,
+    config: Res<VisualConfig>,
+    rules: Res<ScanRules>,
+    scan_errors: Res<ScanErrors>,
 ) {
     if ui_state.main_view != MainView::Universe {
         return;
@@ -648,7 +1332,9 @@ pub fn render_tooltip(
     let Some(hovered_entity) = ui_state.hovered_entity else {
         return;
     };
-    let Ok((celestial, transform)) = celestials.get(hovered_entity) else {
+    let Ok((celestial, transform, symlink, subtree, file_type, type_source, is_star, pending)) =
+        celestials.get(hovered_entity)
+    else {
         return;
     };
     let Ok((camera, camera_transform)) = camera_query.get_single() else {
@@ -680,10 +1366,84 @@ pub fn render_tooltip(
                             .color(egui::Color32::WHITE),
                     );
                     ui.add_space(4.0);
-                    ui.label(
-                        egui::RichText::new(format_size(celestial.size_bytes))
-                            .color(egui::Color32::from_rgb(180, 180, 200)),
+                    if let Some(&file_type @ FileType::File(category)) = file_type {
+                        let name = config.type_name(file_type);
+                        let text = match type_source {
+                            Some(source) => format!("{} · {}", name, source.label()),
+                            None => name.to_string(),
+                        };
+                        ui.label(
+                            egui::RichText::new(text)
+                                .color(egui_color(config.categories.get(category).color)),
+                        );
+                    }
+                    if let Some(SymlinkTarget { link }) = symlink {
+                        ui.label(
+                            egui::RichText::new(format!("→ {}", link.target.display()))
+                                .color(egui_color(config.symlink_color)),
+                        );
+                        let note = if link.broken {
+                            Some("Broken link: target not found")
+                        } else if link.loops {
+                            Some("Link points back to a parent folder")
+                        } else if link.target_is_dir && !rules.follow_symlinks {
+                            Some("Enable \"Follow directory symlinks\" to open")
+                        } else {
+                            None
+                        };
+                        if let Some(note) = note {
+                            ui.label(
+                                egui::RichText::new(note)
+                                    .color(egui::Color32::from_rgb(240, 120, 120)),
+                            );
+                        }
+                    }
+                    render_usage(
+                        ui,
+                        celestial,
+                        is_star.then_some(pending),
+                        &planets,
+                        config.size_mode,
+                        egui::Color32::from_rgb(180, 180, 200),
                     );
+                    let unreadable = scan_errors.count_below(&celestial.path);
+                    if unreadable > 0 {
+                        ui.label(
+                            egui::RichText::new(format!(
+                                "Size is a lower bound: {} {} unreadable",
+                                unreadable,
+                                if unreadable == 1 { "entry" } else { "entries" }
+                            ))
+                            .color(WARNING_COLOR),
+                        );
+                    }
+                    let stats = subtree
+                        .map(|s| s.stats.clone())
+                        .or_else(|| index.directory_stats(&celestial.path));
+                    if let Some(stats) = stats {
+                        ui.label(
+                            egui::RichText::new(format!(
+                                "{} files in {} folders",
+                                format_count(stats.files),
+                                format_count(stats.dirs)
+                            ))
+                            .color(egui::Color32::from_rgb(160, 160, 180)),
+                        );
+                        if let Some((path, len)) = &stats.largest_file {
+                            let name = path
+                                .file_name()
+                                .map(|n| n.to_string_lossy().to_string())
+                                .unwrap_or_default();
+                            ui.label(
+                                egui::RichText::new(format!(
+                                    "Largest: {} ({})",
+                                    name,
+                                    format_size(*len)
+                                ))
+                                .color(egui::Color32::from_rgb(160, 160, 180)),
+                            );
+                        }
+                    }
                     ui.label(
                         egui::RichText::new(format_relative_time(celestial.modified))
                             .color(egui::Color32::from_rgb(160, 160, 180)),
@@ -692,8 +1452,87 @@ pub fn render_tooltip(
         });
 }
 
+/// Render progress while a directory listing is read in the background
+pub fn render_loading_indicator(
+    mut contexts: EguiContexts,
+    load: Res<DirectoryLoad>,
+    layout: Res<UiLayout>,
+    ui_state: Res<UiState>,
+) {
+    if ui_state.main_view != MainView::Universe || !load.is_loading() {
+        return;
+    }
+    let Some(ref path) = load.path else {
+        return;
+    };
+
+    let ctx = contexts.ctx_mut();
+    let folder_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "/".to_string());
+
+    // Centered in the 3D viewport (right of the sidebar)
+    egui::Area::new(egui::Id::new("loading_indicator"))
+        .anchor(
+            egui::Align2::CENTER_CENTER,
+            egui::vec2(layout.sidebar_width / 2.0, 0.0),
+        )
+        .show(ctx, |ui| {
+            egui::Frame::none()
+                .fill(egui::Color32::from_rgba_unmultiplied(20, 20, 30, 230))
+                .rounding(8.0)
+                .inner_margin(egui::Margin::symmetric(16.0, 12.0))
+                .show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.vertical(|ui| {
+                            ui.label(
+                                egui::RichText::new(format!("Reading {}...", folder_name))
+                                    .color(egui::Color32::WHITE),
+                            );
+                            ui.label(
+                                egui::RichText::new(format!(
+                                    "{} entries",
+                                    load.progress.entries_read()
+                                ))
+                                .color(egui::Color32::from_rgb(160, 160, 180))
+                                .small(),
+                            );
+                        });
+                    });
+                });
+        });
+}
+
 // ── Utility functions ──
 
+/// Size label for the active size mode ("Both" shows apparent and on-disk)
+fn format_usage(usage: DiskUsage, mode: SizeMode) -> String {
+    match mode {
+        SizeMode::Apparent => format_size(usage.apparent),
+        SizeMode::Allocated => format!("{} on disk", format_size(usage.allocated)),
+        SizeMode::Both => format!(
+            "{} ({} on disk)",
+            format_size(usage.apparent),
+            format_size(usage.allocated)
+        ),
+    }
+}
+
+/// Count with thousands separators ("12,431")
+fn format_count(count: u64) -> String {
+    let digits = count.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(digit);
+    }
+    out
+}
+
 fn format_size(bytes: u64) -> String {
     const KB: u64 = 1024;
     const MB: u64 = KB * 1024;
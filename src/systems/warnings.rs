@@ -0,0 +1,55 @@
+//! Scan warning systems
+//!
+//! Mark planets whose subtree contains entries that couldn't be read.
+
+use crate::components::{CelestialBody, Planet, ScanWarning};
+use crate::resources::ScanErrors;
+use crate::utils::{create_sphere_mesh, create_warning_material};
+use bevy::prelude::*;
+
+/// Marker position above the planet (local space, so it follows planet scale)
+const MARKER_OFFSET: Vec3 = Vec3::new(0.0, 1.4, 0.0);
+
+/// Marker radius (local space)
+const MARKER_RADIUS: f32 = 0.25;
+
+/// System: Add, update or remove warning markers when errors or planets change
+pub fn mark_scan_warnings(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    scan_errors: Res<ScanErrors>,
+    new_planets: Query<(), Added<Planet>>,
+    mut planets: Query<(Entity, &CelestialBody, Option<&mut ScanWarning>), With<Planet>>,
+) {
+    if !scan_errors.is_changed() && new_planets.is_empty() {
+        return;
+    }
+
+    for (entity, body, warning) in planets.iter_mut() {
+        let count = scan_errors.count_below(&body.path);
+        match warning {
+            Some(warning) if count == 0 => {
+                commands.entity(warning.marker).despawn_recursive();
+                commands.entity(entity).remove::<ScanWarning>();
+            }
+            Some(mut warning) if warning.count != count => {
+                warning.count = count;
+            }
+            None if count > 0 => {
+                let marker = commands
+                    .spawn((
+                        Mesh3d(create_sphere_mesh(MARKER_RADIUS, &mut meshes)),
+                        MeshMaterial3d(create_warning_material(&mut materials)),
+                        Transform::from_translation(MARKER_OFFSET),
+                    ))
+                    .id();
+                commands
+                    .entity(entity)
+                    .add_child(marker)
+                    .insert(ScanWarning { count, marker });
+            }
+            Some(_) | None => {}
+        }
+    }
+}
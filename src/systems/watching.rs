@@ -0,0 +1,333 @@
+//! Filesystem watching systems
+//!
+//! Keep watches on the current directory and its child directories, and
+//! apply changes in place: invalidate caches, re-size the directories that
+//! changed, and add, remove or re-layout planets when entries come and go.
+
+use crate::components::*;
+use crate::resources::*;
+use crate::systems::filesystem::{lookup_cached_sizes, read_directory_sync};
+use crate::systems::size_calculation::SizeJobQueue;
+use crate::systems::spawning::{fold_overflow, spawn_planet, unsized_directories};
+use crate::utils::{
+    calculate_brightness, calculate_orbital_position, calculate_size, celestial_emissive,
+    create_octahedron_mesh,
+};
+use bevy::prelude::*;
+use bevy::render::primitives::Aabb;
+use bevy::tasks::IoTaskPool;
+use futures_lite::future;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// System: Point the watcher at the current directory and its child directories
+pub fn sync_fs_watches(
+    mut watcher: ResMut<FsWatcher>,
+    current_dir: Res<CurrentDirectory>,
+    new_directories: Query<&CelestialBody, (Added<Drillable>, With<Planet>)>,
+) {
+    if watcher.directory() != current_dir.path.as_deref() {
+        watcher.watch_directory(current_dir.path.as_deref());
+    }
+    let Some(directory) = watcher.directory().map(Path::to_path_buf) else {
+        return;
+    };
+    for body in new_directories.iter() {
+        if body.path.parent() == Some(directory.as_path()) {
+            watcher.watch(&body.path);
+        }
+    }
+}
+
+/// System: Apply a settled batch of changes to the caches and pending sizes
+///
+/// Only the child directories containing a change are sized again; the
+/// star's total is then added up from its children (`derive_star_size`).
+/// Entries added to or removed from the current directory trigger a
+/// re-read that `apply_fs_refresh` applies to the planets.
+#[allow(clippy::too_many_arguments)]
+pub fn handle_fs_changes(
+    mut commands: Commands,
+    mut watcher: ResMut<FsWatcher>,
+    mut cache: ResMut<DirectoryCache>,
+    mut index: ResMut<TreeIndex>,
+    persistent_cache: Option<Res<PersistentCache>>,
+    rules: Res<ScanRules>,
+    config: Res<VisualConfig>,
+    mut size_queue: ResMut<SizeJobQueue>,
+    directories: Query<(Entity, &CelestialBody, Has<Star>), With<Drillable>>,
+    mut belts: Query<&mut AsteroidBelt>,
+) {
+    watcher.drain();
+    let Some(directory) = watcher.directory().map(Path::to_path_buf) else {
+        return;
+    };
+    let Some(changed) = watcher.take_settled() else {
+        return;
+    };
+
+    let mut relist = false;
+    let mut changed_children = HashSet::new();
+    for path in &changed {
+        index.invalidate(path);
+        cache.invalidate(path);
+        if let Some(parent) = path.parent() {
+            cache.invalidate(&parent.to_path_buf());
+        }
+        // Entry added, removed or modified in the current directory itself
+        relist |= path.parent() == Some(directory.as_path());
+        // Child of the current directory the change happened in (or is)
+        if let Some(first) = path
+            .strip_prefix(&directory)
+            .ok()
+            .and_then(|relative| relative.components().next())
+        {
+            changed_children.insert(directory.join(first));
+        }
+    }
+    if let Some(ref persistent_cache) = persistent_cache {
+        persistent_cache.invalidate_sizes(changed.iter().map(PathBuf::as_path));
+    }
+
+    info!(
+        "{} changes under {} ({} directories to re-size)",
+        changed.len(),
+        directory.display(),
+        changed_children.len()
+    );
+
+    // The star waits for the re-sized children instead of walking them all again
+    let mut jobs = Vec::new();
+    for (entity, body, is_star) in directories.iter() {
+        if is_star {
+            commands.entity(entity).insert(PendingSizeCalculation);
+        } else if changed_children.contains(&body.path) {
+            commands
+                .entity(entity)
+                .insert((PulseAnimation::default(), PendingSizeCalculation));
+            jobs.push(body.path.clone());
+        }
+    }
+    for mut belt in belts.iter_mut() {
+        for (path, size) in belt.directories.iter_mut() {
+            if changed_children.contains(path) {
+                *size = None;
+                jobs.push(path.clone());
+            }
+        }
+    }
+    size_queue.requeue(&directory, jobs);
+
+    if relist {
+        // Replaces (and so cancels) a re-read still in flight
+        let rules = rules.clone();
+        let max_display_items = config.max_display_items;
+        let reader = persistent_cache.as_deref().map(PersistentCache::reader);
+        watcher.refresh = Some(IoTaskPool::get().spawn(async move {
+            let mut listing = read_directory_sync(
+                &directory,
+                &rules,
+                max_display_items,
+                &LoadProgress::default(),
+            )?;
+            if let Some(reader) = reader {
+                listing.cached = lookup_cached_sizes(&reader, &listing, &rules);
+            }
+            Some(listing)
+        }));
+    }
+}
+
+/// Planets of the system on screen, with what an in-place update touches
+type RefreshedPlanets<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static mut CelestialBody,
+        &'static Planet,
+        &'static mut Transform,
+        &'static mut Mesh3d,
+        &'static mut Brightness,
+        &'static FileType,
+        &'static MeshMaterial3d<StandardMaterial>,
+        Option<&'static SymlinkTarget>,
+    ),
+    Without<Star>,
+>;
+
+/// System: Add, remove, update and re-layout planets from a re-read of the current directory
+///
+/// Planets keep their entity (and any size still being calculated) while
+/// their entry stays the same kind; changed files get a new size and
+/// brightness in place. Only entries that became another kind (file,
+/// directory or link) are respawned.
+#[allow(clippy::too_many_arguments)]
+pub fn apply_fs_refresh(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut watcher: ResMut<FsWatcher>,
+    mut cache: ResMut<DirectoryCache>,
+    index: Res<TreeIndex>,
+    rules: Res<ScanRules>,
+    config: Res<VisualConfig>,
+    mut size_queue: ResMut<SizeJobQueue>,
+    persistent_cache: Option<Res<PersistentCache>>,
+    mut scan_errors: ResMut<ScanErrors>,
+    load: Res<DirectoryLoad>,
+    stars: Query<(Entity, &CelestialBody), With<Star>>,
+    mut planets: RefreshedPlanets,
+    mut belts: Query<(Entity, &mut AsteroidBelt)>,
+) {
+    let Some(task) = watcher.refresh.as_mut() else {
+        return;
+    };
+    let Some(result) = future::block_on(future::poll_once(task)) else {
+        return;
+    };
+    watcher.refresh = None;
+    let Some(mut listing) = result else {
+        return;
+    };
+
+    // Only patch the system that is on screen; a pending spawn reads fresh anyway
+    let Some((star, _)) = stars.iter().find(|(_, body)| body.path == listing.path) else {
+        return;
+    };
+    if load.path.as_ref() == Some(&listing.path) {
+        return;
+    }
+    // Entries came or went, so the total is added up again
+    commands.entity(star).insert(PendingSizeCalculation);
+
+    scan_errors.record(std::mem::take(&mut listing.errors));
+    cache.insert(listing.path.clone(), listing.entries.clone(), listing.modified);
+    if let Some(ref persistent_cache) = persistent_cache {
+        persistent_cache.write_manifest(&listing.path, &listing.entries, &rules);
+    }
+
+    let shown: HashMap<&Path, &FileEntry> = listing
+        .entries
+        .iter()
+        .take(config.max_display_items)
+        .map(|entry| (entry.path.as_path(), entry))
+        .collect();
+
+    // Keep planets whose entry is still shown as the same kind
+    let mut kept: HashMap<PathBuf, Entity> = HashMap::new();
+    for (
+        entity,
+        mut body,
+        planet,
+        mut transform,
+        mut mesh,
+        mut brightness,
+        file_type,
+        material,
+        link,
+    ) in planets.iter_mut()
+    {
+        let entry = shown.get(body.path.as_path());
+        let same_kind = entry.is_some_and(|entry| {
+            entry.is_drillable(rules.follow_symlinks) == planet.is_directory
+                && entry.symlink.as_ref() == link.map(|target| &target.link)
+        });
+        let Some(entry) = entry.filter(|_| same_kind) else {
+            if planet.is_directory {
+                watcher.unwatch(&body.path);
+            }
+            commands.entity(entity).despawn_recursive();
+            continue;
+        };
+        kept.insert(body.path.clone(), entity);
+
+        if planet.is_directory {
+            // Directory sizes are re-calculated separately; only refresh what the listing knows
+            body.modified = entry.modified;
+            match entry.child_count {
+                Some(count) if count > 0 => {
+                    commands.entity(entity).insert(GrandchildRing { count });
+                }
+                _ => {
+                    commands.entity(entity).remove::<GrandchildRing>();
+                }
+            }
+            continue;
+        }
+        if entry.usage == body.usage && entry.modified == body.modified {
+            continue;
+        }
+
+        // Changed file: resize and re-light the existing planet
+        body.usage = entry.usage;
+        body.modified = entry.modified;
+        *brightness = calculate_brightness(entry.modified, &config);
+        if let Some(material) = materials.get_mut(&material.0) {
+            material.emissive = celestial_emissive(*file_type, brightness.value, &config);
+        }
+        let size = calculate_size(entry.usage.get(config.size_mode), false, &config);
+        if link.is_some() {
+            transform.scale = Vec3::splat(size);
+        } else {
+            // File meshes bake their size in; the bounds are recomputed from the new mesh
+            mesh.0 = create_octahedron_mesh(size, &mut meshes);
+            commands.entity(entity).remove::<Aabb>();
+        }
+    }
+
+    // Re-layout the orbit in listing order, spawning the new entries
+    let total = shown.len();
+    let mut pending_calculations = Vec::new();
+    for (index_in_orbit, entry) in listing
+        .entries
+        .iter()
+        .take(config.max_display_items)
+        .enumerate()
+    {
+        let position = calculate_orbital_position(index_in_orbit, total, config.orbit_radius);
+        match kept.get(&entry.path) {
+            Some(&entity) => {
+                if let Ok((_, _, _, mut transform, ..)) = planets.get_mut(entity) {
+                    transform.translation = position;
+                }
+            }
+            None => {
+                spawn_planet(
+                    &mut commands,
+                    &mut meshes,
+                    &mut materials,
+                    entry,
+                    position,
+                    &index,
+                    &rules,
+                    &config,
+                    &listing.cached,
+                    &mut pending_calculations,
+                );
+            }
+        }
+    }
+
+    // Asteroid belt follows the overflow entries
+    let folded = fold_overflow(&listing.entries, &index, &rules, &config, &listing.cached);
+    pending_calculations.extend(unsized_directories(&folded));
+    match belts.iter_mut().next() {
+        Some((entity, _)) if folded.count == 0 => {
+            commands.entity(entity).despawn_recursive();
+        }
+        Some((_, mut belt)) => *belt = folded,
+        None if folded.count > 0 => {
+            commands.spawn((folded, Transform::default()));
+        }
+        None => {}
+    }
+    size_queue.requeue(&listing.path, pending_calculations);
+
+    info!(
+        "Refreshed {} in place ({} kept, {} entries)",
+        listing.path.display(),
+        kept.len(),
+        listing.entries.len()
+    );
+}
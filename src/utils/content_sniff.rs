@@ -0,0 +1,168 @@
+//! Content sniffing
+//!
+//! Recognizes common file formats from their first bytes, for files the
+//! extension doesn't classify (or misclassifies).
+
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Bytes read from the start of a file (tar's magic sits at 257)
+pub const SNIFF_BYTES: u64 = 4096;
+
+/// A recognized format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sniffed {
+    /// Format name for the tooltip
+    pub format: &'static str,
+    /// Built-in category the format belongs to
+    pub category: &'static str,
+    /// Unambiguous signature; weak ones (short magic, containers such as
+    /// ZIP behind docx/jar) only classify files the extension didn't
+    pub strong: bool,
+}
+
+/// (offset, magic, format, category, strong)
+const SIGNATURES: &[(usize, &[u8], &str, &str, bool)] = &[
+    (0, b"\x89PNG\r\n\x1a\n", "PNG", "Image", true),
+    (0, b"\xff\xd8\xff", "JPEG", "Image", true),
+    (0, b"GIF87a", "GIF", "Image", true),
+    (0, b"GIF89a", "GIF", "Image", true),
+    (0, b"II*\0", "TIFF", "Image", true),
+    (0, b"MM\0*", "TIFF", "Image", true),
+    (0, b"%PDF-", "PDF", "Document", true),
+    (0, b"SQLite format 3\0", "SQLite", "Data", true),
+    (0, b"\x1f\x8b", "gzip", "Archive", true),
+    (0, b"BZh", "bzip2", "Archive", false),
+    (0, b"\xfd7zXZ\0", "xz", "Archive", true),
+    (0, b"7z\xbc\xaf\x27\x1c", "7z", "Archive", true),
+    (0, b"Rar!\x1a\x07", "RAR", "Archive", true),
+    (257, b"ustar", "tar", "Archive", true),
+    (0, b"PK\x03\x04", "ZIP", "Archive", false),
+    (0, b"PK\x05\x06", "ZIP", "Archive", false),
+    (0, b"\x1a\x45\xdf\xa3", "Matroska", "Video", true),
+    (0, b"\x7fELF", "ELF", "Executable", true),
+    (0, b"\xfe\xed\xfa\xce", "Mach-O", "Executable", true),
+    (0, b"\xfe\xed\xfa\xcf", "Mach-O", "Executable", true),
+    (0, b"\xce\xfa\xed\xfe", "Mach-O", "Executable", true),
+    (0, b"\xcf\xfa\xed\xfe", "Mach-O", "Executable", true),
+    (0, b"MZ", "PE", "Executable", false),
+    (0, b"#!", "script", "Code", false),
+];
+
+/// Format of a file from its first bytes
+pub fn sniff(header: &[u8]) -> Option<Sniffed> {
+    // RIFF and ISO media containers are told apart by a tag after the magic
+    if header.starts_with(b"RIFF") {
+        let (format, category) = match header.get(8..12)? {
+            b"WEBP" => ("WebP", "Image"),
+            b"AVI " => ("AVI", "Video"),
+            _ => return None,
+        };
+        return Some(Sniffed {
+            format,
+            category,
+            strong: true,
+        });
+    }
+    if header.get(4..8) == Some(b"ftyp") {
+        let (format, category) = match header.get(8..12)? {
+            b"heic" | b"heix" | b"mif1" => ("HEIF", "Image"),
+            b"avif" => ("AVIF", "Image"),
+            b"M4A " => return None,
+            b"qt  " => ("QuickTime", "Video"),
+            _ => ("MP4", "Video"),
+        };
+        return Some(Sniffed {
+            format,
+            category,
+            strong: true,
+        });
+    }
+
+    SIGNATURES
+        .iter()
+        .find(|(offset, magic, ..)| header.get(*offset..offset + magic.len()) == Some(*magic))
+        .map(|&(_, _, format, category, strong)| Sniffed {
+            format,
+            category,
+            strong,
+        })
+}
+
+/// First `SNIFF_BYTES` of a regular file
+///
+/// FIFOs, devices and sockets are refused: reading them can block or have
+/// side effects. The open doesn't block on unix either, in case the path
+/// was swapped for a FIFO after the check.
+pub fn read_header(path: &Path) -> io::Result<Vec<u8>> {
+    if !std::fs::symlink_metadata(path)?.file_type().is_file() {
+        return Err(not_a_regular_file());
+    }
+
+    let mut options = std::fs::OpenOptions::new();
+    options.read(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.custom_flags(libc::O_NONBLOCK);
+    }
+    let file = options.open(path)?;
+    if !file.metadata()?.is_file() {
+        return Err(not_a_regular_file());
+    }
+
+    let mut header = Vec::with_capacity(SNIFF_BYTES as usize);
+    file.take(SNIFF_BYTES).read_to_end(&mut header)?;
+    Ok(header)
+}
+
+fn not_a_regular_file() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "not a regular file")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_dir::TestDir;
+
+    #[test]
+    fn test_sniff_signatures() {
+        let format = |header: &[u8]| sniff(header).map(|s| s.format);
+        assert_eq!(format(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), Some("PNG"));
+        assert_eq!(format(b"\x7fELF\x02\x01\x01"), Some("ELF"));
+        assert_eq!(format(b"RIFF\0\0\0\0WEBPVP8 "), Some("WebP"));
+        assert_eq!(format(b"\0\0\0\x18ftypisom"), Some("MP4"));
+        assert_eq!(format(b"\0\0\0\x18ftypheic"), Some("HEIF"));
+        assert_eq!(format(b"plain text"), None);
+        assert_eq!(format(b""), None);
+
+        let mut tar = vec![0u8; 512];
+        tar[257..262].copy_from_slice(b"ustar");
+        assert_eq!(format(&tar), Some("tar"));
+
+        // Containers behind other formats are weak
+        assert!(!sniff(b"PK\x03\x04\x14\0").unwrap().strong);
+        assert!(sniff(b"%PDF-1.7").unwrap().strong);
+    }
+
+    #[test]
+    fn test_read_header_only_reads_regular_files() {
+        let dir = TestDir::new("sniff-special");
+        std::fs::write(dir.join("image.png"), b"\x89PNG\r\n\x1a\n").unwrap();
+        assert_eq!(
+            read_header(&dir.join("image.png")).unwrap(),
+            b"\x89PNG\r\n\x1a\n"
+        );
+        assert!(read_header(dir.path()).is_err());
+
+        // Opening a FIFO for reading would wait for a writer
+        #[cfg(unix)]
+        {
+            let fifo =
+                std::ffi::CString::new(dir.join("fifo").into_os_string().into_encoded_bytes())
+                    .unwrap();
+            assert_eq!(unsafe { libc::mkfifo(fifo.as_ptr(), 0o600) }, 0);
+            assert!(read_header(&dir.join("fifo")).is_err());
+        }
+    }
+}
@@ -1,7 +1,11 @@
 //! Utility functions for CLOSM Probe
 
+pub mod content_sniff;
+#[cfg(test)]
+pub mod test_dir;
 pub mod viewport;
 pub mod visual_encoding;
 
+pub use content_sniff::*;
 pub use viewport::*;
 pub use visual_encoding::*;
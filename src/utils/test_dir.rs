@@ -0,0 +1,44 @@
+//! Temporary directories for tests
+//!
+//! Each fixture gets its own directory, removed when the guard drops (also
+//! when an assertion fails).
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// A scratch directory, deleted on drop
+pub struct TestDir {
+    path: PathBuf,
+}
+
+impl TestDir {
+    /// Create an empty directory; unique per process and call
+    pub fn new(name: &str) -> Self {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!(
+            "closm-{}-{}-{}",
+            name,
+            std::process::id(),
+            id
+        ));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn join(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.path.join(path)
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
@@ -12,7 +12,7 @@ use std::time::SystemTime;
 ///
 /// Uses band-based normalization (KB/MB/GB/TB bands) with radius-proportional mapping.
 pub fn calculate_size(size_bytes: u64, is_directory: bool, config: &VisualConfig) -> f32 {
-    let normalized = band_normalize(size_bytes);
+    let normalized = band_normalize(size_bytes, &config.size_bands);
 
     // Radius-proportional: radius = min + t * (max - min)
     // Volume scales as radius³, so visual size differences are amplified.
@@ -24,55 +24,57 @@ pub fn calculate_size(size_bytes: u64, is_directory: bool, config: &VisualConfig
     min + normalized * (max - min)
 }
 
+/// Calculate star scale from the current directory's total size
+///
+/// Same bands as planets, so a 1 GB folder's star is as large relative to
+/// its range as a 1 GB planet.
+pub fn calculate_star_scale(size_bytes: u64, config: &VisualConfig) -> f32 {
+    1.0 + band_normalize(size_bytes, &config.size_bands) * (config.star_scale_max - 1.0)
+}
+
 /// Normalize byte size using magnitude bands for perceptual differentiation.
 ///
-/// Each band (KB, MB, GB, TB) gets a proportional slice of [0, 1].
+/// Each band (KB, MB, GB, TB by default) gets a slice of [0, 1].
 /// Log-linear interpolation within each band preserves relative differences.
-fn band_normalize(size_bytes: u64) -> f32 {
-    const BANDS: &[(u64, f32)] = &[
-        (1, 0.00),                    // < 1 KB
-        (1_000, 0.10),                // 1 KB
-        (1_000_000, 0.35),            // 1 MB
-        (1_000_000_000, 0.70),        // 1 GB
-        (1_000_000_000_000, 1.00),    // 1 TB
-    ];
-
+fn band_normalize(size_bytes: u64, bands: &[(u64, f32)]) -> f32 {
     let bytes = size_bytes.max(1);
 
-    for i in 1..BANDS.len() {
-        if bytes < BANDS[i].0 {
-            let (low_bytes, low_norm) = BANDS[i - 1];
-            let (high_bytes, high_norm) = BANDS[i];
-            let log_low = (low_bytes as f64).log10();
+    for i in 1..bands.len() {
+        if bytes < bands[i].0 {
+            let (low_bytes, low_norm) = bands[i - 1];
+            let (high_bytes, high_norm) = bands[i];
+            let log_low = (low_bytes.max(1) as f64).log10();
             let log_high = (high_bytes as f64).log10();
             let log_val = (bytes as f64).log10();
-            let t = ((log_val - log_low) / (log_high - log_low)) as f32;
+            let t = ((log_val - log_low) / (log_high - log_low)).clamp(0.0, 1.0) as f32;
             return low_norm + t * (high_norm - low_norm);
         }
     }
 
-    1.0 // >= 1 TB
+    bands.last().map_or(1.0, |(_, norm)| *norm) // >= the largest band
 }
 
 /// Calculate brightness from modification time
-pub fn calculate_brightness(modified: SystemTime) -> Brightness {
+pub fn calculate_brightness(modified: SystemTime, config: &VisualConfig) -> Brightness {
     let age_secs = modified
         .elapsed()
         .map(|d| d.as_secs())
         .unwrap_or(u64::MAX);
-    Brightness::from_age_seconds(age_secs)
+    Brightness::from_age_seconds(age_secs, &config.brightness_steps, config.brightness_oldest)
 }
 
 /// Create material for celestial body
 pub fn create_celestial_material(
     file_type: FileType,
     brightness: f32,
+    config: &VisualConfig,
     materials: &mut Assets<StandardMaterial>,
 ) -> Handle<StandardMaterial> {
-    let base_color = file_type.color();
+    let base_color = config.color(file_type);
 
-    // Directories need alpha blending for pulse animation during size calculation
-    let alpha_mode = if file_type == FileType::Directory {
+    // Directories (and directory links) need alpha blending for pulse animation
+    // during size calculation
+    let alpha_mode = if matches!(file_type, FileType::Directory | FileType::Symlink) {
         AlphaMode::Blend
     } else {
         AlphaMode::Opaque
@@ -80,12 +82,63 @@ pub fn create_celestial_material(
 
     materials.add(StandardMaterial {
         base_color,
-        emissive: LinearRgba::from(base_color) * brightness * 2.0,
+        emissive: celestial_emissive(file_type, brightness, config),
         alpha_mode,
         ..default()
     })
 }
 
+/// Glow of a celestial material at the given brightness
+pub fn celestial_emissive(
+    file_type: FileType,
+    brightness: f32,
+    config: &VisualConfig,
+) -> LinearRgba {
+    LinearRgba::from(config.color(file_type)) * brightness * 2.0
+}
+
+/// Create material for a symlink whose target is missing (dim, no glow)
+pub fn create_broken_link_material(materials: &mut Assets<StandardMaterial>) -> Handle<StandardMaterial> {
+    materials.add(StandardMaterial {
+        base_color: Color::srgb_u8(0x7f, 0x1d, 0x1d), // Dark red #7f1d1d
+        ..default()
+    })
+}
+
+/// Create material for the warning marker on planets with unreadable entries
+pub fn create_warning_material(materials: &mut Assets<StandardMaterial>) -> Handle<StandardMaterial> {
+    let warning_color = Color::srgb_u8(0xfb, 0xbf, 0x24); // Amber #fbbf24
+
+    materials.add(StandardMaterial {
+        base_color: warning_color,
+        emissive: LinearRgba::from(warning_color) * 4.0,
+        unlit: true,
+        ..default()
+    })
+}
+
+/// Create material for the selection ring
+pub fn create_selection_material(materials: &mut Assets<StandardMaterial>) -> Handle<StandardMaterial> {
+    let selection_color = Color::srgb_u8(0x64, 0xb4, 0xff); // UI accent #64b4ff
+
+    materials.add(StandardMaterial {
+        base_color: selection_color,
+        emissive: LinearRgba::from(selection_color) * 3.0,
+        unlit: true,
+        ..default()
+    })
+}
+
+/// Create material for the hover glow (additive, faint)
+pub fn create_hover_material(materials: &mut Assets<StandardMaterial>) -> Handle<StandardMaterial> {
+    materials.add(StandardMaterial {
+        base_color: Color::srgba(0.6, 0.8, 1.0, 0.12),
+        alpha_mode: AlphaMode::Add,
+        unlit: true,
+        ..default()
+    })
+}
+
 /// Create emissive material for the star
 pub fn create_star_material(materials: &mut Assets<StandardMaterial>) -> Handle<StandardMaterial> {
     let star_color = Color::srgb(1.0, 0.9, 0.6); // Warm yellow
@@ -113,14 +166,26 @@ pub fn calculate_orbital_position(index: usize, total: usize, orbit_radius: f32)
     )
 }
 
-/// Orbit layout configuration
-pub const ORBIT_RADIUS: f32 = 8.0;
+/// Angle of a position around the star, increasing in layout order
+pub fn orbital_angle(position: Vec3) -> f32 {
+    position.z.atan2(position.x).rem_euclid(2.0 * PI)
+}
 
 /// Create sphere mesh for planet/star
 pub fn create_sphere_mesh(radius: f32, meshes: &mut Assets<Mesh>) -> Handle<Mesh> {
     meshes.add(Sphere::new(radius))
 }
 
+/// Create torus mesh for symlink representation (unit size, scaled via transform)
+pub fn create_torus_mesh(meshes: &mut Assets<Mesh>) -> Handle<Mesh> {
+    meshes.add(Torus::new(0.5, 1.0))
+}
+
+/// Create thin ring mesh for the selection highlight (unit radius, axis Y)
+pub fn create_highlight_ring_mesh(meshes: &mut Assets<Mesh>) -> Handle<Mesh> {
+    meshes.add(Torus::new(0.96, 1.0))
+}
+
 /// Create octahedron mesh for file representation
 pub fn create_octahedron_mesh(size: f32, meshes: &mut Assets<Mesh>) -> Handle<Mesh> {
     // Octahedron vertices: 6 points at ±x, ±y, ±z
@@ -178,3 +243,19 @@ pub fn create_octahedron_mesh(size: f32, meshes: &mut Assets<Mesh>) -> Handle<Me
 
     meshes.add(mesh)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_orbital_angle_follows_layout_order() {
+        let total = 7;
+        let angles: Vec<f32> = (0..total)
+            .map(|i| orbital_angle(calculate_orbital_position(i, total, 10.0)))
+            .collect();
+        assert!(angles[0].abs() < 1e-4);
+        assert!(angles.windows(2).all(|w| w[0] < w[1]));
+        assert!(angles[total - 1] < 2.0 * PI);
+    }
+}